@@ -19,7 +19,7 @@ pub fn main() -> Result<()> {
         .drain(..)
         .next()
         .context("Failed to find a valid USRP to attach to")?
-        .pipe(|addr| Usrp::open(&addr))
+        .pipe(Usrp::open)
         .context("Failed to find properly open the USRP")?;
 
     let _ = usrp.set_clock_source("external", 0);
@@ -30,13 +30,13 @@ pub fn main() -> Result<()> {
     let clock_source = usrp.get_clock_source(0).unwrap();
     println!("Clock source: {:?}", clock_source);
     assert_eq!(clock_source, "internal");
-        
+
     usrp.set_rx_sample_rate(1e6, CHANNEL)?;
     usrp.set_rx_antenna("TX/RX", CHANNEL)?;
     usrp.set_rx_frequency(&TuneRequest::with_frequency(2.4e9), CHANNEL)?;
 
     let mut receiver = usrp
-        .get_rx_stream(&uhd::StreamArgs::<Complex<i16>>::new("sc16"))
+        .get_rx_stream(&uhd::StreamArgs::<Complex<i16>>::new(uhd::WireFormat::Sc16))
         .unwrap();
 
     let mut buffer = uhd::alloc_boxed_slice::<Complex<i16>, NUM_SAMPLES>();
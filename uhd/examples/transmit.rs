@@ -20,7 +20,7 @@ pub fn main() -> Result<()> {
         .drain(..)
         .next()
         .context("Failed to find a valid USRP to attach to")?
-        .pipe(|addr| Usrp::open(&addr))
+        .pipe(Usrp::open)
         .context("Failed to find properly open the USRP")?;
 
     // Set properties
@@ -34,7 +34,7 @@ pub fn main() -> Result<()> {
 
     // Get TransmitStreamer
     let mut transmitter = usrp
-        .get_tx_stream(&uhd::StreamArgs::<Complex<i16>>::new("sc16"))
+        .get_tx_stream(&uhd::StreamArgs::<Complex<i16>>::new(uhd::WireFormat::Sc16))
         .unwrap();
 
     // Generate a sine wave at Fs/4
@@ -0,0 +1,519 @@
+//! Receive and transmit streams for async code
+//!
+//! [`AsyncReceiver`] is a [`Stream`] of received blocks and [`AsyncTransmitter`] is a [`Sink`] of
+//! blocks to transmit. Each one runs the blocking UHD calls on a dedicated I/O thread, so they can
+//! be used from any executor without `spawn_blocking`. Both use bounded channels: a slow consumer
+//! makes the receive thread wait (and the device eventually overflow), and a full transmit queue
+//! makes `poll_ready` return `Pending`.
+//!
+//! This module is available with the `async` feature.
+
+use std::io;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::thread;
+use std::time::Duration;
+
+use futures::channel::{mpsc, oneshot};
+use futures::executor::block_on;
+use futures::task::{self, ArcWake};
+use futures::{Future, Sink, SinkExt, Stream, StreamExt};
+
+use crate::device::{Device, ReceiveStream, TransmitStream};
+use crate::error::{Error, Result};
+use crate::receiver::capture::{CaptureOptions, IoThread, RxBlock};
+use crate::stream::{Sample, StreamArgs};
+use crate::{AsyncMetadata, TimeSpec, TransmitMetadata};
+
+/// The maximum time that the transmit thread waits for a block before checking for
+/// asynchronous messages
+const MESSAGE_POLL_INTERVAL: Duration = Duration::from_millis(10);
+/// The timeout, in seconds, for each send call on the transmit thread
+const SEND_TIMEOUT: f64 = 0.1;
+/// The timeout, in seconds, for collecting asynchronous messages after the last block
+const FINAL_MESSAGE_TIMEOUT: f64 = 0.1;
+/// The number of asynchronous messages that can wait to be received
+const MESSAGE_CAPACITY: usize = 64;
+
+/// A stream of blocks received on a dedicated I/O thread
+///
+/// The stream returns blocks in order. Receive errors that the device reports, like overflows,
+/// are attached to blocks. An error from UHD is returned as the last item of the stream.
+///
+/// Dropping an `AsyncReceiver` stops streaming without waiting for the I/O thread.
+///
+/// ```no_run
+/// # async fn run() -> Result<(), uhd::Error> {
+/// use futures::TryStreamExt;
+/// use num_complex::Complex32;
+/// use uhd::{AsyncReceiver, CaptureOptions, StreamArgs, Usrp, WireFormat};
+///
+/// let usrp = Usrp::open("")?;
+/// let args = StreamArgs::<Complex32>::new(WireFormat::Sc16);
+/// let mut blocks = AsyncReceiver::spawn_on(usrp, args, CaptureOptions::new())?;
+/// while let Some(block) = blocks.try_next().await? {
+///     println!("{} samples at {:?}", block.samples(), block.time_spec());
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct AsyncReceiver<I> {
+    blocks: mpsc::Receiver<Result<RxBlock<I>>>,
+    stop: Arc<AtomicBool>,
+}
+
+impl<I> AsyncReceiver<I>
+where
+    I: Sample + Send + 'static,
+{
+    /// Starts streaming on a streamer and receives on a new thread
+    pub fn spawn<S>(streamer: S, options: CaptureOptions) -> Result<Self>
+    where
+        S: ReceiveStream<I> + Send + 'static,
+    {
+        let stop = Arc::new(AtomicBool::new(false));
+        let (sender, blocks) = mpsc::channel(options.num_blocks());
+        let io = IoThread::start(streamer, options, Arc::clone(&stop))?;
+        thread::Builder::new()
+            .name("uhd-rx".into())
+            .spawn(move || forward_blocks(sender, |deliver| io.run(deliver)))?;
+        Ok(AsyncReceiver { blocks, stop })
+    }
+
+    /// Moves a device to a new thread, creates a receive stream, and starts streaming
+    ///
+    /// Errors from creating the stream are returned from the stream.
+    pub fn spawn_on<D>(device: D, args: StreamArgs<I>, options: CaptureOptions) -> Result<Self>
+    where
+        D: Device + Send + 'static,
+    {
+        let stop = Arc::new(AtomicBool::new(false));
+        let (sender, blocks) = mpsc::channel(options.num_blocks());
+        let io_stop = Arc::clone(&stop);
+        thread::Builder::new()
+            .name("uhd-rx".into())
+            .spawn(move || {
+                let mut device = device;
+                forward_blocks(sender, |deliver| {
+                    let streamer = device.get_rx_stream(&args)?;
+                    IoThread::start(streamer, options, io_stop)?.run(deliver)
+                })
+            })?;
+        Ok(AsyncReceiver { blocks, stop })
+    }
+}
+
+impl<I> AsyncReceiver<I> {
+    /// Stops streaming
+    ///
+    /// The stream ends after the blocks that have already been received.
+    pub fn stop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Runs `receive` and sends its blocks, and then its error, to `sender`
+fn forward_blocks<I, F>(mut sender: mpsc::Sender<Result<RxBlock<I>>>, receive: F)
+where
+    F: FnOnce(&mut dyn FnMut(RxBlock<I>) -> bool) -> Result<()>,
+{
+    let mut block_sender = sender.clone();
+    let result = receive(&mut |block| block_on(block_sender.send(Ok(block))).is_ok());
+    if let Err(e) = result {
+        let _ = block_on(sender.send(Err(e)));
+    }
+}
+
+impl<I> Stream for AsyncReceiver<I> {
+    type Item = Result<RxBlock<I>>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.blocks.poll_next_unpin(cx)
+    }
+}
+
+impl<I> Drop for AsyncReceiver<I> {
+    fn drop(&mut self) {
+        // Dropping the channel wakes the I/O thread if it is waiting for space
+        self.stop();
+    }
+}
+
+/// A block of samples to transmit on all channels of a stream
+#[derive(Debug, Clone)]
+pub struct TxBlock<I> {
+    buffers: Vec<Vec<I>>,
+    start_of_burst: bool,
+    end_of_burst: bool,
+    time_spec: Option<TimeSpec>,
+}
+
+impl<I> TxBlock<I> {
+    /// Creates a block with one buffer per channel of the stream
+    ///
+    /// The block is part of a continuous stream, with no burst flags and no time.
+    pub fn new(buffers: Vec<Vec<I>>) -> Self {
+        TxBlock {
+            buffers,
+            start_of_burst: false,
+            end_of_burst: false,
+            time_spec: None,
+        }
+    }
+
+    /// Sets whether this block starts and/or ends a burst
+    pub fn burst(self, start_of_burst: bool, end_of_burst: bool) -> Self {
+        TxBlock {
+            start_of_burst,
+            end_of_burst,
+            ..self
+        }
+    }
+
+    /// Sets the device time when the first sample should be transmitted
+    pub fn at(self, time: TimeSpec) -> Self {
+        TxBlock {
+            time_spec: Some(time),
+            ..self
+        }
+    }
+
+    /// Returns the buffers of samples, one per channel
+    pub fn buffers(&self) -> &[Vec<I>] {
+        &self.buffers
+    }
+}
+
+/// A sink of blocks that are transmitted on a dedicated I/O thread
+///
+/// Blocks are transmitted in order. If a send call accepts only part of a block, the I/O thread
+/// sends the rest without a start of burst flag or time.
+///
+/// Closing the sink waits until all blocks have been transmitted and returns any error from
+/// the I/O thread. Dropping it without closing lets the I/O thread transmit the queued blocks in
+/// the background. Errors from UHD are also returned from `poll_ready` once the thread has
+/// stopped. If a send call times out without sending any samples, the thread stops with an error
+/// with the kind [`TimedOut`](std::io::ErrorKind::TimedOut).
+///
+/// Asynchronous messages are available from [`async_messages`](Self::async_messages). If they
+/// are not received, messages are discarded when more than 64 are waiting.
+#[derive(Debug)]
+pub struct AsyncTransmitter<I> {
+    blocks: mpsc::Sender<TxBlock<I>>,
+    messages: mpsc::Receiver<AsyncMetadata>,
+    /// Receives the result of the I/O thread (None after it has been received)
+    done: Option<oneshot::Receiver<Result<()>>>,
+}
+
+impl<I> AsyncTransmitter<I>
+where
+    I: Sample + Send + 'static,
+{
+    /// Transmits on a streamer from a new thread
+    ///
+    /// `capacity` is the number of blocks that can wait to be transmitted.
+    pub fn spawn<S>(streamer: S, capacity: usize) -> Result<Self>
+    where
+        S: TransmitStream<I> + Send + 'static,
+    {
+        AsyncTransmitter::start(capacity, move |blocks, messages| {
+            let mut streamer = streamer;
+            transmit_blocks(&mut streamer, blocks, messages)
+        })
+    }
+
+    /// Moves a device to a new thread, creates a transmit stream, and transmits on it
+    ///
+    /// Errors from creating the stream are returned when the sink is used.
+    pub fn spawn_on<D>(device: D, args: StreamArgs<I>, capacity: usize) -> Result<Self>
+    where
+        D: Device + Send + 'static,
+    {
+        AsyncTransmitter::start(capacity, move |blocks, messages| {
+            let mut device = device;
+            let mut streamer = device.get_tx_stream(&args)?;
+            transmit_blocks(&mut streamer, blocks, messages)
+        })
+    }
+
+    fn start<F>(capacity: usize, transmit: F) -> Result<Self>
+    where
+        F: FnOnce(mpsc::Receiver<TxBlock<I>>, mpsc::Sender<AsyncMetadata>) -> Result<()>
+            + Send
+            + 'static,
+    {
+        let (blocks, block_receiver) = mpsc::channel(capacity);
+        let (message_sender, messages) = mpsc::channel(MESSAGE_CAPACITY);
+        let (done_sender, done) = oneshot::channel();
+        thread::Builder::new()
+            .name("uhd-tx".into())
+            .spawn(move || {
+                let _ = done_sender.send(transmit(block_receiver, message_sender));
+            })?;
+        Ok(AsyncTransmitter {
+            blocks,
+            messages,
+            done: Some(done),
+        })
+    }
+}
+
+impl<I> AsyncTransmitter<I> {
+    /// Returns the stream of asynchronous messages about transmitted blocks
+    ///
+    /// The stream ends when the I/O thread stops.
+    pub fn async_messages(&mut self) -> &mut mpsc::Receiver<AsyncMetadata> {
+        &mut self.messages
+    }
+
+    /// Waits for the I/O thread to stop and returns its error
+    fn poll_done(&mut self, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        let done = match self.done.as_mut() {
+            Some(done) => done,
+            None => return Poll::Ready(Err(stopped_error())),
+        };
+        let result = match Pin::new(done).poll(cx) {
+            Poll::Ready(Ok(result)) => result,
+            // The I/O thread panicked
+            Poll::Ready(Err(oneshot::Canceled)) => Err(stopped_error()),
+            Poll::Pending => return Poll::Pending,
+        };
+        self.done = None;
+        Poll::Ready(result)
+    }
+}
+
+fn stopped_error() -> Error {
+    Error::Unique("The transmit I/O thread has stopped".into())
+}
+
+impl<I> Sink<TxBlock<I>> for AsyncTransmitter<I> {
+    type Error = Error;
+
+    fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        match self.blocks.poll_ready(cx) {
+            Poll::Ready(Ok(())) => Poll::Ready(Ok(())),
+            Poll::Ready(Err(_)) => match self.poll_done(cx) {
+                Poll::Ready(Ok(())) => Poll::Ready(Err(stopped_error())),
+                other => other,
+            },
+            Poll::Pending => Poll::Pending,
+        }
+    }
+
+    fn start_send(mut self: Pin<&mut Self>, block: TxBlock<I>) -> Result<()> {
+        self.blocks.start_send(block).map_err(|_| stopped_error())
+    }
+
+    /// Returns immediately: blocks are passed to the I/O thread when they are sent. Closing the
+    /// sink waits for them to be transmitted.
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        self.blocks
+            .poll_flush_unpin(cx)
+            .map_err(|_| stopped_error())
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        // Closing the channel makes the I/O thread stop after the queued blocks
+        self.blocks.close_channel();
+        if self.done.is_none() {
+            return Poll::Ready(Ok(()));
+        }
+        self.poll_done(cx)
+    }
+}
+
+/// Unparks a thread when woken
+struct ThreadWaker(thread::Thread);
+
+impl ArcWake for ThreadWaker {
+    fn wake_by_ref(arc_self: &Arc<Self>) {
+        arc_self.0.unpark();
+    }
+}
+
+/// Transmits blocks until the channel closes, forwarding asynchronous messages
+fn transmit_blocks<S, I>(
+    streamer: &mut S,
+    mut blocks: mpsc::Receiver<TxBlock<I>>,
+    mut messages: mpsc::Sender<AsyncMetadata>,
+) -> Result<()>
+where
+    S: TransmitStream<I>,
+{
+    let waker = task::waker(Arc::new(ThreadWaker(thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    loop {
+        match blocks.poll_next_unpin(&mut cx) {
+            Poll::Ready(Some(block)) => transmit_block(streamer, &block, &mut messages)?,
+            Poll::Ready(None) => break,
+            Poll::Pending => thread::park_timeout(MESSAGE_POLL_INTERVAL),
+        }
+        forward_messages(streamer, &mut messages, 0.0)?;
+    }
+    // Wait briefly for messages about the last blocks, like end of burst acknowledgements
+    forward_messages(streamer, &mut messages, FINAL_MESSAGE_TIMEOUT)
+}
+
+fn transmit_block<S, I>(
+    streamer: &mut S,
+    block: &TxBlock<I>,
+    messages: &mut mpsc::Sender<AsyncMetadata>,
+) -> Result<()>
+where
+    S: TransmitStream<I>,
+{
+    let num_channels = streamer.num_channels();
+    if block.buffers.len() != num_channels {
+        return Err(Error::Unique(format!(
+            "Block has {} buffers, but the stream has {} channels",
+            block.buffers.len(),
+            num_channels
+        )));
+    }
+    let length = block.buffers.first().map_or(0, Vec::len);
+    if block.buffers.iter().any(|buffer| buffer.len() != length) {
+        return Err(Error::Unique("Block buffers have different lengths".into()));
+    }
+
+    let mut offset = 0;
+    loop {
+        let first = offset == 0;
+        let mut metadata = TransmitMetadata::with_flags(
+            block.start_of_burst && first,
+            block.end_of_burst,
+            if first { block.time_spec } else { None },
+        );
+        let mut buffers: Vec<&[I]> = block
+            .buffers
+            .iter()
+            .map(|buffer| &buffer[offset..])
+            .collect();
+        let sent = streamer.send(&mut buffers, &mut metadata, SEND_TIMEOUT)?;
+        offset += sent;
+        if offset >= length {
+            return Ok(());
+        }
+        if sent == 0 {
+            return Err(Error::IO(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "Timed out sending samples",
+            )));
+        }
+        forward_messages(streamer, messages, 0.0)?;
+    }
+}
+
+/// Receives asynchronous messages and sends them to `messages`, discarding them if the channel
+/// is full
+fn forward_messages<S, I>(
+    streamer: &mut S,
+    messages: &mut mpsc::Sender<AsyncMetadata>,
+    timeout: f64,
+) -> Result<()>
+where
+    S: TransmitStream<I>,
+{
+    while let Some(message) = streamer.recv_async_msg(timeout)? {
+        let _ = messages.try_send(message);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use futures::executor::block_on;
+    use futures::{SinkExt, StreamExt};
+    use num_complex::Complex32;
+
+    use super::{AsyncReceiver, AsyncTransmitter, TxBlock};
+    use crate::sim::{Signal, SimUsrp};
+    use crate::{AsyncEventCode, CaptureOptions, Device, StreamArgs, TimeSpec, WireFormat};
+
+    #[test]
+    fn receive_stream() {
+        let mut usrp = SimUsrp::new();
+        usrp.set_rx_sample_rate(1e6, 0).unwrap();
+        usrp.add_rx_signal(0, Signal::Noise { amplitude: 0.1 })
+            .unwrap();
+        let options = CaptureOptions::new().block_length(256).blocks(2);
+        let mut blocks = AsyncReceiver::spawn_on(
+            usrp,
+            StreamArgs::<Complex32>::new(WireFormat::Sc16),
+            options,
+        )
+        .unwrap();
+
+        let received = block_on(blocks.by_ref().take(3).collect::<Vec<_>>());
+        assert_eq!(received.len(), 3);
+        for (i, block) in received.into_iter().enumerate() {
+            let block = block.unwrap();
+            assert_eq!(block.samples(), 256);
+            assert_eq!(block.start_of_burst(), i == 0);
+            assert!(block.error().is_none());
+            assert_eq!(
+                block.time_spec(),
+                Some(TimeSpec::from_ticks(256 * i as i64, 1e6))
+            );
+        }
+
+        // Blocks that were received before stopping are still returned, with no gaps
+        blocks.stop();
+        let remaining = block_on(blocks.collect::<Vec<_>>());
+        for (i, block) in remaining.into_iter().enumerate() {
+            let block = block.unwrap();
+            assert_eq!(block.samples(), 256);
+            assert_eq!(
+                block.time_spec(),
+                Some(TimeSpec::from_ticks(256 * (i as i64 + 3), 1e6))
+            );
+        }
+    }
+
+    #[test]
+    fn receive_stream_setup_error() {
+        let usrp = SimUsrp::new();
+        let args = StreamArgs::<Complex32>::builder().channels(vec![5]).build();
+        let mut blocks = AsyncReceiver::spawn_on(usrp, args, CaptureOptions::new()).unwrap();
+        assert!(block_on(blocks.next()).unwrap().is_err());
+        assert!(block_on(blocks.next()).is_none());
+    }
+
+    #[test]
+    fn transmit_sink() {
+        let usrp = SimUsrp::new();
+        let mut sink = AsyncTransmitter::spawn_on(
+            usrp.clone(),
+            StreamArgs::<Complex32>::new(WireFormat::Sc16),
+            2,
+        )
+        .unwrap();
+
+        block_on(async {
+            for i in 0..4 {
+                let block =
+                    TxBlock::new(vec![vec![Complex32::new(0.5, 0.0); 100]]).burst(i == 0, i == 3);
+                sink.send(block).await.unwrap();
+            }
+            sink.close().await.unwrap();
+        });
+        assert_eq!(usrp.transmitted_samples(0).unwrap(), 400);
+        let messages = block_on(sink.async_messages().collect::<Vec<_>>());
+        assert!(messages
+            .iter()
+            .any(|message| message.event_code() == AsyncEventCode::BurstAck));
+
+        // Blocks must have one buffer per channel
+        let mut sink =
+            AsyncTransmitter::spawn_on(usrp, StreamArgs::new(WireFormat::Sc16), 2).unwrap();
+        let result = block_on(async {
+            sink.send(TxBlock::new(vec![Vec::<Complex32>::new(); 2]))
+                .await?;
+            sink.close().await
+        });
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,338 @@
+//! Throughput benchmarks for receive and transmit streams
+//!
+//! These are similar to UHD's `benchmark_rate` example, but they run through the streamers in this
+//! library. A [`Benchmark`] streams for a fixed wall-clock duration and counts samples and errors.
+
+use std::fmt;
+use std::iter;
+use std::time::{Duration, Instant};
+
+use num_complex::Complex64;
+
+use crate::device::{ReceiveStream, TransmitStream};
+use crate::error::{Error, Result};
+use crate::stream::{Sample, StreamCommand, StreamCommandType, StreamTime};
+use crate::{AsyncEventCode, ReceiveErrorKind, TimeSpec, TransmitMetadata};
+
+/// Settings for a receive or transmit benchmark
+#[derive(Debug, Clone)]
+pub struct Benchmark {
+    duration: Duration,
+    block_length: usize,
+    timeout: f64,
+}
+
+impl Benchmark {
+    /// Creates a benchmark that streams for a duration, with blocks of 10000 samples per channel
+    /// and a timeout of 0.1 seconds for each receive or send call
+    pub fn new(duration: Duration) -> Self {
+        Benchmark {
+            duration,
+            block_length: 10000,
+            timeout: 0.1,
+        }
+    }
+
+    /// Sets the number of samples per channel for each receive or send call
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `block_length` is zero.
+    pub fn block_length(self, block_length: usize) -> Self {
+        assert_ne!(block_length, 0, "Block length must not be zero");
+        Benchmark {
+            block_length,
+            ..self
+        }
+    }
+
+    /// Sets the timeout, in seconds, for each receive or send call
+    pub fn timeout(self, timeout: f64) -> Self {
+        Benchmark { timeout, ..self }
+    }
+
+    /// Receives continuously and counts samples and errors
+    ///
+    /// `sample_rate` is the receive sample rate of the device, which is used to find the samples
+    /// that were dropped between packets.
+    ///
+    /// Overflows, sequence errors, late commands, and timeouts are counted. Other receive errors
+    /// stop streaming and are returned.
+    pub fn receive<I, S>(&self, streamer: &mut S, sample_rate: f64) -> Result<RxBenchmarkReport>
+    where
+        I: Sample,
+        S: ReceiveStream<I>,
+    {
+        let mut buffers: Vec<Vec<I>> = (0..streamer.num_channels())
+            .map(|_| {
+                iter::repeat_with(|| I::from_fc64(Complex64::default()))
+                    .take(self.block_length)
+                    .collect()
+            })
+            .collect();
+        let mut report = RxBenchmarkReport::default();
+        // The time and length of the last block with samples
+        let mut previous: Option<(TimeSpec, usize)> = None;
+
+        streamer.send_command(&StreamCommand {
+            command_type: StreamCommandType::StartContinuous,
+            time: StreamTime::Now,
+        })?;
+        let start = Instant::now();
+        let result = loop {
+            if start.elapsed() >= self.duration {
+                break Ok(());
+            }
+            let mut slices: Vec<&mut [I]> = buffers.iter_mut().map(|b| &mut b[..]).collect();
+            let metadata = match streamer.receive(&mut slices, self.timeout, false) {
+                Ok(metadata) => metadata,
+                Err(e) => break Err(e),
+            };
+            if let Some(error) = metadata.last_error() {
+                match error.kind {
+                    ReceiveErrorKind::Overflow => report.overflows += 1,
+                    ReceiveErrorKind::OutOfSequence => report.sequence_errors += 1,
+                    ReceiveErrorKind::LateCommand => report.late_commands += 1,
+                    ReceiveErrorKind::Timeout => report.timeouts += 1,
+                    _ => break Err(Error::Other(error.into())),
+                }
+            }
+            let samples = metadata.samples();
+            if samples != 0 {
+                report.samples += samples as u64;
+                if let Some(time) = metadata.time_spec() {
+                    if let Some((previous_time, previous_samples)) = previous {
+                        report.dropped_samples +=
+                            dropped_samples(previous_time, previous_samples, time, sample_rate);
+                    }
+                    previous = Some((time, samples));
+                }
+            }
+        };
+        report.elapsed = start.elapsed();
+
+        // An error from the loop is more useful than an error from stopping
+        let stopped = streamer.send_command(&StreamCommand {
+            command_type: StreamCommandType::StopContinuous,
+            time: StreamTime::Now,
+        });
+        result.and(stopped).map(|_| report)
+    }
+
+    /// Transmits one burst of zeros and counts samples and asynchronous events
+    ///
+    /// After the duration has passed, this ends the burst and waits up to one second for the
+    /// device to acknowledge it.
+    pub fn transmit<I, S>(&self, streamer: &mut S) -> Result<TxBenchmarkReport>
+    where
+        I: Sample,
+        S: TransmitStream<I>,
+    {
+        let zeros: Vec<I> = iter::repeat_with(|| I::from_fc64(Complex64::default()))
+            .take(self.block_length)
+            .collect();
+        let channels = streamer.num_channels();
+        let mut report = TxBenchmarkReport::default();
+
+        let start = Instant::now();
+        let mut first = true;
+        while start.elapsed() < self.duration {
+            let mut metadata = TransmitMetadata::with_flags(first, false, None);
+            let mut buffers = vec![&zeros[..]; channels];
+            let sent = streamer.send(&mut buffers, &mut metadata, self.timeout)?;
+            if sent != 0 {
+                first = false;
+            }
+            report.samples += sent as u64;
+            while let Some(message) = streamer.recv_async_msg(0.0)? {
+                report.add_event(message.event_code());
+            }
+        }
+        report.elapsed = start.elapsed();
+
+        // End the burst
+        let mut metadata = TransmitMetadata::with_flags(first, true, None);
+        streamer.send(&mut vec![&[][..]; channels], &mut metadata, self.timeout)?;
+        let wait_start = Instant::now();
+        while wait_start.elapsed() < Duration::from_secs(1) {
+            match streamer.recv_async_msg(0.1)? {
+                Some(message) if message.event_code() == AsyncEventCode::BurstAck => break,
+                Some(message) => report.add_event(message.event_code()),
+                None => {}
+            }
+        }
+        Ok(report)
+    }
+}
+
+/// Returns the number of samples missing between a block of samples and the next block
+///
+/// `previous_time` and `previous_samples` are the time and length of a block. If the next block
+/// starts later than the end of that block, the difference is the number of dropped samples.
+fn dropped_samples(
+    previous_time: TimeSpec,
+    previous_samples: usize,
+    next_time: TimeSpec,
+    sample_rate: f64,
+) -> u64 {
+    let expected = previous_time.to_ticks(sample_rate) + previous_samples as i64;
+    (next_time.to_ticks(sample_rate) - expected).max(0) as u64
+}
+
+/// The results of a receive benchmark
+#[derive(Debug, Clone, Default)]
+pub struct RxBenchmarkReport {
+    /// The number of samples received on each channel
+    pub samples: u64,
+    /// The time spent receiving
+    pub elapsed: Duration,
+    /// The number of overflows
+    pub overflows: u64,
+    /// The number of out-of-sequence packets
+    pub sequence_errors: u64,
+    /// The number of late stream commands
+    pub late_commands: u64,
+    /// The number of receive calls that timed out
+    pub timeouts: u64,
+    /// The number of samples on each channel that were lost, based on the times of received
+    /// packets
+    pub dropped_samples: u64,
+}
+
+impl RxBenchmarkReport {
+    /// Returns the average number of samples received per second on each channel
+    pub fn samples_per_second(&self) -> f64 {
+        self.samples as f64 / self.elapsed.as_secs_f64()
+    }
+}
+
+impl fmt::Display for RxBenchmarkReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Samples received per channel: {}", self.samples)?;
+        writeln!(
+            f,
+            "Receive rate: {:.0} samples/s",
+            self.samples_per_second()
+        )?;
+        writeln!(f, "Overflows: {}", self.overflows)?;
+        writeln!(f, "Sequence errors: {}", self.sequence_errors)?;
+        writeln!(f, "Late commands: {}", self.late_commands)?;
+        writeln!(f, "Timeouts: {}", self.timeouts)?;
+        write!(f, "Dropped samples: {}", self.dropped_samples)
+    }
+}
+
+/// The results of a transmit benchmark
+#[derive(Debug, Clone, Default)]
+pub struct TxBenchmarkReport {
+    /// The number of samples sent on each channel
+    pub samples: u64,
+    /// The time spent sending
+    pub elapsed: Duration,
+    /// The number of underflows
+    pub underflows: u64,
+    /// The number of sequence errors
+    pub sequence_errors: u64,
+    /// The number of packets that arrived at the device too late
+    pub late_packets: u64,
+    /// The number of other error events
+    pub other_errors: u64,
+}
+
+impl TxBenchmarkReport {
+    /// Returns the average number of samples sent per second on each channel
+    pub fn samples_per_second(&self) -> f64 {
+        self.samples as f64 / self.elapsed.as_secs_f64()
+    }
+
+    fn add_event(&mut self, event_code: AsyncEventCode) {
+        match event_code {
+            AsyncEventCode::Underflow | AsyncEventCode::UnderflowInPacket => self.underflows += 1,
+            AsyncEventCode::SequenceError | AsyncEventCode::SequenceErrorInBurst => {
+                self.sequence_errors += 1
+            }
+            AsyncEventCode::TimeError => self.late_packets += 1,
+            code if code.is_error() => self.other_errors += 1,
+            _ => {}
+        }
+    }
+}
+
+impl fmt::Display for TxBenchmarkReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Samples sent per channel: {}", self.samples)?;
+        writeln!(
+            f,
+            "Transmit rate: {:.0} samples/s",
+            self.samples_per_second()
+        )?;
+        writeln!(f, "Underflows: {}", self.underflows)?;
+        writeln!(f, "Sequence errors: {}", self.sequence_errors)?;
+        writeln!(f, "Late packets: {}", self.late_packets)?;
+        write!(f, "Other errors: {}", self.other_errors)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::thread;
+    use std::time::Duration;
+
+    use num_complex::Complex32;
+
+    use super::{dropped_samples, Benchmark};
+    use crate::sim::SimUsrp;
+    use crate::{AsyncEventCode, Device, ReceiveErrorKind, StreamArgs, TimeSpec, WireFormat};
+
+    #[test]
+    fn gaps_between_blocks() {
+        let rate = 1e6;
+        let start = TimeSpec::new(10, 0.5);
+        assert_eq!(
+            dropped_samples(start, 100, start + TimeSpec::from_ticks(100, rate), rate),
+            0
+        );
+        assert_eq!(
+            dropped_samples(start, 100, start + TimeSpec::from_ticks(350, rate), rate),
+            250
+        );
+        // A block that starts early (after a time reset) does not count as dropped samples
+        assert_eq!(dropped_samples(start, 100, TimeSpec::new(0, 0.0), rate), 0);
+    }
+
+    #[test]
+    fn receive_and_transmit() {
+        let mut usrp = SimUsrp::new();
+        let control = usrp.clone();
+        usrp.set_rx_sample_rate(1e6, 0).unwrap();
+        let rate = usrp.get_rx_sample_rate(0).unwrap();
+        let benchmark = Benchmark::new(Duration::from_millis(100)).block_length(1000);
+
+        let mut streamer = usrp
+            .get_rx_stream(&StreamArgs::<Complex32>::new(WireFormat::Sc16))
+            .unwrap();
+        let report = thread::scope(|scope| {
+            // Cause an overflow after the first samples have been received
+            scope.spawn(|| {
+                while control.get_current_time(0).unwrap() == TimeSpec::default() {
+                    thread::yield_now();
+                }
+                control.inject_rx_error(ReceiveErrorKind::Overflow);
+            });
+            benchmark.receive(&mut streamer, rate).unwrap()
+        });
+        drop(streamer);
+        assert!(report.samples > 0);
+        assert_eq!(report.overflows, 1);
+        // The simulated device drops one buffer of samples after an overflow
+        assert_eq!(report.dropped_samples, 1000);
+
+        control.inject_tx_event(AsyncEventCode::Underflow);
+        let mut streamer = usrp
+            .get_tx_stream(&StreamArgs::<Complex32>::new(WireFormat::Sc16))
+            .unwrap();
+        let report = benchmark.transmit(&mut streamer).unwrap();
+        assert_eq!(report.underflows, 1);
+        assert_eq!(report.samples, control.transmitted_samples(0).unwrap());
+    }
+}
@@ -0,0 +1,112 @@
+//! Measures receive and transmit throughput with a USRP
+//!
+//! This is similar to the `benchmark_rate` example that comes with UHD, but it streams through
+//! this library.
+
+use std::error::Error;
+use std::thread;
+use std::time::Duration;
+
+use clap::{ArgGroup, Parser};
+use num_complex::Complex32;
+use uhd::{Benchmark, DeviceArgs, StreamArgs, Usrp, WireFormat};
+
+/// Measures receive and transmit throughput with a USRP
+#[derive(Debug, Parser)]
+#[command(name = "uhd-benchmark", version)]
+#[command(group(ArgGroup::new("direction").required(true).multiple(true).args(["rx_rate", "tx_rate"])))]
+struct Options {
+    /// Device arguments, like "type=b200" or "addr=192.168.10.2"
+    #[arg(long, default_value = "")]
+    args: DeviceArgs,
+    /// Time to stream, seconds
+    #[arg(long, default_value_t = 10.0)]
+    duration: f64,
+    /// Receive sample rate, samples/second. Without this, nothing is received.
+    #[arg(long)]
+    rx_rate: Option<f64>,
+    /// Transmit sample rate, samples/second. Without this, nothing is transmitted.
+    #[arg(long)]
+    tx_rate: Option<f64>,
+    /// Channels to receive, separated by commas
+    #[arg(long, value_delimiter = ',', default_value = "0")]
+    rx_channels: Vec<usize>,
+    /// Channels to transmit on, separated by commas
+    #[arg(long, value_delimiter = ',', default_value = "0")]
+    tx_channels: Vec<usize>,
+    /// Format of samples sent between the host and the device
+    #[arg(long, default_value = "sc16")]
+    wire: WireFormat,
+    /// Samples per channel for each receive or send call
+    #[arg(long, default_value_t = 10000)]
+    spb: usize,
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let options = Options::parse();
+    if options.spb == 0 {
+        return Err("--spb must be greater than zero".into());
+    }
+    if !(options.duration.is_finite() && options.duration > 0.0) {
+        return Err("--duration must be greater than zero".into());
+    }
+    let benchmark =
+        Benchmark::new(Duration::from_secs_f64(options.duration)).block_length(options.spb);
+
+    let mut usrp = Usrp::open(&options.args)?;
+    println!("Using device {}", usrp.get_motherboard_name(0)?);
+    let mut rx_rate = None;
+    if let Some(rate) = options.rx_rate {
+        for &channel in &options.rx_channels {
+            usrp.set_rx_sample_rate(rate, channel)?;
+        }
+        let actual = usrp.get_rx_sample_rate(options.rx_channels[0])?;
+        println!(
+            "Receiving at {} samples/s on channels {:?}",
+            actual, options.rx_channels
+        );
+        rx_rate = Some(actual);
+    }
+    if let Some(rate) = options.tx_rate {
+        for &channel in &options.tx_channels {
+            usrp.set_tx_sample_rate(rate, channel)?;
+        }
+        println!(
+            "Transmitting at {} samples/s on channels {:?}",
+            usrp.get_tx_sample_rate(options.tx_channels[0])?,
+            options.tx_channels
+        );
+    }
+
+    let rx_args = StreamArgs::<Complex32>::builder()
+        .wire_format(options.wire.clone())
+        .channels(options.rx_channels.clone())
+        .build();
+    let tx_args = StreamArgs::<Complex32>::builder()
+        .wire_format(options.wire.clone())
+        .channels(options.tx_channels.clone())
+        .build();
+    match (rx_rate, options.tx_rate.is_some()) {
+        (Some(rate), true) => {
+            let (mut rx, mut tx) = usrp.get_rx_tx_streams(&rx_args, &tx_args)?;
+            let (rx_report, tx_report) = thread::scope(|scope| {
+                let tx_thread = scope.spawn(|| benchmark.transmit(&mut tx));
+                let rx_report = benchmark.receive(&mut rx, rate);
+                let tx_report = tx_thread.join().expect("Transmit thread panicked");
+                (rx_report, tx_report)
+            });
+            println!("{}", rx_report?);
+            println!("{}", tx_report?);
+        }
+        (Some(rate), false) => {
+            let mut rx = usrp.get_rx_stream(&rx_args)?;
+            println!("{}", benchmark.receive(&mut rx, rate)?);
+        }
+        (None, true) => {
+            let mut tx = usrp.get_tx_stream(&tx_args)?;
+            println!("{}", benchmark.transmit(&mut tx)?);
+        }
+        (None, false) => unreachable!("clap requires --rx-rate or --tx-rate"),
+    }
+    Ok(())
+}
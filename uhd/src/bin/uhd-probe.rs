@@ -0,0 +1,66 @@
+//! Describes connected USRPs without changing any settings
+//!
+//! This is similar to `uhd_usrp_probe`, but it can also produce JSON for comparing devices.
+
+use std::error::Error;
+
+use clap::{Parser, ValueEnum};
+use serde_json::json;
+use uhd::{DeviceArgs, DeviceReport, Usrp};
+
+/// Describes connected USRPs without changing any settings
+#[derive(Debug, Parser)]
+#[command(name = "uhd-probe", version)]
+struct Options {
+    /// Arguments of one device to probe, like "type=b200" or "addr=192.168.10.2". Without this,
+    /// all devices that can be found are probed.
+    #[arg(long)]
+    args: Option<DeviceArgs>,
+    /// Output format
+    #[arg(long, value_enum, default_value_t = Format::Table)]
+    format: Format,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum Format {
+    /// Indented text
+    Table,
+    /// A JSON array with the address and report of each device
+    Json,
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let options = Options::parse();
+    let addresses = match options.args {
+        Some(args) => vec![args],
+        None => Usrp::find("")?.into_iter().map(DeviceArgs::from).collect(),
+    };
+
+    let mut reports = Vec::new();
+    for address in addresses {
+        let report = Usrp::open(&address).and_then(|usrp| DeviceReport::from_usrp(&usrp));
+        match report {
+            Ok(report) => reports.push((address, report)),
+            Err(e) => eprintln!("Failed to probe {}: {}", address, e),
+        }
+    }
+
+    match options.format {
+        Format::Table => {
+            for (address, report) in &reports {
+                println!("Device {}", address);
+                print!("{}", report);
+            }
+        }
+        Format::Json => {
+            let devices = reports
+                .iter()
+                .map(
+                    |(address, report)| json!({ "address": address.to_string(), "report": report }),
+                )
+                .collect::<Vec<_>>();
+            println!("{}", serde_json::to_string_pretty(&devices)?);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,343 @@
+//! Receives samples from a USRP and writes them to files
+//!
+//! This is similar to the `rx_samples_to_file` example that comes with UHD.
+
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use clap::{ArgGroup, Parser, ValueEnum};
+use num_complex::{Complex, Complex32, Complex64};
+use uhd::{
+    DeviceArgs, ReceiveErrorKind, ReceiveMetadata, RecordingInfo, SigmfItem, SigmfRecorder,
+    StreamArgs, StreamCommand, StreamCommandType, StreamTime, TimeSpec, TuneRequest, Usrp,
+    WireFormat,
+};
+
+/// Extra time, in seconds, allowed for the first samples to arrive after streaming starts
+const START_TIMEOUT: f64 = 1.0;
+
+/// Receives samples from a USRP and writes them to files
+#[derive(Debug, Parser)]
+#[command(name = "uhd-rx", version)]
+#[command(group(ArgGroup::new("length").required(true).args(["duration", "nsamps"])))]
+struct Options {
+    /// Device arguments, like "type=b200" or "addr=192.168.10.2"
+    #[arg(long, default_value = "")]
+    args: DeviceArgs,
+    /// Sample rate, samples/second
+    #[arg(long, default_value_t = 1e6)]
+    rate: f64,
+    /// Center frequency, hertz
+    #[arg(long)]
+    freq: f64,
+    /// Overall gain, decibels
+    #[arg(long)]
+    gain: Option<f64>,
+    /// Antenna
+    #[arg(long)]
+    ant: Option<String>,
+    /// Analog bandwidth, hertz
+    #[arg(long)]
+    bw: Option<f64>,
+    /// Channels to receive, separated by commas
+    #[arg(long, value_delimiter = ',', default_value = "0")]
+    channels: Vec<usize>,
+    /// Format of samples sent between the device and the host
+    #[arg(long, default_value = "sc16")]
+    wire: WireFormat,
+    /// Format of samples in memory and in the output files
+    #[arg(long = "type", value_enum, default_value_t = HostFormat::Fc32)]
+    host_format: HostFormat,
+    /// Time to receive, seconds
+    #[arg(long)]
+    duration: Option<f64>,
+    /// Number of samples to receive on each channel
+    #[arg(long)]
+    nsamps: Option<u64>,
+    /// Delay before receiving, seconds (uses a timed stream command)
+    #[arg(long)]
+    start_delay: Option<f64>,
+    /// Samples per channel for each receive call
+    #[arg(long, default_value_t = 10000)]
+    spb: usize,
+    /// Output file. With more than one channel, the channel number is added to each file name.
+    /// SigMF recordings append .sigmf-data and .sigmf-meta to this name.
+    #[arg(long, default_value = "usrp_samples.dat")]
+    file: PathBuf,
+    /// Output file format
+    #[arg(long, value_enum, default_value_t = OutputFormat::Raw)]
+    output: OutputFormat,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum HostFormat {
+    Fc64,
+    Fc32,
+    Sc16,
+    Sc8,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum OutputFormat {
+    /// Interleaved I/Q samples in little-endian byte order
+    Raw,
+    /// A SigMF recording (.sigmf-data and .sigmf-meta)
+    Sigmf,
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let options = Options::parse();
+    if options.spb == 0 {
+        return Err("--spb must be greater than zero".into());
+    }
+    if matches!(options.duration, Some(duration) if !(duration >= 0.0 && duration.is_finite())) {
+        return Err("--duration must be a non-negative number of seconds".into());
+    }
+    if matches!(options.start_delay, Some(delay) if !(delay >= 0.0 && delay.is_finite())) {
+        return Err("--start-delay must be a non-negative number of seconds".into());
+    }
+    for (i, channel) in options.channels.iter().enumerate() {
+        if options.channels[..i].contains(channel) {
+            return Err(
+                format!("Channel {} is listed more than once in --channels", channel).into(),
+            );
+        }
+    }
+    match options.host_format {
+        HostFormat::Fc64 => receive::<Complex64>(&options),
+        HostFormat::Fc32 => receive::<Complex32>(&options),
+        HostFormat::Sc16 => receive::<Complex<i16>>(&options),
+        HostFormat::Sc8 => receive::<Complex<i8>>(&options),
+    }
+}
+
+fn receive<I>(options: &Options) -> Result<(), Box<dyn Error>>
+where
+    I: SigmfItem,
+{
+    let mut usrp = Usrp::open(&options.args)?;
+    println!("Using device {}", usrp.get_motherboard_name(0)?);
+    for &channel in &options.channels {
+        configure_channel(&mut usrp, options, channel)?;
+    }
+    let rate = usrp.get_rx_sample_rate(options.channels[0])?;
+    let total = match (options.nsamps, options.duration) {
+        (Some(nsamps), _) => nsamps,
+        (None, Some(duration)) => (duration * rate).round() as u64,
+        (None, None) => unreachable!("clap requires --duration or --nsamps"),
+    };
+
+    let mut outputs = options
+        .channels
+        .iter()
+        .map(|&channel| Output::create(options, &usrp, channel))
+        .collect::<Result<Vec<Output<I>>, _>>()?;
+
+    let args = StreamArgs::<I>::builder()
+        .wire_format(options.wire.clone())
+        .channels(options.channels.clone())
+        .build();
+    // The first receive call waits for streaming to start
+    let mut timeout = START_TIMEOUT;
+    let time = match options.start_delay {
+        Some(delay) => {
+            timeout += delay;
+            StreamTime::At(usrp.get_current_time(0)? + TimeSpec::new(delay as i64, delay.fract()))
+        }
+        None => StreamTime::Now,
+    };
+    let mut streamer = usrp.get_rx_stream(&args)?;
+    streamer.send_command(&StreamCommand {
+        command_type: StreamCommandType::StartContinuous,
+        time,
+    })?;
+
+    let mut buffers: Vec<Vec<I>> = options
+        .channels
+        .iter()
+        .map(|_| {
+            std::iter::repeat_with(|| I::from_fc64(Complex64::default()))
+                .take(options.spb)
+                .collect()
+        })
+        .collect();
+    let mut statistics = Statistics::default();
+    let mut failure = None;
+    while statistics.samples < total {
+        let length = (total - statistics.samples).min(options.spb as u64) as usize;
+        let mut slices: Vec<&mut [I]> = buffers
+            .iter_mut()
+            .map(|buffer| &mut buffer[..length])
+            .collect();
+        let metadata = streamer.receive(&mut slices, timeout, false)?;
+        timeout = 0.1;
+
+        if let Some(error) = metadata.last_error() {
+            statistics.add_error(&error.kind);
+            match error.kind {
+                ReceiveErrorKind::Overflow => eprint!("O"),
+                ReceiveErrorKind::OutOfSequence => eprint!("D"),
+                _ => {
+                    failure = Some(error);
+                    break;
+                }
+            }
+        }
+        for (output, buffer) in outputs.iter_mut().zip(&buffers) {
+            output.write(&buffer[..metadata.samples()], &metadata)?;
+        }
+        statistics.samples += metadata.samples() as u64;
+    }
+
+    streamer.send_command(&StreamCommand {
+        command_type: StreamCommandType::StopContinuous,
+        time: StreamTime::Now,
+    })?;
+    for output in outputs {
+        output.finish()?;
+    }
+    println!();
+    println!("{}", statistics);
+    match failure {
+        Some(error) => Err(format!("Receive error: {}", error).into()),
+        None => Ok(()),
+    }
+}
+
+fn configure_channel(
+    usrp: &mut Usrp,
+    options: &Options,
+    channel: usize,
+) -> Result<(), Box<dyn Error>> {
+    let mut rx = usrp.rx_channel(channel)?;
+    rx.set_sample_rate(options.rate)?;
+    rx.set_frequency(&TuneRequest::with_frequency(options.freq))?;
+    if let Some(gain) = options.gain {
+        rx.set_gain(gain, "")?;
+    }
+    if let Some(antenna) = &options.ant {
+        rx.set_antenna(antenna)?;
+    }
+    if let Some(bandwidth) = options.bw {
+        rx.set_bandwidth(bandwidth)?;
+    }
+    println!(
+        "Channel {}: rate {} samples/s, frequency {} Hz, gain {} dB, antenna {}, bandwidth {} Hz",
+        channel,
+        rx.get_sample_rate()?,
+        rx.get_frequency()?,
+        rx.get_gain("")?,
+        rx.get_antenna()?,
+        rx.get_bandwidth()?,
+    );
+    if rx
+        .get_sensor_names()?
+        .iter()
+        .any(|name| name == "lo_locked")
+    {
+        let locked = rx.get_sensor("lo_locked")?;
+        println!("Channel {}: LO locked: {}", channel, locked);
+    }
+    Ok(())
+}
+
+/// A file that samples from one channel are written to
+enum Output<I> {
+    Raw(BufWriter<File>, Vec<u8>),
+    Sigmf(Box<SigmfRecorder<I>>),
+}
+
+impl<I> Output<I>
+where
+    I: SigmfItem,
+{
+    fn create(options: &Options, usrp: &Usrp, channel: usize) -> Result<Self, Box<dyn Error>> {
+        let path = channel_path(&options.file, channel, options.channels.len());
+        match options.output {
+            OutputFormat::Raw => {
+                println!("Writing channel {} to {}", channel, path.display());
+                Ok(Output::Raw(BufWriter::new(File::create(path)?), Vec::new()))
+            }
+            OutputFormat::Sigmf => {
+                println!(
+                    "Writing channel {} to {}.sigmf-data",
+                    channel,
+                    path.display()
+                );
+                let mut info = RecordingInfo::from_usrp(usrp, channel)?;
+                info.description = Some(format!("Received by uhd-rx on channel {}", channel));
+                Ok(Output::Sigmf(Box::new(SigmfRecorder::create(path, info)?)))
+            }
+        }
+    }
+
+    fn write(&mut self, samples: &[I], metadata: &ReceiveMetadata) -> Result<(), Box<dyn Error>> {
+        match self {
+            Output::Raw(file, bytes) => {
+                bytes.clear();
+                I::write_le(samples, bytes);
+                file.write_all(bytes)?;
+            }
+            Output::Sigmf(recorder) => recorder.write_block(samples, metadata)?,
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Result<(), Box<dyn Error>> {
+        match self {
+            Output::Raw(mut file, _) => file.flush()?,
+            Output::Sigmf(recorder) => recorder.finish()?,
+        }
+        Ok(())
+    }
+}
+
+/// Returns the path of the file for a channel, adding the channel number if there is more than
+/// one channel
+fn channel_path(path: &Path, channel: usize, channels: usize) -> PathBuf {
+    if channels == 1 {
+        return path.to_owned();
+    }
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match path.extension() {
+        Some(extension) => format!("{}.ch{}.{}", stem, channel, extension.to_string_lossy()),
+        None => format!("{}.ch{}", stem, channel),
+    };
+    path.with_file_name(name)
+}
+
+#[derive(Debug, Default)]
+struct Statistics {
+    samples: u64,
+    overflows: u64,
+    sequence_errors: u64,
+    timeouts: u64,
+    late_commands: u64,
+    other_errors: u64,
+}
+
+impl Statistics {
+    fn add_error(&mut self, kind: &ReceiveErrorKind) {
+        match kind {
+            ReceiveErrorKind::Overflow => self.overflows += 1,
+            ReceiveErrorKind::OutOfSequence => self.sequence_errors += 1,
+            ReceiveErrorKind::Timeout => self.timeouts += 1,
+            ReceiveErrorKind::LateCommand => self.late_commands += 1,
+            _ => self.other_errors += 1,
+        }
+    }
+}
+
+impl fmt::Display for Statistics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Samples received per channel: {}", self.samples)?;
+        writeln!(f, "Overflows: {}", self.overflows)?;
+        writeln!(f, "Sequence errors: {}", self.sequence_errors)?;
+        writeln!(f, "Timeouts: {}", self.timeouts)?;
+        writeln!(f, "Late commands: {}", self.late_commands)?;
+        write!(f, "Other errors: {}", self.other_errors)
+    }
+}
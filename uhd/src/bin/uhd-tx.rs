@@ -0,0 +1,384 @@
+//! Transmits samples from a file or a synthesized waveform with a USRP
+//!
+//! This is similar to the `tx_samples_from_file` and `tx_waveforms` examples that come with UHD.
+
+use std::error::Error;
+use std::fmt;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use clap::{Parser, ValueEnum};
+use num_complex::Complex32;
+use uhd::{
+    AsyncEventCode, AsyncMetadata, DeviceArgs, FileFormat, Playback, Repeat, StreamArgs, TimeSpec,
+    TransmitMetadata, TransmitStreamer, TuneRequest, Usrp, Waveform, WaveformGenerator, WireFormat,
+};
+
+/// The timeout, in seconds, for each send call (in addition to any start delay)
+const SEND_TIMEOUT: f64 = 1.0;
+
+/// Transmits samples from a file or a synthesized waveform with a USRP
+#[derive(Debug, Parser)]
+#[command(name = "uhd-tx", version)]
+struct Options {
+    /// Device arguments, like "type=b200" or "addr=192.168.10.2"
+    #[arg(long, default_value = "")]
+    args: DeviceArgs,
+    /// Sample rate, samples/second
+    #[arg(long, default_value_t = 1e6)]
+    rate: f64,
+    /// Center frequency, hertz
+    #[arg(long)]
+    freq: f64,
+    /// Overall gain, decibels
+    #[arg(long)]
+    gain: Option<f64>,
+    /// Antenna
+    #[arg(long)]
+    ant: Option<String>,
+    /// Analog bandwidth, hertz
+    #[arg(long)]
+    bw: Option<f64>,
+    /// Channels to transmit on, separated by commas. Waveforms are sent on every channel; files
+    /// can only be sent on one channel.
+    #[arg(long, value_delimiter = ',', default_value = "0")]
+    channels: Vec<usize>,
+    /// Format of samples sent between the host and the device
+    #[arg(long, default_value = "sc16")]
+    wire: WireFormat,
+    /// File to transmit instead of a waveform
+    #[arg(long)]
+    file: Option<PathBuf>,
+    /// Format of the file. For SigMF, --file is the recording path without an extension.
+    #[arg(long, value_enum, default_value_t = InputFormat::Sc16)]
+    format: InputFormat,
+    /// Number of times to transmit the file
+    #[arg(long, requires = "file", conflicts_with = "forever")]
+    repeat: Option<u64>,
+    /// Transmit the file repeatedly until the program is stopped
+    #[arg(long = "loop", requires = "file")]
+    forever: bool,
+    /// Waveform to transmit
+    #[arg(long, value_enum, default_value_t = WaveType::Sine)]
+    wave_type: WaveType,
+    /// Waveform frequency (the start frequency for a chirp), hertz
+    #[arg(long, default_value_t = 0.0, allow_negative_numbers = true)]
+    wave_freq: f64,
+    /// Chirp stop frequency, hertz
+    #[arg(long, default_value_t = 0.0, allow_negative_numbers = true)]
+    chirp_stop: f64,
+    /// Chirp sweep period, seconds
+    #[arg(long, default_value_t = 0.01)]
+    chirp_period: f64,
+    /// Waveform amplitude (RMS amplitude for noise), between 0 and 1
+    #[arg(long, default_value_t = 0.3)]
+    ampl: f64,
+    /// Time to transmit the waveform, seconds. Without this or --nsamps, the waveform is
+    /// transmitted until Ctrl-C is pressed.
+    #[arg(long, conflicts_with_all = ["file", "nsamps"])]
+    duration: Option<f64>,
+    /// Number of waveform samples to transmit on each channel
+    #[arg(long, conflicts_with = "file")]
+    nsamps: Option<u64>,
+    /// Delay before transmitting, seconds (uses a timed burst)
+    #[arg(long)]
+    delay: Option<f64>,
+    /// Samples per channel for each send call
+    #[arg(long, default_value_t = 10000)]
+    spb: usize,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum InputFormat {
+    Fc64,
+    Fc32,
+    Sc16,
+    Sc8,
+    /// A SigMF recording, with the format and sample rate from its metadata
+    Sigmf,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum WaveType {
+    Sine,
+    Square,
+    Const,
+    Chirp,
+    Noise,
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let options = Options::parse();
+    if options.spb == 0 {
+        return Err("--spb must be greater than zero".into());
+    }
+    if matches!(options.duration, Some(duration) if !(duration >= 0.0 && duration.is_finite())) {
+        return Err("--duration must be a non-negative number of seconds".into());
+    }
+    if matches!(options.delay, Some(delay) if !(delay >= 0.0 && delay.is_finite())) {
+        return Err("--delay must be a non-negative number of seconds".into());
+    }
+    if !(0.0..=1.0).contains(&options.ampl) {
+        return Err("--ampl must be between 0 and 1".into());
+    }
+    if options.file.is_some() && options.channels.len() != 1 {
+        return Err("A file can only be transmitted on one channel".into());
+    }
+
+    // Ctrl-C ends the burst and prints the statistics
+    let stop = Arc::new(AtomicBool::new(false));
+    let handler_stop = Arc::clone(&stop);
+    ctrlc::set_handler(move || handler_stop.store(true, Ordering::Relaxed))?;
+
+    let mut usrp = Usrp::open(&options.args)?;
+    println!("Using device {}", usrp.get_motherboard_name(0)?);
+    for &channel in &options.channels {
+        configure_channel(&mut usrp, &options, channel)?;
+    }
+    let rate = usrp.get_tx_sample_rate(options.channels[0])?;
+    let start_time = match options.delay {
+        Some(delay) => Some(usrp.get_current_time(0)? + TimeSpec::new(delay as i64, delay.fract())),
+        None => None,
+    };
+
+    let args = StreamArgs::<Complex32>::builder()
+        .wire_format(options.wire.clone())
+        .channels(options.channels.clone())
+        .build();
+    let mut streamer = usrp.get_tx_stream(&args)?;
+    let mut events = Events::default();
+    let sent = match &options.file {
+        Some(path) => transmit_file(
+            &options,
+            path,
+            rate,
+            start_time,
+            &mut streamer,
+            &stop,
+            &mut events,
+        )?,
+        None => transmit_waveform(
+            &options,
+            rate,
+            start_time,
+            &mut streamer,
+            &stop,
+            &mut events,
+        )?,
+    };
+
+    // Wait for the end of the burst to be acknowledged
+    let mut timeout = SEND_TIMEOUT + options.delay.unwrap_or(0.0);
+    while let Some(message) = streamer.recv_async_msg(timeout)? {
+        events.record(&message);
+        if message.event_code() == AsyncEventCode::BurstAck {
+            break;
+        }
+        timeout = 0.1;
+    }
+    eprintln!();
+    println!("Samples sent per channel: {}", sent);
+    println!("{}", events);
+    Ok(())
+}
+
+fn configure_channel(
+    usrp: &mut Usrp,
+    options: &Options,
+    channel: usize,
+) -> Result<(), Box<dyn Error>> {
+    let mut tx = usrp.tx_channel(channel)?;
+    tx.set_sample_rate(options.rate)?;
+    tx.set_frequency(&TuneRequest::with_frequency(options.freq))?;
+    if let Some(gain) = options.gain {
+        tx.set_gain(gain, "")?;
+    }
+    if let Some(antenna) = &options.ant {
+        tx.set_antenna(antenna)?;
+    }
+    if let Some(bandwidth) = options.bw {
+        tx.set_bandwidth(bandwidth)?;
+    }
+    println!(
+        "Channel {}: rate {} samples/s, frequency {} Hz, gain {} dB, antenna {}, bandwidth {} Hz",
+        channel,
+        tx.get_sample_rate()?,
+        tx.get_frequency()?,
+        tx.get_gain("")?,
+        tx.get_antenna()?,
+        tx.get_bandwidth()?,
+    );
+    Ok(())
+}
+
+fn transmit_file(
+    options: &Options,
+    path: &Path,
+    rate: f64,
+    start_time: Option<TimeSpec>,
+    streamer: &mut TransmitStreamer<Complex32>,
+    stop: &AtomicBool,
+    events: &mut Events,
+) -> Result<u64, Box<dyn Error>> {
+    let playback = match options.format {
+        InputFormat::Fc64 => Playback::raw(path, FileFormat::Fc64),
+        InputFormat::Fc32 => Playback::raw(path, FileFormat::Fc32),
+        InputFormat::Sc16 => Playback::raw(path, FileFormat::Sc16),
+        InputFormat::Sc8 => Playback::raw(path, FileFormat::Sc8),
+        InputFormat::Sigmf => {
+            let playback = Playback::sigmf(path)?;
+            if let Some(file_rate) = playback.sample_rate() {
+                if file_rate != rate {
+                    eprintln!(
+                        "Warning: the recording has a sample rate of {} samples/s, but the device \
+                         is transmitting at {} samples/s",
+                        file_rate, rate
+                    );
+                }
+            }
+            playback
+        }
+    };
+    let repeat = if options.forever {
+        Repeat::Forever
+    } else {
+        Repeat::Times(options.repeat.unwrap_or(1))
+    };
+    let mut playback = playback.repeat(repeat).block_length(options.spb);
+    if let Some(time) = start_time {
+        playback = playback.start_time(time);
+    }
+    println!("Transmitting {}", playback.path().display());
+    let sent =
+        playback.transmit_with_messages(streamer, stop, |message| events.record(&message))?;
+    Ok(sent)
+}
+
+fn transmit_waveform(
+    options: &Options,
+    rate: f64,
+    start_time: Option<TimeSpec>,
+    streamer: &mut TransmitStreamer<Complex32>,
+    stop: &AtomicBool,
+    events: &mut Events,
+) -> Result<u64, Box<dyn Error>> {
+    if options.wave_type == WaveType::Chirp && options.chirp_period <= 0.0 {
+        return Err("--chirp-period must be greater than zero".into());
+    }
+    let waveform = match options.wave_type {
+        WaveType::Sine => Waveform::Sine {
+            frequency: options.wave_freq,
+        },
+        WaveType::Square => Waveform::Square {
+            frequency: options.wave_freq,
+        },
+        WaveType::Const => Waveform::Constant,
+        WaveType::Chirp => Waveform::Chirp {
+            start: options.wave_freq,
+            stop: options.chirp_stop,
+            period: options.chirp_period,
+        },
+        WaveType::Noise => Waveform::Noise,
+    };
+    let total = match (options.nsamps, options.duration) {
+        (Some(nsamps), _) => Some(nsamps),
+        (None, Some(duration)) => Some((duration * rate).round() as u64),
+        (None, None) => None,
+    };
+    println!(
+        "Transmitting {:?} with amplitude {}",
+        waveform, options.ampl
+    );
+
+    let mut generator = WaveformGenerator::new(waveform, rate, options.ampl);
+    let mut buffer = vec![Complex32::default(); options.spb];
+    let mut timeout = SEND_TIMEOUT + options.delay.unwrap_or(0.0);
+    let mut sent = 0u64;
+    loop {
+        if stop.load(Ordering::Relaxed) {
+            if sent != 0 {
+                // End the burst with an empty packet
+                let mut metadata = TransmitMetadata::with_flags(false, true, None);
+                let mut buffers = vec![&buffer[..0]; options.channels.len()];
+                streamer.send(&mut buffers, &mut metadata, SEND_TIMEOUT)?;
+            }
+            break;
+        }
+        let length = match total {
+            Some(total) => (total - sent).min(options.spb as u64) as usize,
+            None => options.spb,
+        };
+        let last = total.is_some_and(|total| sent + length as u64 == total);
+        let samples = &mut buffer[..length];
+        generator.fill(samples);
+
+        // Every channel gets the same samples
+        let mut offset = 0;
+        loop {
+            let mut metadata = if sent == 0 && offset == 0 {
+                TransmitMetadata::with_flags(true, last, start_time)
+            } else {
+                TransmitMetadata::with_flags(false, last, None)
+            };
+            let mut buffers = vec![&samples[offset..]; options.channels.len()];
+            let sent_now = streamer.send(&mut buffers, &mut metadata, timeout)?;
+            timeout = SEND_TIMEOUT;
+            offset += sent_now;
+            if offset >= length {
+                break;
+            }
+            if sent_now == 0 {
+                return Err("Timed out sending samples".into());
+            }
+        }
+        sent += length as u64;
+
+        while let Some(message) = streamer.recv_async_msg(0.0)? {
+            events.record(&message);
+        }
+        if last {
+            break;
+        }
+    }
+    Ok(sent)
+}
+
+/// Counts of asynchronous transmit events
+#[derive(Debug, Default)]
+struct Events {
+    underflows: u64,
+    late: u64,
+    sequence_errors: u64,
+    other: u64,
+}
+
+impl Events {
+    /// Counts an event and updates the counts shown on the terminal if it is an error
+    fn record(&mut self, message: &AsyncMetadata) {
+        match message.event_code() {
+            AsyncEventCode::Underflow | AsyncEventCode::UnderflowInPacket => self.underflows += 1,
+            AsyncEventCode::TimeError => self.late += 1,
+            AsyncEventCode::SequenceError | AsyncEventCode::SequenceErrorInBurst => {
+                self.sequence_errors += 1
+            }
+            code if code.is_error() => self.other += 1,
+            _ => return,
+        }
+        eprint!(
+            "\rUnderflows: {}, late packets: {}, sequence errors: {}",
+            self.underflows, self.late, self.sequence_errors
+        );
+        let _ = std::io::stderr().flush();
+    }
+}
+
+impl fmt::Display for Events {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Underflows: {}", self.underflows)?;
+        writeln!(f, "Late packets: {}", self.late)?;
+        writeln!(f, "Sequence errors: {}", self.sequence_errors)?;
+        write!(f, "Other errors: {}", self.other)
+    }
+}
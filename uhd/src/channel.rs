@@ -0,0 +1,383 @@
+use crate::error::Error;
+use crate::range::MetaRange;
+use crate::usrp::Usrp;
+use crate::{ReceiveInfo, SensorValue, TransmitInfo, TuneRequest, TuneResult};
+
+/// A handle to one receive channel of a USRP
+///
+/// This is returned by [`Usrp::rx_channel`], which checks that the channel exists. The methods
+/// have the same meanings as the corresponding `Usrp` methods with `rx` in their names.
+///
+/// ```no_run
+/// use uhd::{TuneRequest, Usrp};
+///
+/// let mut usrp = Usrp::open("")?;
+/// for channel in 0..usrp.get_num_rx_channels()? {
+///     let mut rx = usrp.rx_channel(channel)?;
+///     rx.set_sample_rate(1e6)?;
+///     rx.set_frequency(&TuneRequest::with_frequency(915e6))?;
+///     rx.set_normalized_gain(0.5)?;
+/// }
+/// # Ok::<(), uhd::Error>(())
+/// ```
+pub struct RxChannel<'usrp> {
+    usrp: &'usrp mut Usrp,
+    channel: usize,
+}
+
+impl<'usrp> RxChannel<'usrp> {
+    pub(crate) fn new(usrp: &'usrp mut Usrp, channel: usize) -> Self {
+        RxChannel { usrp, channel }
+    }
+
+    /// Returns the index of this channel
+    pub fn index(&self) -> usize {
+        self.channel
+    }
+
+    /// Returns information about the configuration of this channel
+    pub fn get_info(&self) -> Result<ReceiveInfo, Error> {
+        self.usrp.get_rx_info(self.channel)
+    }
+
+    /// Returns the name of the subdevice that this channel uses
+    pub fn get_subdev_name(&self) -> Result<String, Error> {
+        self.usrp.get_rx_subdev_name(self.channel)
+    }
+
+    /// Returns the center frequency
+    pub fn get_frequency(&self) -> Result<f64, Error> {
+        self.usrp.get_rx_frequency(self.channel)
+    }
+
+    /// Returns the range of center frequencies that can be tuned to
+    pub fn get_frequency_range(&self) -> Result<MetaRange, Error> {
+        self.usrp.get_rx_frequency_range(self.channel)
+    }
+
+    /// Returns the frequency range of the RF front end
+    pub fn get_fe_frequency_range(&self) -> Result<MetaRange, Error> {
+        self.usrp.get_fe_rx_freq_range(self.channel)
+    }
+
+    /// Sets the center frequency
+    pub fn set_frequency(&mut self, request: &TuneRequest) -> Result<TuneResult, Error> {
+        self.usrp.set_rx_frequency(request, self.channel)
+    }
+
+    /// Returns the gain of a gain element, or the overall gain if `name` is empty
+    pub fn get_gain(&self, name: &str) -> Result<f64, Error> {
+        self.usrp.get_rx_gain(self.channel, name)
+    }
+
+    /// Returns the names of the gain elements
+    pub fn get_gain_names(&self) -> Result<Vec<String>, Error> {
+        self.usrp.get_rx_gain_names(self.channel)
+    }
+
+    /// Returns the range of a gain element, or of the overall gain if `name` is empty
+    pub fn get_gain_range(&self, name: &str) -> Result<MetaRange, Error> {
+        self.usrp.get_rx_gain_range(self.channel, name)
+    }
+
+    /// Sets the gain of a gain element, or the overall gain if `name` is empty
+    pub fn set_gain(&mut self, gain: f64, name: &str) -> Result<(), Error> {
+        self.usrp.set_rx_gain(gain, self.channel, name)
+    }
+
+    /// Returns the overall gain, normalized to [0, 1]
+    pub fn get_normalized_gain(&self) -> Result<f64, Error> {
+        self.usrp.get_normalized_rx_gain(self.channel)
+    }
+
+    /// Sets the overall gain, normalized to [0, 1]
+    pub fn set_normalized_gain(&mut self, gain: f64) -> Result<(), Error> {
+        self.usrp.set_normalized_rx_gain(gain, self.channel)
+    }
+
+    /// Enables or disables the automatic gain control
+    pub fn set_agc_enabled(&mut self, enabled: bool) -> Result<(), Error> {
+        self.usrp.set_rx_agc_enabled(enabled, self.channel)
+    }
+
+    /// Returns the selected antenna
+    pub fn get_antenna(&self) -> Result<String, Error> {
+        self.usrp.get_rx_antenna(self.channel)
+    }
+
+    /// Returns the antennas that can be selected
+    pub fn get_antennas(&self) -> Result<Vec<String>, Error> {
+        self.usrp.get_rx_antennas(self.channel)
+    }
+
+    /// Selects an antenna
+    pub fn set_antenna(&mut self, antenna: &str) -> Result<(), Error> {
+        self.usrp.set_rx_antenna(antenna, self.channel)
+    }
+
+    /// Returns the analog bandwidth
+    pub fn get_bandwidth(&self) -> Result<f64, Error> {
+        self.usrp.get_rx_bandwidth(self.channel)
+    }
+
+    /// Returns the range of analog bandwidths
+    pub fn get_bandwidth_range(&self) -> Result<MetaRange, Error> {
+        self.usrp.get_rx_bandwidth_range(self.channel)
+    }
+
+    /// Sets the analog bandwidth
+    pub fn set_bandwidth(&mut self, bandwidth: f64) -> Result<(), Error> {
+        self.usrp.set_rx_bandwidth(bandwidth, self.channel)
+    }
+
+    /// Returns the sample rate
+    pub fn get_sample_rate(&self) -> Result<f64, Error> {
+        self.usrp.get_rx_sample_rate(self.channel)
+    }
+
+    /// Returns the ranges of sample rates
+    pub fn get_sample_rates(&self) -> Result<MetaRange, Error> {
+        self.usrp.get_rx_sample_rates(self.channel)
+    }
+
+    /// Sets the sample rate
+    pub fn set_sample_rate(&mut self, rate: f64) -> Result<(), Error> {
+        self.usrp.set_rx_sample_rate(rate, self.channel)
+    }
+
+    /// Enables or disables DC offset correction
+    pub fn set_dc_offset_enabled(&mut self, enabled: bool) -> Result<(), Error> {
+        self.usrp.set_rx_dc_offset_enabled(enabled, self.channel)
+    }
+
+    /// Returns the names of the local oscillators
+    pub fn get_lo_names(&self) -> Result<Vec<String>, Error> {
+        self.usrp.get_rx_lo_names(self.channel)
+    }
+
+    /// Returns the frequency of a local oscillator
+    pub fn get_lo_frequency(&self, name: &str) -> Result<f64, Error> {
+        self.usrp.get_rx_lo_frequency(self.channel, name)
+    }
+
+    /// Sets the frequency of a local oscillator and returns the frequency that it was actually
+    /// tuned to
+    pub fn set_lo_frequency(&mut self, frequency: f64, name: &str) -> Result<f64, Error> {
+        self.usrp.set_rx_lo_frequency(frequency, self.channel, name)
+    }
+
+    /// Returns the selected source of a local oscillator
+    pub fn get_lo_source(&self, name: &str) -> Result<String, Error> {
+        self.usrp.get_rx_lo_source(self.channel, name)
+    }
+
+    /// Returns the sources that can be selected for a local oscillator
+    pub fn get_lo_sources(&self, name: &str) -> Result<Vec<String>, Error> {
+        self.usrp.get_rx_lo_sources(self.channel, name)
+    }
+
+    /// Selects the source of a local oscillator, or of all local oscillators if `name` is
+    /// [`Usrp::ALL_LOS`]
+    pub fn set_lo_source(&mut self, source: &str, name: &str) -> Result<(), Error> {
+        self.usrp.set_rx_lo_source(source, self.channel, name)
+    }
+
+    /// Returns true if a local oscillator is exported
+    pub fn get_lo_export_enabled(&self, name: &str) -> Result<bool, Error> {
+        self.usrp.get_rx_lo_export_enabled(self.channel, name)
+    }
+
+    /// Enables or disables exporting a local oscillator
+    pub fn set_lo_export_enabled(&mut self, enabled: bool, name: &str) -> Result<(), Error> {
+        self.usrp
+            .set_rx_lo_export_enabled(enabled, self.channel, name)
+    }
+
+    /// Returns the names of the sensors of this channel
+    pub fn get_sensor_names(&self) -> Result<Vec<String>, Error> {
+        self.usrp.get_rx_sensor_names(self.channel)
+    }
+
+    /// Returns the value of a sensor
+    pub fn get_sensor(&self, name: &str) -> Result<SensorValue, Error> {
+        self.usrp.get_rx_sensor(name, self.channel)
+    }
+}
+
+/// A handle to one transmit channel of a USRP
+///
+/// This is returned by [`Usrp::tx_channel`], which checks that the channel exists. The methods
+/// have the same meanings as the corresponding `Usrp` methods with `tx` in their names.
+pub struct TxChannel<'usrp> {
+    usrp: &'usrp mut Usrp,
+    channel: usize,
+}
+
+impl<'usrp> TxChannel<'usrp> {
+    pub(crate) fn new(usrp: &'usrp mut Usrp, channel: usize) -> Self {
+        TxChannel { usrp, channel }
+    }
+
+    /// Returns the index of this channel
+    pub fn index(&self) -> usize {
+        self.channel
+    }
+
+    /// Returns information about the configuration of this channel
+    pub fn get_info(&self) -> Result<TransmitInfo, Error> {
+        self.usrp.get_tx_info(self.channel)
+    }
+
+    /// Returns the name of the subdevice that this channel uses
+    pub fn get_subdev_name(&self) -> Result<String, Error> {
+        self.usrp.get_tx_subdev_name(self.channel)
+    }
+
+    /// Returns the center frequency
+    pub fn get_frequency(&self) -> Result<f64, Error> {
+        self.usrp.get_tx_frequency(self.channel)
+    }
+
+    /// Returns the range of center frequencies that can be tuned to
+    pub fn get_frequency_range(&self) -> Result<MetaRange, Error> {
+        self.usrp.get_tx_frequency_range(self.channel)
+    }
+
+    /// Returns the frequency range of the RF front end
+    pub fn get_fe_frequency_range(&self) -> Result<MetaRange, Error> {
+        self.usrp.get_fe_tx_freq_range(self.channel)
+    }
+
+    /// Sets the center frequency
+    pub fn set_frequency(&mut self, request: &TuneRequest) -> Result<TuneResult, Error> {
+        self.usrp.set_tx_frequency(request, self.channel)
+    }
+
+    /// Returns the gain of a gain element, or the overall gain if `name` is empty
+    pub fn get_gain(&self, name: &str) -> Result<f64, Error> {
+        self.usrp.get_tx_gain(self.channel, name)
+    }
+
+    /// Returns the names of the gain elements
+    pub fn get_gain_names(&self) -> Result<Vec<String>, Error> {
+        self.usrp.get_tx_gain_names(self.channel)
+    }
+
+    /// Returns the range of a gain element, or of the overall gain if `name` is empty
+    pub fn get_gain_range(&self, name: &str) -> Result<MetaRange, Error> {
+        self.usrp.get_tx_gain_range(self.channel, name)
+    }
+
+    /// Sets the gain of a gain element, or the overall gain if `name` is empty
+    pub fn set_gain(&mut self, gain: f64, name: &str) -> Result<(), Error> {
+        self.usrp.set_tx_gain(gain, self.channel, name)
+    }
+
+    /// Returns the overall gain, normalized to [0, 1]
+    pub fn get_normalized_gain(&self) -> Result<f64, Error> {
+        self.usrp.get_normalized_tx_gain(self.channel)
+    }
+
+    /// Sets the overall gain, normalized to [0, 1]
+    pub fn set_normalized_gain(&mut self, gain: f64) -> Result<(), Error> {
+        self.usrp.set_normalized_tx_gain(gain, self.channel)
+    }
+
+    /// Returns the selected antenna
+    pub fn get_antenna(&self) -> Result<String, Error> {
+        self.usrp.get_tx_antenna(self.channel)
+    }
+
+    /// Returns the antennas that can be selected
+    pub fn get_antennas(&self) -> Result<Vec<String>, Error> {
+        self.usrp.get_tx_antennas(self.channel)
+    }
+
+    /// Selects an antenna
+    pub fn set_antenna(&mut self, antenna: &str) -> Result<(), Error> {
+        self.usrp.set_tx_antenna(antenna, self.channel)
+    }
+
+    /// Returns the analog bandwidth
+    pub fn get_bandwidth(&self) -> Result<f64, Error> {
+        self.usrp.get_tx_bandwidth(self.channel)
+    }
+
+    /// Returns the range of analog bandwidths
+    pub fn get_bandwidth_range(&self) -> Result<MetaRange, Error> {
+        self.usrp.get_tx_bandwidth_range(self.channel)
+    }
+
+    /// Sets the analog bandwidth
+    pub fn set_bandwidth(&mut self, bandwidth: f64) -> Result<(), Error> {
+        self.usrp.set_tx_bandwidth(bandwidth, self.channel)
+    }
+
+    /// Returns the sample rate
+    pub fn get_sample_rate(&self) -> Result<f64, Error> {
+        self.usrp.get_tx_sample_rate(self.channel)
+    }
+
+    /// Returns the ranges of sample rates
+    pub fn get_sample_rates(&self) -> Result<MetaRange, Error> {
+        self.usrp.get_tx_sample_rates(self.channel)
+    }
+
+    /// Sets the sample rate
+    pub fn set_sample_rate(&mut self, rate: f64) -> Result<(), Error> {
+        self.usrp.set_tx_sample_rate(rate, self.channel)
+    }
+
+    /// Returns the names of the local oscillators
+    pub fn get_lo_names(&self) -> Result<Vec<String>, Error> {
+        self.usrp.get_tx_lo_names(self.channel)
+    }
+
+    /// Returns the frequency of a local oscillator
+    pub fn get_lo_frequency(&self, name: &str) -> Result<f64, Error> {
+        self.usrp.get_tx_lo_frequency(self.channel, name)
+    }
+
+    /// Sets the frequency of a local oscillator and returns the frequency that it was actually
+    /// tuned to
+    pub fn set_lo_frequency(&mut self, frequency: f64, name: &str) -> Result<f64, Error> {
+        self.usrp.set_tx_lo_frequency(frequency, self.channel, name)
+    }
+
+    /// Returns the selected source of a local oscillator
+    pub fn get_lo_source(&self, name: &str) -> Result<String, Error> {
+        self.usrp.get_tx_lo_source(self.channel, name)
+    }
+
+    /// Returns the sources that can be selected for a local oscillator
+    pub fn get_lo_sources(&self, name: &str) -> Result<Vec<String>, Error> {
+        self.usrp.get_tx_lo_sources(self.channel, name)
+    }
+
+    /// Selects the source of a local oscillator, or of all local oscillators if `name` is
+    /// [`Usrp::ALL_LOS`]
+    pub fn set_lo_source(&mut self, source: &str, name: &str) -> Result<(), Error> {
+        self.usrp.set_tx_lo_source(source, self.channel, name)
+    }
+
+    /// Returns true if a local oscillator is exported
+    pub fn get_lo_export_enabled(&self, name: &str) -> Result<bool, Error> {
+        self.usrp.get_tx_lo_export_enabled(self.channel, name)
+    }
+
+    /// Enables or disables exporting a local oscillator
+    pub fn set_lo_export_enabled(&mut self, enabled: bool, name: &str) -> Result<(), Error> {
+        self.usrp
+            .set_tx_lo_export_enabled(enabled, self.channel, name)
+    }
+
+    /// Returns the names of the sensors of this channel
+    pub fn get_sensor_names(&self) -> Result<Vec<String>, Error> {
+        self.usrp.get_tx_sensor_names(self.channel)
+    }
+
+    /// Returns the value of a sensor
+    pub fn get_sensor(&self, name: &str) -> Result<SensorValue, Error> {
+        self.usrp.get_tx_sensor(name, self.channel)
+    }
+}
@@ -0,0 +1,572 @@
+//! Radio configuration profiles
+//!
+//! A [`RadioConfig`] describes the settings of a device in a form that can be stored in a TOML or
+//! JSON file, applied to many devices with [`RadioConfig::apply`], and captured from a configured
+//! device with [`RadioConfig::from_usrp`].
+//!
+//! ```toml
+//! clock_source = "external"
+//! master_clock_rate = 30.72e6
+//!
+//! [[rx_channels]]
+//! channel = 0
+//! sample_rate = 1e6
+//! frequency = 915e6
+//! gain = 30.0
+//! antenna = "RX2"
+//!
+//! [[tx_channels]]
+//! channel = 0
+//! sample_rate = 1e6
+//! normalized_gain = 0.8
+//! tune_request = { target_frequency = 2.4e9, rf = "auto", dsp = { manual = 0.0 } }
+//! ```
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::device::check_channel;
+use crate::error::{Error, Result};
+use crate::master_clock_rate::ChannelDirection;
+use crate::settings::{ConfigReport, ConfigValue};
+use crate::usrp::Usrp;
+use crate::{SubdevSpec, TuneRequest};
+
+/// Settings of a device
+///
+/// Settings that are `None` (or empty) are not changed when the configuration is applied.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RadioConfig {
+    /// The clock source of all motherboards
+    pub clock_source: Option<String>,
+    /// The time source of all motherboards
+    pub time_source: Option<String>,
+    /// The master clock rate of all motherboards, hertz
+    pub master_clock_rate: Option<f64>,
+    /// The receive subdevice specification of all motherboards, like `A:A A:B`
+    pub rx_subdev_spec: Option<String>,
+    /// The transmit subdevice specification of all motherboards
+    pub tx_subdev_spec: Option<String>,
+    /// Settings of receive channels, at most one for each channel index
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rx_channels: Vec<ChannelConfig>,
+    /// Settings of transmit channels, at most one for each channel index
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tx_channels: Vec<ChannelConfig>,
+}
+
+/// Settings of one receive or transmit channel
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ChannelConfig {
+    /// The index of the channel
+    pub channel: usize,
+    /// Sample rate, samples/second
+    pub sample_rate: Option<f64>,
+    /// Center frequency, hertz (cannot be used with `tune_request`)
+    pub frequency: Option<f64>,
+    /// A detailed request for the center frequency (cannot be used with `frequency`)
+    pub tune_request: Option<TuneRequest>,
+    /// Overall gain, decibels (cannot be used with `normalized_gain`)
+    pub gain: Option<f64>,
+    /// Overall gain, normalized to [0, 1] (cannot be used with `gain`)
+    pub normalized_gain: Option<f64>,
+    /// Gains of individual gain elements, decibels, applied after the overall gain
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub gains: BTreeMap<String, f64>,
+    /// Antenna name, like `TX/RX` or `RX2`
+    pub antenna: Option<String>,
+    /// Analog bandwidth, hertz
+    pub bandwidth: Option<f64>,
+    /// Automatic gain control (receive channels only)
+    pub agc: Option<bool>,
+    /// DC offset correction (receive channels only)
+    pub dc_offset: Option<bool>,
+}
+
+impl ChannelConfig {
+    /// Creates a configuration for a channel that does not change any settings
+    pub fn new(channel: usize) -> Self {
+        ChannelConfig {
+            channel,
+            ..ChannelConfig::default()
+        }
+    }
+
+    /// Returns the tune request for the frequency or tune_request setting
+    fn frequency_request(&self) -> Option<TuneRequest> {
+        self.tune_request
+            .clone()
+            .or_else(|| self.frequency.map(TuneRequest::with_frequency))
+    }
+
+    fn validate(&self, direction: ChannelDirection) -> Result<()> {
+        let invalid = |problem: &str| {
+            Err(Error::InvalidConfig(format!(
+                "{} channel {}: {}",
+                direction, self.channel, problem
+            )))
+        };
+        if self.frequency.is_some() && self.tune_request.is_some() {
+            return invalid("frequency and tune_request cannot both be set");
+        }
+        if self.gain.is_some() && self.normalized_gain.is_some() {
+            return invalid("gain and normalized_gain cannot both be set");
+        }
+        if let Some(gain) = self.normalized_gain {
+            if !(0.0..=1.0).contains(&gain) {
+                return invalid("normalized_gain must be in the range [0, 1]");
+            }
+        }
+        if direction == ChannelDirection::Transmit
+            && (self.agc.is_some() || self.dc_offset.is_some())
+        {
+            return invalid("agc and dc_offset can only be set for receive channels");
+        }
+        Ok(())
+    }
+}
+
+impl RadioConfig {
+    /// Parses and checks a configuration in TOML format
+    pub fn from_toml(text: &str) -> Result<Self> {
+        let config: RadioConfig =
+            toml::from_str(text).map_err(|e| Error::InvalidConfig(e.to_string()))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Parses and checks a configuration in JSON format
+    pub fn from_json(text: &str) -> Result<Self> {
+        let config: RadioConfig =
+            serde_json::from_str(text).map_err(|e| Error::InvalidConfig(e.to_string()))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Formats this configuration as TOML
+    pub fn to_toml(&self) -> Result<String> {
+        toml::to_string_pretty(self).map_err(|e| Error::InvalidConfig(e.to_string()))
+    }
+
+    /// Formats this configuration as JSON
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(self).map_err(|e| Error::InvalidConfig(e.to_string()))
+    }
+
+    /// Reads a configuration from a file
+    ///
+    /// Files with the extension `json` are parsed as JSON, and all other files are parsed as
+    /// TOML.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)?;
+        if is_json(path) {
+            RadioConfig::from_json(&text)
+        } else {
+            RadioConfig::from_toml(&text)
+        }
+    }
+
+    /// Writes this configuration to a file, in JSON format if the extension is `json` and
+    /// TOML format otherwise
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        let text = if is_json(path) {
+            self.to_json()?
+        } else {
+            self.to_toml()?
+        };
+        fs::write(path, text)?;
+        Ok(())
+    }
+
+    /// Checks that the settings are consistent, without accessing a device
+    pub fn validate(&self) -> Result<()> {
+        if let Some(rate) = self.master_clock_rate {
+            if !(rate.is_finite() && rate > 0.0) {
+                return Err(Error::InvalidConfig(format!(
+                    "master_clock_rate {} must be positive",
+                    rate
+                )));
+            }
+        }
+        for spec in self.rx_subdev_spec.iter().chain(&self.tx_subdev_spec) {
+            spec.parse::<SubdevSpec>()?;
+        }
+        for (direction, channels) in [
+            (ChannelDirection::Receive, &self.rx_channels),
+            (ChannelDirection::Transmit, &self.tx_channels),
+        ] {
+            for (i, channel) in channels.iter().enumerate() {
+                channel.validate(direction)?;
+                if channels[..i]
+                    .iter()
+                    .any(|other| other.channel == channel.channel)
+                {
+                    return Err(Error::InvalidConfig(format!(
+                        "{} channel {} is configured more than once",
+                        direction, channel.channel
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Applies this configuration to a device
+    ///
+    /// Motherboard settings are applied to all motherboards, then the channel settings are
+    /// applied in the order sample rate, antenna, bandwidth, frequency, AGC, gain, and DC offset.
+    /// The configuration is checked before anything is changed. This includes checking that all
+    /// channels exist with the subdevice specifications that the configuration sets.
+    ///
+    /// A setting that the device rejects is recorded in the report, and the other settings are
+    /// still applied. The report also compares each requested value with the value that the
+    /// device actually uses (read from motherboard 0 for motherboard settings). A value that
+    /// can't be read back is reported as unknown.
+    ///
+    /// An error is returned if a configured channel doesn't exist after the subdevice
+    /// specifications are applied, for example because the device rejected a specification.
+    pub fn apply(&self, usrp: &mut Usrp) -> Result<ConfigReport> {
+        self.validate()?;
+        let num_mboards = usrp.get_num_motherboards()?;
+        let num_channels = |spec: &Option<String>, current: usize| {
+            channels_with_spec(spec.as_deref(), current, num_mboards)
+        };
+        for (direction, channels, num_channels) in [
+            (
+                ChannelDirection::Receive,
+                &self.rx_channels,
+                num_channels(&self.rx_subdev_spec, usrp.get_num_rx_channels()?)?,
+            ),
+            (
+                ChannelDirection::Transmit,
+                &self.tx_channels,
+                num_channels(&self.tx_subdev_spec, usrp.get_num_tx_channels()?)?,
+            ),
+        ] {
+            for channel in channels {
+                check_channel(channel.channel, num_channels, direction)?;
+            }
+        }
+
+        let mut report = ConfigReport::default();
+        if let Some(source) = &self.clock_source {
+            let result = usrp.set_clock_source(source, Usrp::ALL_MBOARDS);
+            report.push_result(
+                "clock source".to_string(),
+                ConfigValue::Text(source.clone()),
+                result,
+                usrp.get_clock_source(0).ok().map(ConfigValue::Text),
+            );
+        }
+        if let Some(source) = &self.time_source {
+            let result = usrp.set_time_source(source, Usrp::ALL_MBOARDS);
+            report.push_result(
+                "time source".to_string(),
+                ConfigValue::Text(source.clone()),
+                result,
+                usrp.get_time_source(0).ok().map(ConfigValue::Text),
+            );
+        }
+        if let Some(rate) = self.master_clock_rate {
+            let result = usrp
+                .set_master_clock_rate(rate, Usrp::ALL_MBOARDS)
+                .map(|_| ());
+            report.push_result(
+                "master clock rate".to_string(),
+                ConfigValue::Number(rate),
+                result,
+                usrp.get_master_clock_rate(0).ok().map(ConfigValue::Number),
+            );
+        }
+        if let Some(spec) = &self.rx_subdev_spec {
+            let spec: SubdevSpec = spec.parse()?;
+            let result = usrp.set_rx_subdev_spec(&spec, Usrp::ALL_MBOARDS);
+            report.push_result(
+                "RX subdevice specification".to_string(),
+                ConfigValue::Text(spec.to_string()),
+                result,
+                usrp.get_rx_subdev_spec(0)
+                    .ok()
+                    .map(|spec| ConfigValue::Text(spec.to_string())),
+            );
+        }
+        if let Some(spec) = &self.tx_subdev_spec {
+            let spec: SubdevSpec = spec.parse()?;
+            let result = usrp.set_tx_subdev_spec(&spec, Usrp::ALL_MBOARDS);
+            report.push_result(
+                "TX subdevice specification".to_string(),
+                ConfigValue::Text(spec.to_string()),
+                result,
+                usrp.get_tx_subdev_spec(0)
+                    .ok()
+                    .map(|spec| ConfigValue::Text(spec.to_string())),
+            );
+        }
+
+        for config in &self.rx_channels {
+            let prefix = format!("RX {}", config.channel);
+            let mut rx = usrp.rx_channel(config.channel)?;
+            if let Some(rate) = config.sample_rate {
+                let result = rx.set_sample_rate(rate);
+                let actual = rx.get_sample_rate();
+                report.push_number(&prefix, "sample rate", rate, result, actual);
+            }
+            if let Some(antenna) = &config.antenna {
+                let result = rx.set_antenna(antenna);
+                let actual = rx.get_antenna();
+                report.push_text(&prefix, "antenna", antenna, result, actual);
+            }
+            if let Some(bandwidth) = config.bandwidth {
+                let result = rx.set_bandwidth(bandwidth);
+                let actual = rx.get_bandwidth();
+                report.push_number(&prefix, "bandwidth", bandwidth, result, actual);
+            }
+            if let Some(request) = config.frequency_request() {
+                let result = rx.set_frequency(&request).map(|_| ());
+                let frequency = request.target_frequency;
+                let actual = rx.get_frequency();
+                report.push_number(&prefix, "frequency", frequency, result, actual);
+            }
+            if let Some(enabled) = config.agc {
+                let result = rx.set_agc_enabled(enabled);
+                report.push_unread(&prefix, "AGC", ConfigValue::Bool(enabled), result);
+            }
+            if let Some(gain) = config.gain {
+                let result = rx.set_gain(gain, "");
+                report.push_number(&prefix, "gain", gain, result, rx.get_gain(""));
+            }
+            if let Some(gain) = config.normalized_gain {
+                let result = rx.set_normalized_gain(gain);
+                let actual = rx.get_normalized_gain();
+                report.push_number(&prefix, "normalized gain", gain, result, actual);
+            }
+            for (name, &gain) in &config.gains {
+                let result = rx.set_gain(gain, name);
+                let setting = format!("gain {}", name);
+                report.push_number(&prefix, &setting, gain, result, rx.get_gain(name));
+            }
+            if let Some(enabled) = config.dc_offset {
+                let result = rx.set_dc_offset_enabled(enabled);
+                let requested = ConfigValue::Bool(enabled);
+                report.push_unread(&prefix, "DC offset correction", requested, result);
+            }
+        }
+        for config in &self.tx_channels {
+            let prefix = format!("TX {}", config.channel);
+            let mut tx = usrp.tx_channel(config.channel)?;
+            if let Some(rate) = config.sample_rate {
+                let result = tx.set_sample_rate(rate);
+                let actual = tx.get_sample_rate();
+                report.push_number(&prefix, "sample rate", rate, result, actual);
+            }
+            if let Some(antenna) = &config.antenna {
+                let result = tx.set_antenna(antenna);
+                let actual = tx.get_antenna();
+                report.push_text(&prefix, "antenna", antenna, result, actual);
+            }
+            if let Some(bandwidth) = config.bandwidth {
+                let result = tx.set_bandwidth(bandwidth);
+                let actual = tx.get_bandwidth();
+                report.push_number(&prefix, "bandwidth", bandwidth, result, actual);
+            }
+            if let Some(request) = config.frequency_request() {
+                let result = tx.set_frequency(&request).map(|_| ());
+                let frequency = request.target_frequency;
+                let actual = tx.get_frequency();
+                report.push_number(&prefix, "frequency", frequency, result, actual);
+            }
+            if let Some(gain) = config.gain {
+                let result = tx.set_gain(gain, "");
+                report.push_number(&prefix, "gain", gain, result, tx.get_gain(""));
+            }
+            if let Some(gain) = config.normalized_gain {
+                let result = tx.set_normalized_gain(gain);
+                let actual = tx.get_normalized_gain();
+                report.push_number(&prefix, "normalized gain", gain, result, actual);
+            }
+            for (name, &gain) in &config.gains {
+                let result = tx.set_gain(gain, name);
+                let setting = format!("gain {}", name);
+                report.push_number(&prefix, &setting, gain, result, tx.get_gain(name));
+            }
+        }
+        Ok(report)
+    }
+
+    /// Reads the current settings of a device
+    ///
+    /// Motherboard settings are read from motherboard 0, and all channels are included. Settings
+    /// that can't be read are left as `None`. AGC and DC offset correction can't be read, and
+    /// gains are captured as overall gains.
+    ///
+    /// An error is returned only if the numbers of channels can't be read.
+    pub fn from_usrp(usrp: &Usrp) -> Result<Self> {
+        let rx_channels = (0..usrp.get_num_rx_channels()?)
+            .map(|channel| ChannelConfig {
+                sample_rate: usrp.get_rx_sample_rate(channel).ok(),
+                frequency: usrp.get_rx_frequency(channel).ok(),
+                gain: usrp.get_rx_gain(channel, "").ok(),
+                antenna: usrp.get_rx_antenna(channel).ok(),
+                bandwidth: usrp.get_rx_bandwidth(channel).ok(),
+                ..ChannelConfig::new(channel)
+            })
+            .collect();
+        let tx_channels = (0..usrp.get_num_tx_channels()?)
+            .map(|channel| ChannelConfig {
+                sample_rate: usrp.get_tx_sample_rate(channel).ok(),
+                frequency: usrp.get_tx_frequency(channel).ok(),
+                gain: usrp.get_tx_gain(channel, "").ok(),
+                antenna: usrp.get_tx_antenna(channel).ok(),
+                bandwidth: usrp.get_tx_bandwidth(channel).ok(),
+                ..ChannelConfig::new(channel)
+            })
+            .collect();
+        Ok(RadioConfig {
+            clock_source: usrp.get_clock_source(0).ok(),
+            time_source: usrp.get_time_source(0).ok(),
+            master_clock_rate: usrp.get_master_clock_rate(0).ok(),
+            rx_subdev_spec: usrp.get_rx_subdev_spec(0).ok().map(|spec| spec.to_string()),
+            tx_subdev_spec: usrp.get_tx_subdev_spec(0).ok().map(|spec| spec.to_string()),
+            rx_channels,
+            tx_channels,
+        })
+    }
+}
+
+/// Returns the number of channels that a device will have after a subdevice specification (if
+/// any) is applied to all of its motherboards
+///
+/// Each motherboard has one channel for each subdevice in the specification.
+fn channels_with_spec(spec: Option<&str>, current: usize, num_mboards: usize) -> Result<usize> {
+    match spec {
+        Some(spec) => Ok(spec.parse::<SubdevSpec>()?.len() * num_mboards),
+        None => Ok(current),
+    }
+}
+
+fn is_json(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("json"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{channels_with_spec, ChannelConfig, ConfigReport, ConfigValue, RadioConfig};
+    use crate::Error;
+
+    const EXAMPLE: &str = r#"
+clock_source = "external"
+master_clock_rate = 30.72e6
+rx_subdev_spec = "A:A A:B"
+
+[[rx_channels]]
+channel = 0
+sample_rate = 1e6
+frequency = 915e6
+gain = 30.0
+antenna = "RX2"
+agc = false
+
+[rx_channels.gains]
+PGA = 10.5
+
+[[tx_channels]]
+channel = 1
+normalized_gain = 0.8
+tune_request = { target_frequency = 2.4e9, rf = "auto", dsp = { manual = 0.0 } }
+"#;
+
+    #[test]
+    fn parse_toml() {
+        let config = RadioConfig::from_toml(EXAMPLE).unwrap();
+        assert_eq!(config.clock_source.as_deref(), Some("external"));
+        assert_eq!(config.time_source, None);
+        assert_eq!(config.master_clock_rate, Some(30.72e6));
+        let rx = &config.rx_channels[0];
+        assert_eq!(rx.frequency, Some(915e6));
+        assert_eq!(rx.gains["PGA"], 10.5);
+        assert_eq!(rx.agc, Some(false));
+        let tx = &config.tx_channels[0];
+        assert_eq!(tx.channel, 1);
+        assert_eq!(tx.frequency_request().unwrap().target_frequency, 2.4e9);
+    }
+
+    #[test]
+    fn toml_and_json_round_trip() {
+        let config = RadioConfig::from_toml(EXAMPLE).unwrap();
+        let toml = config.to_toml().unwrap();
+        assert_eq!(RadioConfig::from_toml(&toml).unwrap(), config);
+        let json = config.to_json().unwrap();
+        assert_eq!(RadioConfig::from_json(&json).unwrap(), config);
+    }
+
+    #[test]
+    fn invalid_configs() {
+        let invalid =
+            |text: &str| matches!(RadioConfig::from_toml(text), Err(Error::InvalidConfig(_)));
+        assert!(invalid("clock_sorce = \"external\""));
+        assert!(invalid(
+            "[[rx_channels]]\nchannel = 0\ngain = 1.0\nnormalized_gain = 0.5"
+        ));
+        assert!(invalid(
+            "[[rx_channels]]\nchannel = 0\nnormalized_gain = 1.5"
+        ));
+        assert!(invalid("[[tx_channels]]\nchannel = 0\nagc = true"));
+        assert!(invalid(
+            "[[rx_channels]]\nchannel = 1\n[[rx_channels]]\nchannel = 1"
+        ));
+        assert!(invalid("master_clock_rate = -1.0"));
+
+        let mut config = RadioConfig::default();
+        config.rx_channels.push(ChannelConfig {
+            frequency: Some(1e9),
+            tune_request: Some(crate::TuneRequest::with_frequency(1e9)),
+            ..ChannelConfig::new(0)
+        });
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn report() {
+        let mut report = ConfigReport::default();
+        report.push_number("RX 0", "sample rate", 1e6, Ok(()), Ok(1_000_000.000_001));
+        report.push_number("RX 0", "gain", 30.3, Ok(()), Ok(30.0));
+        report.push_unread("RX 0", "AGC", ConfigValue::Bool(true), Ok(()));
+        report.push_text("TX 0", "antenna", "TX/RX", Ok(()), Ok("TX/RX".to_string()));
+        report.push_number("TX 0", "bandwidth", 1e6, Ok(()), Err(Error::NotImplemented));
+        let dc_offset = ConfigValue::Bool(false);
+        report.push_unread("RX 1", "DC offset correction", dc_offset, Err(Error::Value));
+        assert!(!report.is_exact());
+        let coerced = report.coerced().map(|s| s.name()).collect::<Vec<_>>();
+        assert_eq!(coerced, vec!["RX 0 gain"]);
+        let failed = report.failed().map(|s| s.name()).collect::<Vec<_>>();
+        assert_eq!(failed, vec!["RX 1 DC offset correction"]);
+        assert_eq!(
+            report.to_string(),
+            format!(
+                "RX 0 sample rate: requested 1000000, actual 1000000.000001\n\
+                 RX 0 gain: requested 30.3, actual 30 (coerced)\n\
+                 RX 0 AGC: requested true, actual unknown\n\
+                 TX 0 antenna: requested \"TX/RX\", actual \"TX/RX\"\n\
+                 TX 0 bandwidth: requested 1000000, actual unknown\n\
+                 RX 1 DC offset correction: requested false, actual unknown (failed: {})\n",
+                Error::Value
+            )
+        );
+    }
+
+    #[test]
+    fn channels_with_subdev_spec() {
+        assert_eq!(channels_with_spec(None, 2, 1).unwrap(), 2);
+        assert_eq!(channels_with_spec(Some("A:0"), 2, 1).unwrap(), 1);
+        assert_eq!(channels_with_spec(Some("A:0 B:0"), 1, 1).unwrap(), 2);
+        assert_eq!(channels_with_spec(Some("A:0 A:1"), 4, 2).unwrap(), 4);
+    }
+}
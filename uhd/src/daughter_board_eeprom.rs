@@ -40,7 +40,7 @@ impl DaughterBoardEeprom {
         check_status(unsafe { uhd_sys::uhd_dboard_eeprom_set_revision(self.0, revision) })
     }
 
-    pub(crate) fn handle(&mut self) -> uhd_sys::uhd_dboard_eeprom_handle {
+    pub(crate) fn handle(&self) -> uhd_sys::uhd_dboard_eeprom_handle {
         self.0
     }
 }
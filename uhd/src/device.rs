@@ -0,0 +1,427 @@
+//! Traits that abstract over USRP devices and their streamers
+//!
+//! [`Usrp`] implements [`Device`] by calling into UHD. [`SimUsrp`](crate::sim::SimUsrp) implements
+//! it in Rust without any hardware, so that application logic written against these traits can
+//! be tested on machines without radios.
+
+use crate::error::Error;
+use crate::master_clock_rate::ChannelDirection;
+use crate::range::MetaRange;
+use crate::stream::{Sample, StreamArgs, StreamCommand};
+use crate::{
+    AsyncMetadata, ReceiveMetadata, ReceiveStreamer, SensorValue, TimeSpec, TransmitMetadata,
+    TransmitStreamer, TuneRequest, TuneResult, Usrp,
+};
+
+/// A stream that receives samples
+///
+/// The methods have the same meanings as the corresponding methods of [`ReceiveStreamer`].
+pub trait ReceiveStream<I> {
+    /// Returns the number of channels that this stream receives
+    fn num_channels(&self) -> usize;
+
+    /// Sends a stream command to start or stop streaming
+    fn send_command(&mut self, command: &StreamCommand) -> Result<(), Error>;
+
+    /// Receives samples into one buffer per channel
+    fn receive(
+        &mut self,
+        buffers: &mut [&mut [I]],
+        timeout: f64,
+        one_packet: bool,
+    ) -> Result<ReceiveMetadata, Error>;
+}
+
+/// A stream that transmits samples
+///
+/// The methods have the same meanings as the corresponding methods of [`TransmitStreamer`].
+pub trait TransmitStream<I> {
+    /// Returns the number of channels that this stream transmits
+    fn num_channels(&self) -> usize;
+
+    /// Transmits samples from one buffer per channel and returns the number of samples sent
+    fn send(
+        &mut self,
+        buffers: &mut [&[I]],
+        metadata: &mut TransmitMetadata,
+        timeout: f64,
+    ) -> Result<usize, Error>;
+
+    /// Receives an asynchronous message about a previous transmit operation
+    fn recv_async_msg(&mut self, timeout: f64) -> Result<Option<AsyncMetadata>, Error>;
+}
+
+/// A USRP-like device
+///
+/// The methods have the same meanings and arguments as the corresponding methods of [`Usrp`].
+pub trait Device {
+    /// The type of stream returned by [`get_rx_stream`](Self::get_rx_stream)
+    type ReceiveStream<'device, I>: ReceiveStream<I>
+    where
+        Self: 'device,
+        I: Sample + 'device;
+
+    /// The type of stream returned by [`get_tx_stream`](Self::get_tx_stream)
+    type TransmitStream<'device, I>: TransmitStream<I>
+    where
+        Self: 'device,
+        I: Sample + 'device;
+
+    /// Returns the number of motherboards
+    fn get_num_motherboards(&self) -> Result<usize, Error>;
+    /// Returns the number of receive channels
+    fn get_num_rx_channels(&self) -> Result<usize, Error>;
+    /// Returns the number of transmit channels
+    fn get_num_tx_channels(&self) -> Result<usize, Error>;
+    /// Returns the name of a motherboard
+    fn get_motherboard_name(&self, mboard: usize) -> Result<String, Error>;
+    /// Returns the master clock rate of a motherboard in hertz
+    fn get_master_clock_rate(&self, mboard: usize) -> Result<f64, Error>;
+
+    /// Returns the names of the antennas that a receive channel can use
+    fn get_rx_antennas(&self, channel: usize) -> Result<Vec<String>, Error>;
+    /// Returns the selected antenna of a receive channel
+    fn get_rx_antenna(&self, channel: usize) -> Result<String, Error>;
+    /// Selects the antenna of a receive channel
+    fn set_rx_antenna(&mut self, antenna: &str, channel: usize) -> Result<(), Error>;
+    /// Returns the names of the antennas that a transmit channel can use
+    fn get_tx_antennas(&self, channel: usize) -> Result<Vec<String>, Error>;
+    /// Returns the selected antenna of a transmit channel
+    fn get_tx_antenna(&self, channel: usize) -> Result<String, Error>;
+    /// Selects the antenna of a transmit channel
+    fn set_tx_antenna(&mut self, antenna: &str, channel: usize) -> Result<(), Error>;
+
+    /// Returns the analog bandwidth of a receive channel in hertz
+    fn get_rx_bandwidth(&self, channel: usize) -> Result<f64, Error>;
+    /// Returns the supported analog bandwidths of a receive channel
+    fn get_rx_bandwidth_range(&self, channel: usize) -> Result<MetaRange, Error>;
+    /// Sets the analog bandwidth of a receive channel in hertz
+    fn set_rx_bandwidth(&mut self, bandwidth: f64, channel: usize) -> Result<(), Error>;
+    /// Returns the analog bandwidth of a transmit channel in hertz
+    fn get_tx_bandwidth(&self, channel: usize) -> Result<f64, Error>;
+    /// Returns the supported analog bandwidths of a transmit channel
+    fn get_tx_bandwidth_range(&self, channel: usize) -> Result<MetaRange, Error>;
+    /// Sets the analog bandwidth of a transmit channel in hertz
+    fn set_tx_bandwidth(&mut self, bandwidth: f64, channel: usize) -> Result<(), Error>;
+
+    /// Returns the center frequency of a receive channel in hertz
+    fn get_rx_frequency(&self, channel: usize) -> Result<f64, Error>;
+    /// Returns the supported center frequencies of a receive channel
+    fn get_rx_frequency_range(&self, channel: usize) -> Result<MetaRange, Error>;
+    /// Tunes a receive channel and returns the frequencies that were actually used
+    fn set_rx_frequency(
+        &mut self,
+        request: &TuneRequest,
+        channel: usize,
+    ) -> Result<TuneResult, Error>;
+    /// Returns the center frequency of a transmit channel in hertz
+    fn get_tx_frequency(&self, channel: usize) -> Result<f64, Error>;
+    /// Returns the supported center frequencies of a transmit channel
+    fn get_tx_frequency_range(&self, channel: usize) -> Result<MetaRange, Error>;
+    /// Tunes a transmit channel and returns the frequencies that were actually used
+    fn set_tx_frequency(
+        &mut self,
+        request: &TuneRequest,
+        channel: usize,
+    ) -> Result<TuneResult, Error>;
+
+    /// Returns the gain of a receive channel, or of one gain element if `name` is not empty
+    fn get_rx_gain(&self, channel: usize, name: &str) -> Result<f64, Error>;
+    /// Returns the names of the gain elements of a receive channel
+    fn get_rx_gain_names(&self, channel: usize) -> Result<Vec<String>, Error>;
+    /// Returns the supported gains of a receive channel, or of one gain element if `name`
+    /// is not empty
+    fn get_rx_gain_range(&self, channel: usize, name: &str) -> Result<MetaRange, Error>;
+    /// Sets the gain of a receive channel, or of one gain element if `name` is not empty
+    fn set_rx_gain(&mut self, gain: f64, channel: usize, name: &str) -> Result<(), Error>;
+    /// Returns the gain of a transmit channel, or of one gain element if `name` is not empty
+    fn get_tx_gain(&self, channel: usize, name: &str) -> Result<f64, Error>;
+    /// Returns the names of the gain elements of a transmit channel
+    fn get_tx_gain_names(&self, channel: usize) -> Result<Vec<String>, Error>;
+    /// Returns the supported gains of a transmit channel, or of one gain element if `name`
+    /// is not empty
+    fn get_tx_gain_range(&self, channel: usize, name: &str) -> Result<MetaRange, Error>;
+    /// Sets the gain of a transmit channel, or of one gain element if `name` is not empty
+    fn set_tx_gain(&mut self, gain: f64, channel: usize, name: &str) -> Result<(), Error>;
+
+    /// Returns the sample rate of a receive channel in samples per second
+    fn get_rx_sample_rate(&self, channel: usize) -> Result<f64, Error>;
+    /// Returns the supported sample rates of a receive channel
+    fn get_rx_sample_rates(&self, channel: usize) -> Result<MetaRange, Error>;
+    /// Sets the sample rate of a receive channel in samples per second
+    fn set_rx_sample_rate(&mut self, rate: f64, channel: usize) -> Result<(), Error>;
+    /// Returns the sample rate of a transmit channel in samples per second
+    fn get_tx_sample_rate(&self, channel: usize) -> Result<f64, Error>;
+    /// Returns the supported sample rates of a transmit channel
+    fn get_tx_sample_rates(&self, channel: usize) -> Result<MetaRange, Error>;
+    /// Sets the sample rate of a transmit channel in samples per second
+    fn set_tx_sample_rate(&mut self, rate: f64, channel: usize) -> Result<(), Error>;
+
+    /// Returns the names of the sensors of a motherboard
+    fn get_mboard_sensor_names(&self, mboard: usize) -> Result<Vec<String>, Error>;
+    /// Reads a sensor of a motherboard
+    fn get_mboard_sensor(&self, name: &str, mboard: usize) -> Result<SensorValue, Error>;
+    /// Returns the names of the sensors of a receive channel
+    fn get_rx_sensor_names(&self, channel: usize) -> Result<Vec<String>, Error>;
+    /// Reads a sensor of a receive channel
+    fn get_rx_sensor(&self, name: &str, channel: usize) -> Result<SensorValue, Error>;
+    /// Returns the names of the sensors of a transmit channel
+    fn get_tx_sensor_names(&self, channel: usize) -> Result<Vec<String>, Error>;
+    /// Reads a sensor of a transmit channel
+    fn get_tx_sensor(&self, name: &str, channel: usize) -> Result<SensorValue, Error>;
+
+    /// Returns the clock source of a motherboard
+    fn get_clock_source(&self, mboard: usize) -> Result<String, Error>;
+    /// Returns the clock sources that a motherboard can use
+    fn get_clock_sources(&self, mboard: usize) -> Result<Vec<String>, Error>;
+    /// Sets the clock source of a motherboard
+    fn set_clock_source(&self, source: &str, mboard: usize) -> Result<(), Error>;
+    /// Returns the time source of a motherboard
+    fn get_time_source(&self, mboard: usize) -> Result<String, Error>;
+    /// Returns the time sources that a motherboard can use
+    fn get_time_sources(&self, mboard: usize) -> Result<Vec<String>, Error>;
+    /// Sets the time source of a motherboard
+    fn set_time_source(&self, source: &str, mboard: usize) -> Result<(), Error>;
+
+    /// Returns the current time of a motherboard
+    fn get_current_time(&self, mboard: usize) -> Result<TimeSpec, Error>;
+    /// Sets the time of all motherboards at the next PPS edge after synchronizing to it
+    fn set_time_unknown_pps(&self, full_secs: i64, frac_secs: f64) -> Result<(), Error>;
+    /// Sets the time of a motherboard at the next PPS edge
+    fn set_time_next_pps(&self, full_secs: i64, frac_secs: f64, mboard: usize)
+        -> Result<(), Error>;
+    /// Returns true if the times of all motherboards are synchronized
+    fn get_time_synchronized(&self) -> Result<bool, Error>;
+    /// Sets the time at which future commands to a motherboard take effect
+    fn set_command_time(&mut self, time: TimeSpec, mboard: usize) -> Result<(), Error>;
+    /// Makes future commands to a motherboard take effect immediately
+    fn clear_command_time(&mut self, mboard: usize) -> Result<(), Error>;
+
+    /// Opens a stream that receives samples
+    fn get_rx_stream<I>(
+        &mut self,
+        args: &StreamArgs<I>,
+    ) -> Result<Self::ReceiveStream<'_, I>, Error>
+    where
+        I: Sample;
+    /// Opens a stream that transmits samples
+    fn get_tx_stream<I>(
+        &mut self,
+        args: &StreamArgs<I>,
+    ) -> Result<Self::TransmitStream<'_, I>, Error>
+    where
+        I: Sample;
+}
+
+impl<I> ReceiveStream<I> for ReceiveStreamer<'_, I> {
+    fn num_channels(&self) -> usize {
+        ReceiveStreamer::num_channels(self)
+    }
+    fn send_command(&mut self, command: &StreamCommand) -> Result<(), Error> {
+        ReceiveStreamer::send_command(self, command)
+    }
+    fn receive(
+        &mut self,
+        buffers: &mut [&mut [I]],
+        timeout: f64,
+        one_packet: bool,
+    ) -> Result<ReceiveMetadata, Error> {
+        ReceiveStreamer::receive(self, buffers, timeout, one_packet)
+    }
+}
+
+impl<I> TransmitStream<I> for TransmitStreamer<'_, I> {
+    fn num_channels(&self) -> usize {
+        TransmitStreamer::num_channels(self)
+    }
+    fn send(
+        &mut self,
+        buffers: &mut [&[I]],
+        metadata: &mut TransmitMetadata,
+        timeout: f64,
+    ) -> Result<usize, Error> {
+        TransmitStreamer::send(self, buffers, metadata, timeout)
+    }
+    fn recv_async_msg(&mut self, timeout: f64) -> Result<Option<AsyncMetadata>, Error> {
+        TransmitStreamer::recv_async_msg(self, timeout)
+    }
+}
+
+/// Implements Device methods by calling the inherent methods with the same names
+macro_rules! delegate {
+    ($(fn $name:ident(&self $(, $arg:ident: $ty:ty)*) -> $ret:ty;)*) => {
+        $(
+            fn $name(&self $(, $arg: $ty)*) -> $ret {
+                Usrp::$name(self $(, $arg)*)
+            }
+        )*
+    };
+    ($(fn $name:ident(&mut self $(, $arg:ident: $ty:ty)*) -> $ret:ty;)*) => {
+        $(
+            fn $name(&mut self $(, $arg: $ty)*) -> $ret {
+                Usrp::$name(self $(, $arg)*)
+            }
+        )*
+    };
+}
+
+impl Device for Usrp {
+    type ReceiveStream<'device, I>
+        = ReceiveStreamer<'device, I>
+    where
+        I: Sample + 'device;
+    type TransmitStream<'device, I>
+        = TransmitStreamer<'device, I>
+    where
+        I: Sample + 'device;
+
+    delegate! {
+        fn get_num_motherboards(&self) -> Result<usize, Error>;
+        fn get_num_rx_channels(&self) -> Result<usize, Error>;
+        fn get_num_tx_channels(&self) -> Result<usize, Error>;
+        fn get_motherboard_name(&self, mboard: usize) -> Result<String, Error>;
+        fn get_master_clock_rate(&self, mboard: usize) -> Result<f64, Error>;
+        fn get_rx_antennas(&self, channel: usize) -> Result<Vec<String>, Error>;
+        fn get_rx_antenna(&self, channel: usize) -> Result<String, Error>;
+        fn get_tx_antennas(&self, channel: usize) -> Result<Vec<String>, Error>;
+        fn get_tx_antenna(&self, channel: usize) -> Result<String, Error>;
+        fn get_rx_bandwidth(&self, channel: usize) -> Result<f64, Error>;
+        fn get_rx_bandwidth_range(&self, channel: usize) -> Result<MetaRange, Error>;
+        fn get_tx_bandwidth(&self, channel: usize) -> Result<f64, Error>;
+        fn get_tx_bandwidth_range(&self, channel: usize) -> Result<MetaRange, Error>;
+        fn get_rx_frequency(&self, channel: usize) -> Result<f64, Error>;
+        fn get_rx_frequency_range(&self, channel: usize) -> Result<MetaRange, Error>;
+        fn get_tx_frequency(&self, channel: usize) -> Result<f64, Error>;
+        fn get_tx_frequency_range(&self, channel: usize) -> Result<MetaRange, Error>;
+        fn get_rx_gain(&self, channel: usize, name: &str) -> Result<f64, Error>;
+        fn get_rx_gain_names(&self, channel: usize) -> Result<Vec<String>, Error>;
+        fn get_rx_gain_range(&self, channel: usize, name: &str) -> Result<MetaRange, Error>;
+        fn get_tx_gain(&self, channel: usize, name: &str) -> Result<f64, Error>;
+        fn get_tx_gain_names(&self, channel: usize) -> Result<Vec<String>, Error>;
+        fn get_tx_gain_range(&self, channel: usize, name: &str) -> Result<MetaRange, Error>;
+        fn get_rx_sample_rate(&self, channel: usize) -> Result<f64, Error>;
+        fn get_rx_sample_rates(&self, channel: usize) -> Result<MetaRange, Error>;
+        fn get_tx_sample_rate(&self, channel: usize) -> Result<f64, Error>;
+        fn get_tx_sample_rates(&self, channel: usize) -> Result<MetaRange, Error>;
+        fn get_mboard_sensor_names(&self, mboard: usize) -> Result<Vec<String>, Error>;
+        fn get_mboard_sensor(&self, name: &str, mboard: usize) -> Result<SensorValue, Error>;
+        fn get_rx_sensor_names(&self, channel: usize) -> Result<Vec<String>, Error>;
+        fn get_rx_sensor(&self, name: &str, channel: usize) -> Result<SensorValue, Error>;
+        fn get_tx_sensor_names(&self, channel: usize) -> Result<Vec<String>, Error>;
+        fn get_tx_sensor(&self, name: &str, channel: usize) -> Result<SensorValue, Error>;
+        fn get_clock_source(&self, mboard: usize) -> Result<String, Error>;
+        fn get_clock_sources(&self, mboard: usize) -> Result<Vec<String>, Error>;
+        fn set_clock_source(&self, source: &str, mboard: usize) -> Result<(), Error>;
+        fn get_time_source(&self, mboard: usize) -> Result<String, Error>;
+        fn get_time_sources(&self, mboard: usize) -> Result<Vec<String>, Error>;
+        fn set_time_source(&self, source: &str, mboard: usize) -> Result<(), Error>;
+        fn get_current_time(&self, mboard: usize) -> Result<TimeSpec, Error>;
+        fn set_time_unknown_pps(&self, full_secs: i64, frac_secs: f64) -> Result<(), Error>;
+        fn set_time_next_pps(&self, full_secs: i64, frac_secs: f64, mboard: usize) -> Result<(), Error>;
+        fn get_time_synchronized(&self) -> Result<bool, Error>;
+    }
+
+    delegate! {
+        fn set_rx_antenna(&mut self, antenna: &str, channel: usize) -> Result<(), Error>;
+        fn set_tx_antenna(&mut self, antenna: &str, channel: usize) -> Result<(), Error>;
+        fn set_rx_bandwidth(&mut self, bandwidth: f64, channel: usize) -> Result<(), Error>;
+        fn set_tx_bandwidth(&mut self, bandwidth: f64, channel: usize) -> Result<(), Error>;
+        fn set_rx_frequency(&mut self, request: &TuneRequest, channel: usize) -> Result<TuneResult, Error>;
+        fn set_tx_frequency(&mut self, request: &TuneRequest, channel: usize) -> Result<TuneResult, Error>;
+        fn set_rx_gain(&mut self, gain: f64, channel: usize, name: &str) -> Result<(), Error>;
+        fn set_tx_gain(&mut self, gain: f64, channel: usize, name: &str) -> Result<(), Error>;
+        fn set_rx_sample_rate(&mut self, rate: f64, channel: usize) -> Result<(), Error>;
+        fn set_tx_sample_rate(&mut self, rate: f64, channel: usize) -> Result<(), Error>;
+        fn set_command_time(&mut self, time: TimeSpec, mboard: usize) -> Result<(), Error>;
+        fn clear_command_time(&mut self, mboard: usize) -> Result<(), Error>;
+    }
+
+    fn get_rx_stream<I>(&mut self, args: &StreamArgs<I>) -> Result<ReceiveStreamer<'_, I>, Error>
+    where
+        I: Sample,
+    {
+        Usrp::get_rx_stream(self, args)
+    }
+
+    fn get_tx_stream<I>(&mut self, args: &StreamArgs<I>) -> Result<TransmitStreamer<'_, I>, Error>
+    where
+        I: Sample,
+    {
+        Usrp::get_tx_stream(self, args)
+    }
+}
+
+/// Returns an error if `channel` is not less than `num_channels`
+pub(crate) fn check_channel(
+    channel: usize,
+    num_channels: usize,
+    direction: ChannelDirection,
+) -> Result<(), Error> {
+    if channel < num_channels {
+        Ok(())
+    } else {
+        Err(Error::NoSuchChannel {
+            direction,
+            channel,
+            num_channels,
+        })
+    }
+}
+
+/// Returns an error if a channel does not exist on a device
+pub(crate) fn check_device_channel<D>(
+    device: &D,
+    channel: usize,
+    direction: ChannelDirection,
+) -> Result<(), Error>
+where
+    D: Device + ?Sized,
+{
+    let num_channels = match direction {
+        ChannelDirection::Receive => device.get_num_rx_channels()?,
+        ChannelDirection::Transmit => device.get_num_tx_channels()?,
+    };
+    check_channel(channel, num_channels, direction)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{check_channel, check_device_channel};
+    use crate::sim::SimUsrp;
+    use crate::{ChannelDirection, Error};
+
+    #[test]
+    fn channel_index_is_checked() {
+        assert!(check_channel(1, 2, ChannelDirection::Receive).is_ok());
+        let error = check_channel(2, 2, ChannelDirection::Transmit).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "TX channel 2 does not exist (the device has 2 TX channels)"
+        );
+        let error = check_channel(1, 1, ChannelDirection::Receive).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "RX channel 1 does not exist (the device has 1 RX channel)"
+        );
+    }
+
+    #[test]
+    fn device_channel_is_checked() {
+        // Usrp::rx_channel() and Usrp::tx_channel() use the same check
+        let usrp = SimUsrp::builder().rx_channels(1).tx_channels(2).build();
+        assert!(check_device_channel(&usrp, 0, ChannelDirection::Receive).is_ok());
+        assert!(matches!(
+            check_device_channel(&usrp, 1, ChannelDirection::Receive),
+            Err(Error::NoSuchChannel {
+                direction: ChannelDirection::Receive,
+                channel: 1,
+                num_channels: 1,
+            })
+        ));
+        assert!(check_device_channel(&usrp, 1, ChannelDirection::Transmit).is_ok());
+        assert!(matches!(
+            check_device_channel(&usrp, 2, ChannelDirection::Transmit),
+            Err(Error::NoSuchChannel { channel: 2, .. })
+        ));
+    }
+}
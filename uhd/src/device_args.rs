@@ -0,0 +1,434 @@
+//! Arguments that select and configure devices
+
+use std::ffi::CString;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::error::{Error, Result};
+
+/// Arguments that select a device and configure the connection to it
+///
+/// These are the `key=value` pairs, separated by commas, that UHD uses to find and open devices.
+/// Keys keep the order in which they were added.
+///
+/// ```
+/// use uhd::DeviceArgs;
+///
+/// let args = DeviceArgs::builder()
+///     .device_type("b200")
+///     .serial("31A8F5B")
+///     .num_recv_frames(512)
+///     .build();
+/// assert_eq!(args.to_string(), "type=b200,serial=31A8F5B,num_recv_frames=512");
+/// assert_eq!("type=b200, serial=31A8F5B".parse::<DeviceArgs>()?.serial(), Some("31A8F5B"));
+/// # Ok::<(), uhd::Error>(())
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DeviceArgs {
+    pairs: Vec<(String, String)>,
+}
+
+impl DeviceArgs {
+    /// Creates empty arguments, which select any available device
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Creates a builder, initialized with empty arguments
+    pub fn builder() -> DeviceArgsBuilder {
+        DeviceArgsBuilder {
+            args: DeviceArgs::new(),
+        }
+    }
+
+    /// Returns the value for a key
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.pairs
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Sets the value for a key, replacing any existing value
+    pub fn set<K, V>(&mut self, key: K, value: V)
+    where
+        K: Into<String>,
+        V: Into<String>,
+    {
+        let key = key.into();
+        let value = value.into();
+        match self.pairs.iter_mut().find(|(k, _)| *k == key) {
+            Some(pair) => pair.1 = value,
+            None => self.pairs.push((key, value)),
+        }
+    }
+
+    /// Removes a key and returns its value
+    pub fn remove(&mut self, key: &str) -> Option<String> {
+        let index = self.pairs.iter().position(|(k, _)| k == key)?;
+        Some(self.pairs.remove(index).1)
+    }
+
+    /// Returns an iterator over the keys and values
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.pairs.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+
+    /// Returns true if there are no arguments
+    pub fn is_empty(&self) -> bool {
+        self.pairs.is_empty()
+    }
+
+    /// Returns the device type (`type`), like `b200` or `x300`
+    pub fn device_type(&self) -> Option<&str> {
+        self.get("type")
+    }
+
+    /// Returns the serial number (`serial`)
+    pub fn serial(&self) -> Option<&str> {
+        self.get("serial")
+    }
+
+    /// Returns the network address (`addr`)
+    pub fn addr(&self) -> Option<&str> {
+        self.get("addr")
+    }
+
+    /// Returns the device name (`name`)
+    pub fn name(&self) -> Option<&str> {
+        self.get("name")
+    }
+
+    /// Returns the resource (`resource`), which selects PCIe devices
+    pub fn resource(&self) -> Option<&str> {
+        self.get("resource")
+    }
+
+    /// Converts these arguments into a C string in the format that UHD expects
+    pub(crate) fn to_c_string(&self) -> Result<CString> {
+        for (key, value) in &self.pairs {
+            if key.is_empty() || key.contains([',', '=']) || value.contains([',', '=']) {
+                return Err(Error::InvalidDeviceArgs(format!(
+                    "{}={} can't be represented as a device argument",
+                    key, value
+                )));
+            }
+        }
+        Ok(CString::new(self.to_string())?)
+    }
+}
+
+/// Parses arguments in the format `key=value,key=value`
+///
+/// Whitespace around keys and values is removed. A key without `=` has an empty value. A pair
+/// with more than one `=` is an error, because UHD would not parse it the same way.
+impl FromStr for DeviceArgs {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut args = DeviceArgs::new();
+        for pair in s.split(',') {
+            let (key, value) = match pair.split_once('=') {
+                Some((key, value)) => (key.trim(), value.trim()),
+                None => (pair.trim(), ""),
+            };
+            if key.is_empty() {
+                if value.is_empty() {
+                    continue;
+                }
+                return Err(Error::InvalidDeviceArgs(format!(
+                    "No key before ={} in \"{}\"",
+                    value, s
+                )));
+            }
+            if value.contains('=') {
+                return Err(Error::InvalidDeviceArgs(format!(
+                    "More than one = in \"{}\"",
+                    pair.trim()
+                )));
+            }
+            args.set(key, value);
+        }
+        Ok(args)
+    }
+}
+
+/// Formats arguments as `key=value,key=value`
+impl fmt::Display for DeviceArgs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (key, value)) in self.pairs.iter().enumerate() {
+            if i != 0 {
+                f.write_str(",")?;
+            }
+            write!(f, "{}={}", key, value)?;
+        }
+        Ok(())
+    }
+}
+
+/// A builder for [`DeviceArgs`]
+#[derive(Debug, Clone)]
+pub struct DeviceArgsBuilder {
+    args: DeviceArgs,
+}
+
+impl DeviceArgsBuilder {
+    /// Sets the device type (`type`), like `b200` or `x300`
+    pub fn device_type<S: Into<String>>(self, device_type: S) -> Self {
+        self.arg("type", device_type)
+    }
+
+    /// Sets the serial number (`serial`)
+    pub fn serial<S: Into<String>>(self, serial: S) -> Self {
+        self.arg("serial", serial)
+    }
+
+    /// Sets the network address (`addr`)
+    pub fn addr<S: Into<String>>(self, addr: S) -> Self {
+        self.arg("addr", addr)
+    }
+
+    /// Sets the device name (`name`)
+    pub fn name<S: Into<String>>(self, name: S) -> Self {
+        self.arg("name", name)
+    }
+
+    /// Sets the resource (`resource`), which selects PCIe devices
+    pub fn resource<S: Into<String>>(self, resource: S) -> Self {
+        self.arg("resource", resource)
+    }
+
+    /// Sets the master clock rate in hertz (`master_clock_rate`)
+    pub fn master_clock_rate(self, rate: f64) -> Self {
+        self.arg("master_clock_rate", rate.to_string())
+    }
+
+    /// Sets the number of receive frames to buffer (`num_recv_frames`)
+    pub fn num_recv_frames(self, frames: usize) -> Self {
+        self.arg("num_recv_frames", frames.to_string())
+    }
+
+    /// Sets the size of each receive frame in bytes (`recv_frame_size`)
+    pub fn recv_frame_size(self, size: usize) -> Self {
+        self.arg("recv_frame_size", size.to_string())
+    }
+
+    /// Sets the number of transmit frames to buffer (`num_send_frames`)
+    pub fn num_send_frames(self, frames: usize) -> Self {
+        self.arg("num_send_frames", frames.to_string())
+    }
+
+    /// Sets the size of each transmit frame in bytes (`send_frame_size`)
+    pub fn send_frame_size(self, size: usize) -> Self {
+        self.arg("send_frame_size", size.to_string())
+    }
+
+    /// Sets any other argument
+    pub fn arg<K, V>(mut self, key: K, value: V) -> Self
+    where
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.args.set(key, value);
+        self
+    }
+
+    /// Builds the arguments
+    pub fn build(self) -> DeviceArgs {
+        self.args
+    }
+}
+
+/// The address of a device that [`Usrp::find`](crate::Usrp::find) or
+/// [`UsrpClock::find`](crate::UsrpClock::find) found
+///
+/// An address can be passed to `open` to open the same device.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeviceAddr {
+    args: DeviceArgs,
+}
+
+impl DeviceAddr {
+    /// Parses an address that UHD returned
+    pub(crate) fn parse(address: &str) -> Result<Self> {
+        Ok(DeviceAddr {
+            args: address.parse()?,
+        })
+    }
+
+    /// Returns all keys and values of this address
+    pub fn args(&self) -> &DeviceArgs {
+        &self.args
+    }
+
+    /// Returns the value for a key
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.args.get(key)
+    }
+
+    /// Returns the device type (`type`), like `b200` or `x300`
+    pub fn device_type(&self) -> Option<&str> {
+        self.args.device_type()
+    }
+
+    /// Returns the serial number (`serial`)
+    pub fn serial(&self) -> Option<&str> {
+        self.args.serial()
+    }
+
+    /// Returns the network address (`addr`), if the device is connected over a network
+    pub fn addr(&self) -> Option<&str> {
+        self.args.addr()
+    }
+
+    /// Returns the device name (`name`), which is often empty
+    pub fn name(&self) -> Option<&str> {
+        self.args.name()
+    }
+
+    /// Returns the product (`product`), like `B210`
+    pub fn product(&self) -> Option<&str> {
+        self.args.get("product")
+    }
+}
+
+impl fmt::Display for DeviceAddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.args.fmt(f)
+    }
+}
+
+impl From<DeviceAddr> for DeviceArgs {
+    fn from(address: DeviceAddr) -> Self {
+        address.args
+    }
+}
+
+/// Types that can be converted into device arguments
+///
+/// This is implemented for strings in the `key=value,key=value` format, [`DeviceArgs`], and
+/// [`DeviceAddr`].
+pub trait IntoDeviceArgs {
+    /// Converts this value into device arguments
+    fn into_device_args(self) -> Result<DeviceArgs>;
+}
+
+impl IntoDeviceArgs for DeviceArgs {
+    fn into_device_args(self) -> Result<DeviceArgs> {
+        Ok(self)
+    }
+}
+
+impl IntoDeviceArgs for &DeviceArgs {
+    fn into_device_args(self) -> Result<DeviceArgs> {
+        Ok(self.clone())
+    }
+}
+
+impl IntoDeviceArgs for DeviceAddr {
+    fn into_device_args(self) -> Result<DeviceArgs> {
+        Ok(self.args)
+    }
+}
+
+impl IntoDeviceArgs for &DeviceAddr {
+    fn into_device_args(self) -> Result<DeviceArgs> {
+        Ok(self.args.clone())
+    }
+}
+
+impl IntoDeviceArgs for &str {
+    fn into_device_args(self) -> Result<DeviceArgs> {
+        self.parse()
+    }
+}
+
+impl IntoDeviceArgs for String {
+    fn into_device_args(self) -> Result<DeviceArgs> {
+        self.parse()
+    }
+}
+
+impl IntoDeviceArgs for &String {
+    fn into_device_args(self) -> Result<DeviceArgs> {
+        self.parse()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{DeviceAddr, DeviceArgs, IntoDeviceArgs};
+    use crate::Error;
+
+    #[test]
+    fn parse_and_format() {
+        let args: DeviceArgs = " type = x300 ,addr=192.168.40.2,, skip_dram, type=n310"
+            .parse()
+            .unwrap();
+        assert_eq!(args.device_type(), Some("n310"));
+        assert_eq!(args.addr(), Some("192.168.40.2"));
+        assert_eq!(args.get("skip_dram"), Some(""));
+        assert_eq!(args.to_string(), "type=n310,addr=192.168.40.2,skip_dram=");
+        assert_eq!(args.to_string().parse::<DeviceArgs>().unwrap(), args);
+
+        assert!("".parse::<DeviceArgs>().unwrap().is_empty());
+        assert!(matches!(
+            "type=b200,=5".parse::<DeviceArgs>(),
+            Err(Error::InvalidDeviceArgs(_))
+        ));
+        assert!(matches!(
+            "a=b=c".parse::<DeviceArgs>(),
+            Err(Error::InvalidDeviceArgs(_))
+        ));
+    }
+
+    #[test]
+    fn builder() {
+        let mut args = DeviceArgs::builder()
+            .resource("RIO0")
+            .master_clock_rate(184.32e6)
+            .recv_frame_size(8000)
+            .arg("fpga", "HG")
+            .build();
+        assert_eq!(
+            args.to_string(),
+            "resource=RIO0,master_clock_rate=184320000,recv_frame_size=8000,fpga=HG"
+        );
+        assert_eq!(args.remove("fpga").as_deref(), Some("HG"));
+        args.set("recv_frame_size", "4000");
+        assert_eq!(args.get("recv_frame_size"), Some("4000"));
+        assert_eq!(args.iter().count(), 3);
+
+        args.set("name", "a,b");
+        assert!(matches!(
+            args.to_c_string(),
+            Err(Error::InvalidDeviceArgs(_))
+        ));
+        args.set("name", "a=b");
+        assert!(matches!(
+            args.to_c_string(),
+            Err(Error::InvalidDeviceArgs(_))
+        ));
+        args.remove("name");
+        args.set("a=b", "c");
+        assert!(matches!(
+            args.to_c_string(),
+            Err(Error::InvalidDeviceArgs(_))
+        ));
+    }
+
+    #[test]
+    fn found_address() {
+        let address = DeviceAddr::parse("type=b200,name=,serial=31A8F5B,product=B210").unwrap();
+        assert_eq!(address.product(), Some("B210"));
+        assert_eq!(address.name(), Some(""));
+        assert_eq!(address.addr(), None);
+        let args = (&address).into_device_args().unwrap();
+        assert_eq!(args.serial(), Some("31A8F5B"));
+        assert_eq!(
+            args.to_c_string().unwrap().to_str().unwrap(),
+            "type=b200,name=,serial=31A8F5B,product=B210"
+        );
+        assert_eq!(DeviceArgs::from(address), args);
+    }
+}
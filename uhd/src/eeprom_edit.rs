@@ -0,0 +1,315 @@
+use std::fmt;
+use std::os::raw::c_int;
+
+use crate::error::Error;
+use crate::usrp::Usrp;
+use crate::{DaughterBoardEeprom, MotherboardEeprom};
+
+/// One value that an EEPROM edit would change
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EepromChange {
+    key: String,
+    current: Option<String>,
+    requested: String,
+}
+
+impl EepromChange {
+    /// Returns the key of the value
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Returns the value currently stored on the device, or None if the key is not set
+    pub fn current(&self) -> Option<&str> {
+        self.current.as_deref()
+    }
+
+    /// Returns the value that the edit would write
+    pub fn requested(&self) -> &str {
+        &self.requested
+    }
+}
+
+impl fmt::Display for EepromChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.current {
+            Some(current) => write!(f, "{}: \"{}\" -> \"{}\"", self.key, current, self.requested),
+            None => write!(f, "{}: <unset> -> \"{}\"", self.key, self.requested),
+        }
+    }
+}
+
+/// The result of committing an EEPROM edit
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EepromCommit {
+    /// All requested values already matched the device, so nothing was written
+    Unchanged,
+    /// The confirmation callback rejected the changes, so nothing was written
+    Declined(Vec<EepromChange>),
+    /// The changes were written to the device
+    Written(Vec<EepromChange>),
+}
+
+/// A read-modify-write edit of a motherboard EEPROM
+///
+/// This is returned by [`Usrp::edit_motherboard_eeprom`]. Values are staged with
+/// [`set`](Self::set), compared against the device with [`diff`](Self::diff), and only written
+/// by [`commit`](Self::commit) after the confirmation callback accepts them. Only the keys that
+/// differ from the device are written.
+pub struct MotherboardEepromEdit<'usrp> {
+    usrp: &'usrp mut Usrp,
+    mboard: usize,
+    current: MotherboardEeprom,
+    requested: Vec<(String, String)>,
+}
+
+impl<'usrp> MotherboardEepromEdit<'usrp> {
+    pub(crate) fn new(usrp: &'usrp mut Usrp, mboard: usize) -> Result<Self, Error> {
+        let current = usrp.get_motherboard_eeprom(mboard)?;
+        Ok(MotherboardEepromEdit {
+            usrp,
+            mboard,
+            current,
+            requested: Vec::new(),
+        })
+    }
+
+    /// Stages a value to be written
+    ///
+    /// If the same key is set more than once, the last value is used.
+    pub fn set<K, V>(&mut self, key: K, value: V) -> &mut Self
+    where
+        K: Into<String>,
+        V: Into<String>,
+    {
+        let key = key.into();
+        let value = value.into();
+        match self
+            .requested
+            .iter_mut()
+            .find(|(existing, _)| *existing == key)
+        {
+            Some(entry) => entry.1 = value,
+            None => self.requested.push((key, value)),
+        }
+        self
+    }
+
+    /// Returns the values that were read from the device when this edit was created
+    pub fn current(&self) -> &MotherboardEeprom {
+        &self.current
+    }
+
+    /// Returns the staged values that differ from the values on the device
+    pub fn diff(&self) -> Result<Vec<EepromChange>, Error> {
+        diff_values(&self.requested, |key| self.current.get(key))
+    }
+
+    /// Writes the changed values to the device if `confirm` returns true
+    ///
+    /// `confirm` is not called if there are no changes.
+    pub fn commit<F>(self, confirm: F) -> Result<EepromCommit, Error>
+    where
+        F: FnOnce(&[EepromChange]) -> bool,
+    {
+        let changes = self.diff()?;
+        if changes.is_empty() {
+            return Ok(EepromCommit::Unchanged);
+        }
+        if !confirm(&changes) {
+            return Ok(EepromCommit::Declined(changes));
+        }
+        let mut eeprom = MotherboardEeprom::default();
+        for change in &changes {
+            eeprom.put(change.key.clone(), change.requested.clone())?;
+        }
+        self.usrp.set_motherboard_eeprom(&eeprom, self.mboard)?;
+        Ok(EepromCommit::Written(changes))
+    }
+}
+
+/// A read-modify-write edit of a daughter board EEPROM
+///
+/// This is returned by [`Usrp::edit_daughter_board_eeprom`] and works like
+/// [`MotherboardEepromEdit`]. The keys used in the diff are `id`, `serial`, and `revision`.
+pub struct DaughterBoardEepromEdit<'usrp> {
+    usrp: &'usrp mut Usrp,
+    unit: String,
+    slot: String,
+    mboard: usize,
+    current: DaughterBoardEeprom,
+    id: Option<String>,
+    serial: Option<String>,
+    revision: Option<c_int>,
+}
+
+impl<'usrp> DaughterBoardEepromEdit<'usrp> {
+    pub(crate) fn new(
+        usrp: &'usrp mut Usrp,
+        unit: &str,
+        slot: &str,
+        mboard: usize,
+    ) -> Result<Self, Error> {
+        let current = usrp.get_daughter_board_eeprom(unit, slot, mboard)?;
+        Ok(DaughterBoardEepromEdit {
+            usrp,
+            unit: unit.to_owned(),
+            slot: slot.to_owned(),
+            mboard,
+            current,
+            id: None,
+            serial: None,
+            revision: None,
+        })
+    }
+
+    /// Stages a new daughter board ID
+    pub fn set_id(&mut self, id: &str) -> &mut Self {
+        self.id = Some(id.to_owned());
+        self
+    }
+
+    /// Stages a new serial number
+    pub fn set_serial(&mut self, serial: &str) -> &mut Self {
+        self.serial = Some(serial.to_owned());
+        self
+    }
+
+    /// Stages a new revision
+    pub fn set_revision(&mut self, revision: c_int) -> &mut Self {
+        self.revision = Some(revision);
+        self
+    }
+
+    /// Returns the values that were read from the device when this edit was created
+    pub fn current(&self) -> &DaughterBoardEeprom {
+        &self.current
+    }
+
+    /// Returns the staged values that differ from the values on the device
+    pub fn diff(&self) -> Result<Vec<EepromChange>, Error> {
+        let mut requested = Vec::new();
+        if let Some(id) = &self.id {
+            requested.push(("id".to_owned(), id.clone()));
+        }
+        if let Some(serial) = &self.serial {
+            requested.push(("serial".to_owned(), serial.clone()));
+        }
+        if let Some(revision) = self.revision {
+            requested.push(("revision".to_owned(), revision.to_string()));
+        }
+        diff_values(&requested, |key| match key {
+            "id" => self.current.id().map(Some),
+            "serial" => self.current.serial().map(Some),
+            "revision" => revision_value(self.current.revision()),
+            _ => Ok(None),
+        })
+    }
+
+    /// Writes the changed values to the device if `confirm` returns true
+    ///
+    /// The daughter board EEPROM is written as a whole, so values that were not staged are
+    /// written back unchanged. `confirm` is not called if there are no changes.
+    pub fn commit<F>(mut self, confirm: F) -> Result<EepromCommit, Error>
+    where
+        F: FnOnce(&[EepromChange]) -> bool,
+    {
+        let changes = self.diff()?;
+        if changes.is_empty() {
+            return Ok(EepromCommit::Unchanged);
+        }
+        if !confirm(&changes) {
+            return Ok(EepromCommit::Declined(changes));
+        }
+        if let Some(id) = &self.id {
+            self.current.set_id(id)?;
+        }
+        if let Some(serial) = &self.serial {
+            self.current.set_serial(serial)?;
+        }
+        if let Some(revision) = self.revision {
+            self.current.set_revision(revision)?;
+        }
+        self.usrp
+            .set_daughter_board_eeprom(&self.current, &self.unit, &self.slot, self.mboard)?;
+        Ok(EepromCommit::Written(changes))
+    }
+}
+
+/// Compares requested values with current values and returns the values that differ
+fn diff_values<F>(requested: &[(String, String)], current: F) -> Result<Vec<EepromChange>, Error>
+where
+    F: Fn(&str) -> Result<Option<String>, Error>,
+{
+    let mut changes = Vec::new();
+    for (key, value) in requested {
+        let current = current(key)?;
+        if current.as_deref() != Some(value.as_str()) {
+            changes.push(EepromChange {
+                key: key.clone(),
+                current,
+                requested: value.clone(),
+            });
+        }
+    }
+    Ok(changes)
+}
+
+/// Converts a daughter board revision into a value to compare, or None if it is not set
+///
+/// UHD stores the revision as a string and converts it with `std::stoi`, which throws a
+/// `std::invalid_argument` if the EEPROM is blank. Other errors are returned.
+fn revision_value(revision: Result<c_int, Error>) -> Result<Option<String>, Error> {
+    match revision {
+        Ok(revision) => Ok(Some(revision.to_string())),
+        Err(Error::Key) | Err(Error::StdExcept) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{diff_values, revision_value, EepromChange};
+    use crate::Error;
+
+    #[test]
+    fn diff_only_changed_values() {
+        let requested = vec![
+            ("name".to_owned(), "station-4".to_owned()),
+            ("serial".to_owned(), "31A2B4F".to_owned()),
+            ("revision".to_owned(), "5".to_owned()),
+        ];
+        let changes = diff_values(&requested, |key| {
+            Ok(match key {
+                "name" => Some("station-4".to_owned()),
+                "serial" => Some("30F1000".to_owned()),
+                _ => None,
+            })
+        })
+        .unwrap();
+        assert_eq!(
+            changes,
+            vec![
+                EepromChange {
+                    key: "serial".to_owned(),
+                    current: Some("30F1000".to_owned()),
+                    requested: "31A2B4F".to_owned(),
+                },
+                EepromChange {
+                    key: "revision".to_owned(),
+                    current: None,
+                    requested: "5".to_owned(),
+                },
+            ]
+        );
+        assert_eq!("revision: <unset> -> \"5\"", changes[1].to_string());
+    }
+
+    #[test]
+    fn unset_revision() {
+        assert_eq!(revision_value(Ok(3)).unwrap(), Some("3".to_owned()));
+        assert_eq!(revision_value(Err(Error::StdExcept)).unwrap(), None);
+        assert_eq!(revision_value(Err(Error::Key)).unwrap(), None);
+        assert!(matches!(revision_value(Err(Error::Io)), Err(Error::Io)));
+    }
+}
@@ -1,3 +1,4 @@
+use crate::master_clock_rate::ChannelDirection;
 use crate::utils::copy_string;
 use std::ffi::NulError;
 use std::str::Utf8Error;
@@ -18,6 +19,37 @@ pub enum Error {
     #[error("Invalid device arguments")]
     InvalidDevice,
 
+    /// Device arguments could not be parsed or represented
+    #[error("Invalid device arguments: {0}")]
+    InvalidDeviceArgs(String),
+
+    /// Stream arguments do not match the device or have an invalid value
+    #[error("Invalid stream arguments: {0}")]
+    InvalidStreamArgs(String),
+
+    /// A radio configuration could not be parsed or has inconsistent settings
+    #[error("Invalid radio configuration: {0}")]
+    InvalidConfig(String),
+
+    /// A device snapshot has more motherboards or channels than the device it is restored to
+    #[error("The snapshot has {snapshot} {items}, but the device has {device}")]
+    SnapshotMismatch {
+        items: &'static str,
+        snapshot: usize,
+        device: usize,
+    },
+
+    /// A channel index is not less than the number of channels of the device
+    #[error(
+        "{direction} channel {channel} does not exist (the device has {num_channels} {direction} channel{})",
+        if *.num_channels == 1 { "" } else { "s" }
+    )]
+    NoSuchChannel {
+        direction: ChannelDirection,
+        channel: usize,
+        num_channels: usize,
+    },
+
     #[error("uhd::index_error - A sequence index is out of range")]
     Index,
 
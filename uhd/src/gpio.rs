@@ -0,0 +1,169 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::error::Error;
+use crate::usrp::Usrp;
+
+/// A GPIO attribute (register) of a GPIO bank
+///
+/// Each bit of an attribute corresponds to one pin of the bank.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GpioAttribute {
+    /// Control mode: 0 for manual control (using `Out`), 1 for automatic transmit/receive
+    /// (ATR) control
+    Ctrl,
+    /// Data direction: 0 for input, 1 for output
+    Ddr,
+    /// Output levels of manually controlled pins
+    Out,
+    /// Output levels of ATR-controlled pins when the radio is idle
+    Atr0x,
+    /// Output levels of ATR-controlled pins when the radio is only receiving
+    AtrRx,
+    /// Output levels of ATR-controlled pins when the radio is only transmitting
+    AtrTx,
+    /// Output levels of ATR-controlled pins when the radio is transmitting and receiving
+    AtrXx,
+    /// Current levels of all pins (read-only)
+    Readback,
+}
+
+impl GpioAttribute {
+    /// All attributes, in the order that UHD lists them
+    pub const ALL: [GpioAttribute; 8] = [
+        GpioAttribute::Ctrl,
+        GpioAttribute::Ddr,
+        GpioAttribute::Out,
+        GpioAttribute::Atr0x,
+        GpioAttribute::AtrRx,
+        GpioAttribute::AtrTx,
+        GpioAttribute::AtrXx,
+        GpioAttribute::Readback,
+    ];
+
+    /// Returns the name that UHD uses for this attribute
+    pub fn name(&self) -> &'static str {
+        match self {
+            GpioAttribute::Ctrl => "CTRL",
+            GpioAttribute::Ddr => "DDR",
+            GpioAttribute::Out => "OUT",
+            GpioAttribute::Atr0x => "ATR_0X",
+            GpioAttribute::AtrRx => "ATR_RX",
+            GpioAttribute::AtrTx => "ATR_TX",
+            GpioAttribute::AtrXx => "ATR_XX",
+            GpioAttribute::Readback => "READBACK",
+        }
+    }
+
+    /// Returns true if this attribute can be set (all attributes except `Readback`)
+    pub fn is_writable(&self) -> bool {
+        *self != GpioAttribute::Readback
+    }
+}
+
+impl fmt::Display for GpioAttribute {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for GpioAttribute {
+    type Err = Error;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        GpioAttribute::ALL
+            .iter()
+            .find(|attr| attr.name().eq_ignore_ascii_case(name))
+            .copied()
+            .ok_or_else(|| Error::Unique(format!("Unknown GPIO attribute \"{}\"", name)))
+    }
+}
+
+/// A handle to one GPIO bank of a motherboard
+///
+/// This is returned by [`Usrp::gpio_bank`].
+pub struct GpioBank<'usrp> {
+    usrp: &'usrp mut Usrp,
+    name: String,
+    mboard: usize,
+}
+
+impl<'usrp> GpioBank<'usrp> {
+    pub(crate) fn new(usrp: &'usrp mut Usrp, name: String, mboard: usize) -> Self {
+        GpioBank { usrp, name, mboard }
+    }
+
+    /// Returns the name of this bank
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the index of the motherboard that this bank belongs to
+    pub fn mboard(&self) -> usize {
+        self.mboard
+    }
+
+    /// Returns the value of an attribute
+    pub fn get(&self, attr: GpioAttribute) -> Result<u32, Error> {
+        self.usrp.get_gpio_attr(&self.name, attr, self.mboard)
+    }
+
+    /// Sets the bits of an attribute that are set in `mask` to the corresponding bits of `value`
+    ///
+    /// This returns an error if the attribute is read-only.
+    pub fn set(&mut self, attr: GpioAttribute, value: u32, mask: u32) -> Result<(), Error> {
+        self.usrp
+            .set_gpio_attr(&self.name, attr, value, mask, self.mboard)
+    }
+
+    /// Configures the pins in `mask` as outputs controlled by [`GpioBank::write`]
+    pub fn configure_manual_outputs(&mut self, mask: u32) -> Result<(), Error> {
+        self.set(GpioAttribute::Ctrl, 0, mask)?;
+        self.set(GpioAttribute::Ddr, mask, mask)
+    }
+
+    /// Configures the pins in `mask` as outputs controlled by the radio's transmit/receive
+    /// state, using the `Atr*` attributes
+    pub fn configure_atr_outputs(&mut self, mask: u32) -> Result<(), Error> {
+        self.set(GpioAttribute::Ctrl, mask, mask)?;
+        self.set(GpioAttribute::Ddr, mask, mask)
+    }
+
+    /// Configures the pins in `mask` as inputs
+    pub fn configure_inputs(&mut self, mask: u32) -> Result<(), Error> {
+        self.set(GpioAttribute::Ddr, 0, mask)
+    }
+
+    /// Sets the levels of the manually controlled output pins in `mask`
+    pub fn write(&mut self, value: u32, mask: u32) -> Result<(), Error> {
+        self.set(GpioAttribute::Out, value, mask)
+    }
+
+    /// Reads the current levels of all pins
+    pub fn read(&self) -> Result<u32, Error> {
+        self.get(GpioAttribute::Readback)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::GpioAttribute;
+
+    #[test]
+    fn attribute_names_round_trip() {
+        for attr in GpioAttribute::ALL.iter() {
+            assert_eq!(*attr, attr.name().parse::<GpioAttribute>().unwrap());
+        }
+        assert_eq!(GpioAttribute::Atr0x, "atr_0x".parse().unwrap());
+        assert!("ATR".parse::<GpioAttribute>().is_err());
+    }
+
+    #[test]
+    fn only_readback_is_read_only() {
+        let read_only = GpioAttribute::ALL
+            .iter()
+            .filter(|attr| !attr.is_writable())
+            .collect::<Vec<_>>();
+        assert_eq!(read_only, vec![&GpioAttribute::Readback]);
+    }
+}
@@ -16,117 +16,80 @@ extern crate libc;
 extern crate num_complex;
 extern crate uhd_sys;
 
-use std::convert::TryInto;
-
+#[cfg(feature = "async")]
+mod async_stream;
+mod benchmark;
+mod channel;
+#[cfg(feature = "config")]
+mod config;
 mod daughter_board_eeprom;
+mod device;
+mod device_args;
+mod eeprom_edit;
 mod error;
+mod gpio;
+mod master_clock_rate;
 mod motherboard_eeprom;
+mod playback;
 pub mod range;
 mod receiver;
+mod report;
+mod settings;
+#[cfg(feature = "sigmf")]
+mod sigmf;
+pub mod sim;
+mod snapshot;
 mod stream;
 mod string_vector;
+mod subdev_spec;
+mod time_spec;
+mod timed_commands;
 mod transmitter;
 mod tune_request;
 mod tune_result;
 mod usrp;
+mod usrp_clock;
 mod utils;
+mod waveform;
 
 // Re-export many public items at the root
+#[cfg(feature = "async")]
+pub use async_stream::{AsyncReceiver, AsyncTransmitter, TxBlock};
+pub use benchmark::{Benchmark, RxBenchmarkReport, TxBenchmarkReport};
+pub use channel::{RxChannel, TxChannel};
+#[cfg(feature = "config")]
+pub use config::{ChannelConfig, RadioConfig};
 pub use daughter_board_eeprom::DaughterBoardEeprom;
+pub use device::{Device, ReceiveStream, TransmitStream};
+pub use device_args::{DeviceAddr, DeviceArgs, DeviceArgsBuilder, IntoDeviceArgs};
+pub use eeprom_edit::{DaughterBoardEepromEdit, EepromChange, EepromCommit, MotherboardEepromEdit};
 pub use error::*;
+pub use gpio::{GpioAttribute, GpioBank};
+pub use master_clock_rate::{ChannelDirection, MasterClockRateReport, SampleRateWarning};
 pub use motherboard_eeprom::MotherboardEeprom;
-pub use receiver::{error::*, info::ReceiveInfo, metadata::*, streamer::ReceiveStreamer};
+pub use playback::{FileFormat, Playback, Repeat};
+pub use receiver::{
+    capture::{CaptureOptions, ContinuousReceiver, RxBlock},
+    error::*,
+    info::ReceiveInfo,
+    metadata::*,
+    streamer::ReceiveStreamer,
+};
+pub use report::{ChannelReport, DaughterBoardReport, DeviceReport, GainReport, MotherboardReport};
+pub use settings::{ConfigReport, ConfigSetting, ConfigValue};
+#[cfg(feature = "sigmf")]
+pub use sigmf::{RecordingInfo, SigmfItem, SigmfRecorder};
+pub use snapshot::{ChannelSnapshot, DeviceSnapshot, MotherboardSnapshot};
 pub use stream::*;
-pub use transmitter::{info::TransmitInfo, metadata::*, streamer::TransmitStreamer};
+pub use subdev_spec::{SubdevSpec, SubdevSpecPair};
+pub use time_spec::TimeSpec;
+pub use timed_commands::TimedCommands;
+pub use transmitter::{
+    async_metadata::*, info::TransmitInfo, metadata::*, streamer::TransmitStreamer,
+};
 pub use tune_request::*;
 pub use tune_result::TuneResult;
 pub use usrp::{SensorValue, Usrp};
+pub use usrp_clock::UsrpClock;
 pub use utils::alloc_boxed_slice;
-// Common definitions
-
-/// A UHD device time.
-///
-/// This type keeps UHD's split integer/floating-point representation contained in one value.
-#[derive(Debug, Clone, Copy, Default, PartialOrd, PartialEq)]
-pub struct TimeSpec {
-    // In some versions of UHD, the corresponding field of uhd::time_spec_t is a time_t.
-    // In other versions, it's a int64_t. The Rust code does conversion to keep this
-    // an i64.
-    pub seconds: i64,
-    pub fraction: f64,
-}
-
-impl TimeSpec {
-    /// Creates a device time from whole and fractional seconds.
-    pub const fn new(full_secs: i64, frac_secs: f64) -> Self {
-        Self {
-            seconds: full_secs,
-            fraction: frac_secs,
-        }
-    }
-
-    /// Creates a device time from a total number of nanoseconds.
-    ///
-    /// Negative values are normalized so that the fractional component is in the range `[0, 1)`.
-    pub fn from_nanos(nanoseconds: i64) -> Self {
-        const NANOS_PER_SECOND: i64 = 1_000_000_000;
-
-        let full_secs = nanoseconds.div_euclid(NANOS_PER_SECOND);
-        let fractional_nanos = nanoseconds.rem_euclid(NANOS_PER_SECOND);
-        Self::new(full_secs, fractional_nanos as f64 / NANOS_PER_SECOND as f64)
-    }
-
-    /// Converts this device time to a total number of nanoseconds.
-    ///
-    /// The fractional component is rounded to the nearest nanosecond.
-    ///
-    /// # Panics
-    ///
-    /// Panics if the fractional component is not finite or the result does not fit in an `i64`.
-    pub fn into_nanos(self) -> i64 {
-        const NANOS_PER_SECOND: i64 = 1_000_000_000;
-
-        assert!(
-            self.fraction.is_finite(),
-            "TimeSpec fractional seconds must be finite"
-        );
-        let full_nanos = i128::from(self.seconds) * i128::from(NANOS_PER_SECOND);
-        let fractional_nanos = (self.fraction * NANOS_PER_SECOND as f64).round() as i128;
-        (full_nanos + fractional_nanos)
-            .try_into()
-            .expect("TimeSpec does not fit in i64 nanoseconds")
-    }
-
-    pub(crate) fn into_parts(self) -> (i64, f64) {
-        (self.seconds, self.fraction)
-    }
-}
-
-#[cfg(test)]
-mod time_spec_tests {
-    use super::TimeSpec;
-
-    #[test]
-    fn constructs_from_parts() {
-        assert_eq!(
-            TimeSpec::new(42, 0.25),
-            TimeSpec {
-                seconds: 42,
-                fraction: 0.25,
-            }
-        );
-    }
-
-    #[test]
-    fn constructs_from_nanoseconds() {
-        assert_eq!(TimeSpec::from_nanos(1_500_000_000), TimeSpec::new(1, 0.5));
-        assert_eq!(TimeSpec::from_nanos(-500_000_000), TimeSpec::new(-1, 0.5));
-    }
-
-    #[test]
-    fn nanoseconds_round_trip() {
-        for nanoseconds in [i64::MIN, -1_500_000_001, -1, 0, 1, 1_500_000_001, i64::MAX] {
-            assert_eq!(TimeSpec::from_nanos(nanoseconds).into_nanos(), nanoseconds);
-        }
-    }
-}
+pub use waveform::{Waveform, WaveformGenerator};
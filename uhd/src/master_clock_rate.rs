@@ -0,0 +1,149 @@
+use std::fmt;
+
+/// The result of changing the master clock rate of a USRP
+///
+/// UHD may coerce the requested master clock rate to a rate that the hardware supports.
+/// Changing the master clock rate can also leave the sample rate of a channel at a value
+/// that is not an integer division of the new master clock rate. These channels are listed
+/// as warnings.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MasterClockRateReport {
+    pub(crate) requested: f64,
+    pub(crate) actual: f64,
+    pub(crate) warnings: Vec<SampleRateWarning>,
+}
+
+impl MasterClockRateReport {
+    /// Returns the master clock rate that was requested, in hertz
+    pub fn requested(&self) -> f64 {
+        self.requested
+    }
+
+    /// Returns the master clock rate that the device actually applied, in hertz
+    pub fn actual(&self) -> f64 {
+        self.actual
+    }
+
+    /// Returns true if the device applied a different rate from the requested rate
+    pub fn is_coerced(&self) -> bool {
+        self.requested != self.actual
+    }
+
+    /// Returns the channels with sample rates that do not divide the new master clock rate
+    /// evenly
+    pub fn warnings(&self) -> &[SampleRateWarning] {
+        &self.warnings
+    }
+}
+
+/// A direction of a USRP channel
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChannelDirection {
+    Receive,
+    Transmit,
+}
+
+impl fmt::Display for ChannelDirection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChannelDirection::Receive => f.write_str("RX"),
+            ChannelDirection::Transmit => f.write_str("TX"),
+        }
+    }
+}
+
+/// A channel with a sample rate that is not an integer division of the master clock rate
+#[derive(Debug, Clone, PartialEq)]
+pub struct SampleRateWarning {
+    pub(crate) direction: ChannelDirection,
+    pub(crate) channel: usize,
+    pub(crate) previous_rate: f64,
+    pub(crate) rate: f64,
+    pub(crate) master_clock_rate: f64,
+}
+
+impl SampleRateWarning {
+    /// Returns the direction of the channel
+    pub fn direction(&self) -> ChannelDirection {
+        self.direction
+    }
+
+    /// Returns the index of the channel
+    pub fn channel(&self) -> usize {
+        self.channel
+    }
+
+    /// Returns the sample rate of the channel before the master clock rate was changed
+    pub fn previous_rate(&self) -> f64 {
+        self.previous_rate
+    }
+
+    /// Returns the sample rate of the channel after the master clock rate was changed
+    pub fn rate(&self) -> f64 {
+        self.rate
+    }
+
+    /// Returns the ratio of the master clock rate to the sample rate, which is not an integer
+    pub fn ratio(&self) -> f64 {
+        self.master_clock_rate / self.rate
+    }
+}
+
+impl fmt::Display for SampleRateWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} channel {}: sample rate {} Hz is not an integer division of master clock rate {} Hz (ratio {})",
+            self.direction,
+            self.channel,
+            self.rate,
+            self.master_clock_rate,
+            self.ratio()
+        )
+    }
+}
+
+/// Returns true if a sample rate is an integer division of a master clock rate
+///
+/// Rates that are not positive and finite never divide evenly.
+pub(crate) fn divides_evenly(master_clock_rate: f64, sample_rate: f64) -> bool {
+    // Sample rates read back from the device are rounded, so allow a small relative error
+    const TOLERANCE: f64 = 1e-6;
+    if !(master_clock_rate.is_finite() && sample_rate.is_finite() && sample_rate > 0.0) {
+        return false;
+    }
+    let ratio = master_clock_rate / sample_rate;
+    ratio.round() >= 1.0 && (ratio - ratio.round()).abs() <= TOLERANCE * ratio
+}
+
+/// Returns the index of the motherboard of each channel, from the number of channels on each
+/// motherboard
+pub(crate) fn channel_motherboards(channels_per_mboard: &[usize]) -> Vec<usize> {
+    channels_per_mboard
+        .iter()
+        .enumerate()
+        .flat_map(|(mboard, &channels)| vec![mboard; channels])
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{channel_motherboards, divides_evenly};
+
+    #[test]
+    fn integer_divisions() {
+        assert!(divides_evenly(30.72e6, 1.92e6));
+        assert!(divides_evenly(56e6, 56e6));
+        assert!(divides_evenly(200e6, 200e6 / 3.0));
+        assert!(!divides_evenly(30.72e6, 1e6));
+        assert!(!divides_evenly(16e6, 32e6));
+        assert!(!divides_evenly(16e6, 0.0));
+        assert!(!divides_evenly(f64::NAN, 1e6));
+    }
+
+    #[test]
+    fn motherboards_of_channels() {
+        assert_eq!(channel_motherboards(&[2, 0, 1]), vec![0, 0, 2]);
+        assert_eq!(channel_motherboards(&[]), Vec::<usize>::new());
+    }
+}
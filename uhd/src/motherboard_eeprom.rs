@@ -30,7 +30,7 @@ impl MotherboardEeprom {
         })
     }
 
-    pub(crate) fn handle(&mut self) -> uhd_sys::uhd_mboard_eeprom_handle {
+    pub(crate) fn handle(&self) -> uhd_sys::uhd_mboard_eeprom_handle {
         self.0
     }
 }
@@ -56,7 +56,7 @@ mod test {
     #[test]
     fn empty_eeprom() {
         let eeprom = MotherboardEeprom::default();
-        let res = eeprom.get("jabberwock".into());
+        let res = eeprom.get("jabberwock");
         assert!(res.is_ok() && res.unwrap().is_none())
     }
 }
@@ -0,0 +1,478 @@
+//! Transmitting samples from files
+
+use std::convert::{TryFrom, TryInto};
+use std::fs::File;
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use num_complex::Complex64;
+
+use crate::device::TransmitStream;
+use crate::error::{Error, Result};
+#[cfg(feature = "sigmf")]
+use crate::sigmf;
+use crate::stream::Sample;
+use crate::{AsyncMetadata, TimeSpec, TransmitMetadata};
+
+/// The timeout, in seconds, for each send call
+const SEND_TIMEOUT: f64 = 1.0;
+
+/// The format of samples in a file
+///
+/// All formats are interleaved I/Q pairs in little-endian byte order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileFormat {
+    /// Complex 64-bit floating-point (SigMF `cf64_le`)
+    Fc64,
+    /// Complex 32-bit floating-point (SigMF `cf32_le`)
+    Fc32,
+    /// Complex 16-bit integer (SigMF `ci16_le`)
+    Sc16,
+    /// Complex 8-bit integer (SigMF `ci8`)
+    Sc8,
+}
+
+impl FileFormat {
+    /// Returns the format with a SigMF `core:datatype`, if it is supported
+    pub fn from_sigmf_datatype(datatype: &str) -> Option<Self> {
+        match datatype {
+            "cf64_le" => Some(FileFormat::Fc64),
+            "cf32_le" => Some(FileFormat::Fc32),
+            "ci16_le" => Some(FileFormat::Sc16),
+            "ci8" => Some(FileFormat::Sc8),
+            _ => None,
+        }
+    }
+
+    /// Returns the size of one sample, in bytes
+    pub fn sample_size(&self) -> usize {
+        match self {
+            FileFormat::Fc64 => 16,
+            FileFormat::Fc32 => 8,
+            FileFormat::Sc16 => 4,
+            FileFormat::Sc8 => 2,
+        }
+    }
+
+    /// Decodes one sample, with integer formats scaled to [-1, 1]
+    fn decode(&self, bytes: &[u8]) -> Complex64 {
+        match self {
+            FileFormat::Fc64 => Complex64::new(
+                f64::from_le_bytes(bytes[..8].try_into().unwrap()),
+                f64::from_le_bytes(bytes[8..16].try_into().unwrap()),
+            ),
+            FileFormat::Fc32 => Complex64::new(
+                f32::from_le_bytes(bytes[..4].try_into().unwrap()).into(),
+                f32::from_le_bytes(bytes[4..8].try_into().unwrap()).into(),
+            ),
+            FileFormat::Sc16 => Complex64::new(
+                f64::from(i16::from_le_bytes([bytes[0], bytes[1]])) / f64::from(i16::MAX),
+                f64::from(i16::from_le_bytes([bytes[2], bytes[3]])) / f64::from(i16::MAX),
+            ),
+            FileFormat::Sc8 => Complex64::new(
+                f64::from(bytes[0] as i8) / f64::from(i8::MAX),
+                f64::from(bytes[1] as i8) / f64::from(i8::MAX),
+            ),
+        }
+    }
+}
+
+/// How many times to play a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Repeat {
+    /// Play the file this many times
+    Times(u64),
+    /// Play the file until stopped
+    Forever,
+}
+
+/// Transmits the samples in a raw or SigMF file
+///
+/// All repetitions of the file are sent as one burst: the first packet has the start of burst
+/// flag (and the start time, if one is set), and the last packet has the end of burst flag.
+///
+/// ```no_run
+/// use num_complex::Complex32;
+/// use uhd::{FileFormat, Playback, Repeat, StreamArgs, TimeSpec, Usrp, WireFormat};
+///
+/// let mut usrp = Usrp::open("")?;
+/// let start = usrp.get_current_time(0)? + TimeSpec::new(1, 0.0);
+/// let mut streamer = usrp.get_tx_stream(&StreamArgs::<Complex32>::new(WireFormat::Sc16))?;
+/// Playback::raw("waveform.sc16", FileFormat::Sc16)
+///     .repeat(Repeat::Times(10))
+///     .start_time(start)
+///     .transmit(&mut streamer)?;
+/// # Ok::<(), uhd::Error>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct Playback {
+    path: PathBuf,
+    format: FileFormat,
+    sample_rate: Option<f64>,
+    repeat: Repeat,
+    start_time: Option<TimeSpec>,
+    block_length: usize,
+}
+
+impl Playback {
+    /// Creates a playback of a file of raw samples, played once, starting immediately
+    pub fn raw<P>(path: P, format: FileFormat) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        Playback {
+            path: path.into(),
+            format,
+            sample_rate: None,
+            repeat: Repeat::Times(1),
+            start_time: None,
+            block_length: 4096,
+        }
+    }
+
+    /// Creates a playback of a SigMF recording, played once, starting immediately
+    ///
+    /// `base` is the path of the recording without the `.sigmf-meta` or `.sigmf-data`
+    /// extension. The data type comes from the `core:datatype` field of the metadata.
+    #[cfg(feature = "sigmf")]
+    pub fn sigmf<P>(base: P) -> Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let base = base.as_ref();
+        let meta_file = File::open(sigmf::meta_path(base))?;
+        let meta: serde_json::Value = serde_json::from_reader(BufReader::new(meta_file))
+            .map_err(|e| Error::Unique(format!("Invalid SigMF metadata: {}", e)))?;
+        let global = &meta["global"];
+        let datatype = global["core:datatype"]
+            .as_str()
+            .ok_or_else(|| Error::Unique("SigMF metadata has no core:datatype".into()))?;
+        let format = FileFormat::from_sigmf_datatype(datatype)
+            .ok_or_else(|| Error::Unique(format!("Unsupported SigMF datatype {}", datatype)))?;
+        Ok(Playback {
+            sample_rate: global["core:sample_rate"].as_f64(),
+            ..Playback::raw(sigmf::data_path(base), format)
+        })
+    }
+
+    /// Returns the sample rate of the recording, if the file specifies one
+    ///
+    /// This does not change the sample rate of the device.
+    pub fn sample_rate(&self) -> Option<f64> {
+        self.sample_rate
+    }
+
+    /// Returns the path of the file with the samples
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Sets how many times to play the file
+    pub fn repeat(self, repeat: Repeat) -> Self {
+        Playback { repeat, ..self }
+    }
+
+    /// Sets the device time when the first sample should be transmitted
+    pub fn start_time(self, time: TimeSpec) -> Self {
+        Playback {
+            start_time: Some(time),
+            ..self
+        }
+    }
+
+    /// Sets the maximum number of samples to send at a time
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `block_length` is zero.
+    pub fn block_length(self, block_length: usize) -> Self {
+        assert_ne!(block_length, 0, "Block length must not be zero");
+        Playback {
+            block_length,
+            ..self
+        }
+    }
+
+    /// Transmits the file on a single-channel stream and returns the number of samples sent
+    ///
+    /// With [`Repeat::Forever`], this returns only if an error occurs.
+    pub fn transmit<I, S>(&self, streamer: &mut S) -> Result<u64>
+    where
+        I: Sample,
+        S: TransmitStream<I>,
+    {
+        self.transmit_until(streamer, &AtomicBool::new(false))
+    }
+
+    /// Transmits the file on a single-channel stream until all repetitions have been sent or
+    /// `stop` is true, and returns the number of samples sent
+    ///
+    /// If stopped early, this ends the burst with an empty packet. If a send call times out
+    /// without sending any samples, this returns an error with the kind
+    /// [`TimedOut`](std::io::ErrorKind::TimedOut).
+    pub fn transmit_until<I, S>(&self, streamer: &mut S, stop: &AtomicBool) -> Result<u64>
+    where
+        I: Sample,
+        S: TransmitStream<I>,
+    {
+        self.transmit_with_messages(streamer, stop, |_| {})
+    }
+
+    /// Transmits the file like [`transmit_until`](Self::transmit_until), passing each
+    /// asynchronous message from the streamer (underflows, burst acknowledgements, ...) to
+    /// `on_message`
+    ///
+    /// Messages are checked after each block is sent, so they may arrive a few blocks late.
+    pub fn transmit_with_messages<I, S, F>(
+        &self,
+        streamer: &mut S,
+        stop: &AtomicBool,
+        mut on_message: F,
+    ) -> Result<u64>
+    where
+        I: Sample,
+        S: TransmitStream<I>,
+        F: FnMut(AsyncMetadata),
+    {
+        if streamer.num_channels() != 1 {
+            return Err(Error::Unique(format!(
+                "Playback needs a stream with 1 channel, not {}",
+                streamer.num_channels()
+            )));
+        }
+        let mut file = File::open(&self.path)?;
+        let file_length = file.metadata()?.len();
+        let sample_size = self.format.sample_size() as u64;
+        if file_length == 0 || file_length % sample_size != 0 {
+            return Err(Error::Unique(format!(
+                "{} has {} bytes, which is not a positive multiple of the {}-byte sample size",
+                self.path.display(),
+                file_length,
+                sample_size
+            )));
+        }
+        let file_samples = file_length / sample_size;
+        let total = match self.repeat {
+            Repeat::Times(times) => Some(file_samples.saturating_mul(times)),
+            Repeat::Forever => None,
+        };
+
+        let mut reader = BufReader::new(&mut file);
+        let mut bytes = vec![0u8; self.block_length * self.format.sample_size()];
+        let mut samples: Vec<I> = Vec::with_capacity(self.block_length);
+        let mut sent = 0u64;
+        let mut position = 0u64;
+        while !matches!(total, Some(total) if sent >= total) {
+            if stop.load(Ordering::Relaxed) {
+                // End the burst
+                let mut metadata = TransmitMetadata::with_flags(sent == 0, true, None);
+                streamer.send(&mut [&[]], &mut metadata, SEND_TIMEOUT)?;
+                break;
+            }
+            if position == file_samples {
+                reader.seek(SeekFrom::Start(0))?;
+                position = 0;
+            }
+            let length = usize::try_from(file_samples - position)
+                .map_or(self.block_length, |remaining| {
+                    remaining.min(self.block_length)
+                });
+            let block_bytes = &mut bytes[..length * self.format.sample_size()];
+            reader.read_exact(block_bytes)?;
+            position += length as u64;
+            samples.clear();
+            samples.extend(
+                block_bytes
+                    .chunks_exact(self.format.sample_size())
+                    .map(|sample| I::from_fc64(self.format.decode(sample))),
+            );
+
+            let last = matches!(total, Some(total) if sent + length as u64 == total);
+            sent += self.send_block(streamer, &samples, sent == 0, last, stop)? as u64;
+            while let Some(message) = streamer.recv_async_msg(0.0)? {
+                on_message(message);
+            }
+        }
+        Ok(sent)
+    }
+
+    /// Sends the samples in a block, which may take several send calls, and returns the number
+    /// of samples sent
+    ///
+    /// This returns early if `stop` becomes true, and returns an error if a send call times out
+    /// without sending any samples.
+    fn send_block<I, S>(
+        &self,
+        streamer: &mut S,
+        samples: &[I],
+        first: bool,
+        last: bool,
+        stop: &AtomicBool,
+    ) -> Result<usize>
+    where
+        I: Sample,
+        S: TransmitStream<I>,
+    {
+        let mut offset = 0;
+        while offset < samples.len() && !stop.load(Ordering::Relaxed) {
+            let mut metadata = if first && offset == 0 {
+                TransmitMetadata::with_flags(true, last, self.start_time)
+            } else {
+                TransmitMetadata::with_flags(false, last, None)
+            };
+            let sent = streamer.send(&mut [&samples[offset..]], &mut metadata, SEND_TIMEOUT)?;
+            if sent == 0 {
+                return Err(Error::IO(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    "Timed out sending samples",
+                )));
+            }
+            offset += sent;
+        }
+        Ok(offset)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+    use std::sync::atomic::AtomicBool;
+
+    use num_complex::Complex32;
+
+    use super::{FileFormat, Playback, Repeat};
+    use crate::device::TransmitStream;
+    use crate::sim::SimUsrp;
+    use crate::{
+        AsyncEventCode, AsyncMetadata, Device, Error, StreamArgs, TimeSpec, TransmitMetadata,
+        WireFormat,
+    };
+
+    #[test]
+    fn decode_formats() {
+        let sample = FileFormat::Sc16.decode(&[0xff, 0x7f, 0x01, 0x80]);
+        assert_eq!((sample.re, sample.im), (1.0, -1.0));
+        let sample = FileFormat::Sc8.decode(&[0x00, 0x81]);
+        assert_eq!((sample.re, sample.im), (0.0, -1.0));
+        let mut bytes = 0.25f32.to_le_bytes().to_vec();
+        bytes.extend_from_slice(&(-0.5f32).to_le_bytes());
+        let sample = FileFormat::Fc32.decode(&bytes);
+        assert_eq!((sample.re, sample.im), (0.25, -0.5));
+        assert_eq!(
+            FileFormat::from_sigmf_datatype("ci16_le"),
+            Some(FileFormat::Sc16)
+        );
+        assert_eq!(FileFormat::from_sigmf_datatype("ri16_le"), None);
+    }
+
+    #[test]
+    fn play_raw_file() {
+        let path =
+            std::env::temp_dir().join(format!("uhd-playback-test-{}.sc8", std::process::id()));
+        // 1000 samples
+        fs::write(&path, vec![64u8; 2000]).unwrap();
+        let mut usrp = SimUsrp::new();
+        let control = usrp.clone();
+        let mut streamer = usrp
+            .get_tx_stream(&StreamArgs::<Complex32>::new(WireFormat::Sc16))
+            .unwrap();
+
+        let mut acks = 0;
+        let sent = Playback::raw(&path, FileFormat::Sc8)
+            .repeat(Repeat::Times(3))
+            .block_length(300)
+            .start_time(TimeSpec::new(1, 0.0))
+            .transmit_with_messages(&mut streamer, &AtomicBool::new(false), |message| {
+                assert_eq!(message.event_code(), AsyncEventCode::BurstAck);
+                acks += 1;
+            })
+            .unwrap();
+        assert_eq!(sent, 3000);
+        assert_eq!(control.transmitted_samples(0).unwrap(), 3000);
+        assert_eq!(acks, 1);
+
+        // A stopped playback ends the burst without sending samples
+        let stop = AtomicBool::new(true);
+        let sent = Playback::raw(&path, FileFormat::Sc8)
+            .repeat(Repeat::Forever)
+            .transmit_until(&mut streamer, &stop)
+            .unwrap();
+        assert_eq!(sent, 0);
+
+        // A block length that does not divide the file length leaves a shorter last block
+        let sent = Playback::raw(&path, FileFormat::Fc64)
+            .repeat(Repeat::Times(1))
+            .block_length(7)
+            .transmit(&mut streamer)
+            .unwrap();
+        assert_eq!(sent, 125);
+
+        // The file length must be a multiple of the sample size
+        fs::write(&path, vec![0u8; 3]).unwrap();
+        assert!(Playback::raw(&path, FileFormat::Sc16)
+            .transmit(&mut streamer)
+            .is_err());
+        fs::remove_file(&path).unwrap();
+    }
+
+    /// A transmit stream that never accepts any samples
+    struct StalledStream;
+
+    impl TransmitStream<Complex32> for StalledStream {
+        fn num_channels(&self) -> usize {
+            1
+        }
+
+        fn send(
+            &mut self,
+            _buffers: &mut [&[Complex32]],
+            _metadata: &mut TransmitMetadata,
+            _timeout: f64,
+        ) -> Result<usize, Error> {
+            Ok(0)
+        }
+
+        fn recv_async_msg(&mut self, _timeout: f64) -> Result<Option<AsyncMetadata>, Error> {
+            Ok(None)
+        }
+    }
+
+    #[test]
+    fn send_timeout() {
+        let path =
+            std::env::temp_dir().join(format!("uhd-playback-timeout-{}.sc8", std::process::id()));
+        fs::write(&path, vec![0u8; 200]).unwrap();
+        let result = Playback::raw(&path, FileFormat::Sc8).transmit(&mut StalledStream);
+        match result {
+            Err(Error::IO(e)) => assert_eq!(e.kind(), std::io::ErrorKind::TimedOut),
+            other => panic!("Expected a timeout error, got {:?}", other),
+        }
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[cfg(feature = "sigmf")]
+    #[test]
+    fn sigmf_metadata() {
+        use crate::sigmf::{data_path, meta_path};
+
+        let base = std::env::temp_dir().join(format!("uhd-playback-test.{}", std::process::id()));
+        fs::write(
+            meta_path(&base),
+            r#"{"global": {"core:datatype": "ci16_le", "core:sample_rate": 1e6}}"#,
+        )
+        .unwrap();
+        let playback = Playback::sigmf(&base).unwrap();
+        assert_eq!(playback.format, FileFormat::Sc16);
+        assert_eq!(playback.sample_rate(), Some(1e6));
+        assert_eq!(playback.path(), data_path(&base));
+
+        fs::write(
+            meta_path(&base),
+            r#"{"global": {"core:datatype": "rf32_le"}}"#,
+        )
+        .unwrap();
+        assert!(Playback::sigmf(&base).is_err());
+        fs::remove_file(meta_path(&base)).unwrap();
+    }
+}
@@ -1,10 +1,35 @@
 use crate::error::{check_status, Error};
+use std::iter::FromIterator;
 use std::ptr;
 
 /// A range of floating-point values, and a step-by amount
 #[derive(Clone)]
 pub struct Range(uhd_sys::uhd_range_t);
 
+impl Range {
+    /// Creates a range from a start value, a stop value, and a step value
+    ///
+    /// A step of 0 means that any value between start and stop is allowed.
+    pub fn new(start: f64, stop: f64, step: f64) -> Self {
+        Range(uhd_sys::uhd_range_t { start, stop, step })
+    }
+
+    /// Returns the start (minimum) of this range
+    pub fn start(&self) -> f64 {
+        self.0.start
+    }
+
+    /// Returns the stop (maximum) of this range
+    pub fn stop(&self) -> f64 {
+        self.0.stop
+    }
+
+    /// Returns the step of this range
+    pub fn step(&self) -> f64 {
+        self.0.step
+    }
+}
+
 impl Default for Range {
     fn default() -> Self {
         Range(uhd_sys::uhd_range_t {
@@ -20,8 +45,10 @@ impl Default for Range {
 /// The ranges in a meta-range should be monotonic (the start of each range should be greater
 /// than or equal to the end of the preceding range). Gaps between ranges are allowed.
 ///
-/// Most MetaRange methods will return errors if called on a non-monotonic range.
-pub struct MetaRange(uhd_sys::uhd_meta_range_handle);
+/// The start, stop, and step methods return errors if called on an empty or non-monotonic
+/// meta-range.
+#[derive(Clone, Default)]
+pub struct MetaRange(Vec<Range>);
 
 impl MetaRange {
     /// Creates an empty meta-range
@@ -31,85 +58,83 @@ impl MetaRange {
 
     /// Returns the overall start of this meta-range
     pub fn start(&self) -> Result<f64, Error> {
-        let mut start = 0.0;
-        check_status(unsafe { uhd_sys::uhd_meta_range_start(self.0, &mut start) })?;
-        Ok(start)
+        self.check_monotonic()?;
+        Ok(self
+            .0
+            .iter()
+            .map(Range::start)
+            .fold(f64::INFINITY, f64::min))
     }
 
     /// Returns the overall end (stop) of this meta-range
     pub fn stop(&self) -> Result<f64, Error> {
-        let mut stop = 0.0;
-        check_status(unsafe { uhd_sys::uhd_meta_range_stop(self.0, &mut stop) })?;
-        Ok(stop)
+        self.check_monotonic()?;
+        Ok(self
+            .0
+            .iter()
+            .map(Range::stop)
+            .fold(f64::NEG_INFINITY, f64::max))
     }
 
     /// Returns the "overall step value" of this meta-range (the minimum of the step values of
     /// each contained range, and the gaps between ranges)
     pub fn step(&self) -> Result<f64, Error> {
-        let mut step = 0.0;
-        check_status(unsafe { uhd_sys::uhd_meta_range_step(self.0, &mut step) })?;
-        Ok(step)
+        self.check_monotonic()?;
+        let steps = self.0.iter().map(Range::step);
+        let gaps = self
+            .0
+            .windows(2)
+            .map(|pair| pair[1].start() - pair[0].stop());
+        let min_step = steps
+            .chain(gaps)
+            .filter(|&step| step > 0.0)
+            .fold(f64::INFINITY, f64::min);
+        // Like UHD, return 0 if all steps and gaps are 0
+        Ok(if min_step.is_finite() { min_step } else { 0.0 })
     }
 
     /// Returns the number of ranges in this meta-range
     pub fn len(&self) -> usize {
-        let mut length = 0usize;
-        check_status(unsafe {
-            uhd_sys::uhd_meta_range_size(self.0, &mut length as *mut usize as *mut _)
-        })
-        .unwrap();
-        length
+        self.0.len()
     }
 
     /// Checks if meta-range is empty
     pub fn is_empty(&self) -> bool {
-        self.len() == 0
+        self.0.is_empty()
     }
 
     /// Returns the range at the provided index, if one exists
     pub fn get(&self, index: usize) -> Option<Range> {
-        let mut range = Range::default();
-        match check_status(unsafe { uhd_sys::uhd_meta_range_at(self.0, index as _, &mut range.0) })
-        {
-            Ok(()) => Some(range),
-            Err(e) => match e {
-                // StdExcept usually indicates a std::out_of_range because index >= length
-                Error::StdExcept => None,
-                _ => panic!("Unexpected UHD error: {}", e),
-            },
-        }
+        self.0.get(index).cloned()
     }
+
     /// Appends a range to the end of this meta-range
     pub fn push(&mut self, range: Range) {
-        check_status(unsafe { uhd_sys::uhd_meta_range_push_back(self.0, &range.0) }).unwrap();
+        self.0.push(range)
     }
 
     /// Returns an iterator over ranges in this meta-range
     pub fn iter(&self) -> Iter<'_> {
-        Iter {
-            range: self,
-            next: 0,
-            length: self.len(),
-        }
+        Iter(self.0.iter())
     }
 
-    pub(crate) fn handle(&mut self) -> uhd_sys::uhd_meta_range_handle {
-        self.0
-    }
-}
-
-impl Default for MetaRange {
-    /// Creates an empty meta-range
-    fn default() -> Self {
-        let mut handle = ptr::null_mut();
-        check_status(unsafe { uhd_sys::uhd_meta_range_make(&mut handle) }).unwrap();
-        MetaRange(handle)
+    /// Returns an error if this meta-range is empty or not monotonic, like UHD does
+    fn check_monotonic(&self) -> Result<(), Error> {
+        let monotonic = self
+            .0
+            .windows(2)
+            .all(|pair| pair[1].start() >= pair[0].stop());
+        if !self.is_empty() && monotonic {
+            Ok(())
+        } else {
+            Err(Error::Value)
+        }
     }
 }
 
-impl Drop for MetaRange {
-    fn drop(&mut self) {
-        let _ = unsafe { uhd_sys::uhd_meta_range_free(&mut self.0) };
+impl FromIterator<Range> for MetaRange {
+    fn from_iter<T: IntoIterator<Item = Range>>(iter: T) -> Self {
+        MetaRange(iter.into_iter().collect())
     }
 }
 
@@ -123,42 +148,68 @@ impl<'m> IntoIterator for &'m MetaRange {
 }
 
 /// An iterator over ranges in a meta-range
-pub struct Iter<'m> {
-    range: &'m MetaRange,
-    /// The index of the next element to yield
-    /// Invariant: next <= length
-    next: usize,
-    length: usize,
-}
+pub struct Iter<'m>(std::slice::Iter<'m, Range>);
 
 impl Iterator for Iter<'_> {
     type Item = Range;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.next == self.length {
-            None
-        } else {
-            let item = self.range.get(self.next).unwrap();
-            self.next += 1;
-            Some(item)
-        }
+        self.0.next().cloned()
     }
 
     fn size_hint(&self) -> (usize, Option<usize>) {
-        let remaining = self.length - self.next;
-        (remaining, Some(remaining))
+        self.0.size_hint()
     }
 
     fn count(self) -> usize
     where
         Self: Sized,
     {
-        self.length - self.next
+        self.0.count()
     }
 }
 
 impl ExactSizeIterator for Iter<'_> {}
 
+/// A meta-range that UHD functions can write to
+pub(crate) struct MetaRangeHandle(uhd_sys::uhd_meta_range_handle);
+
+impl MetaRangeHandle {
+    /// Creates an empty meta-range
+    pub fn new() -> Result<Self, Error> {
+        let mut handle = ptr::null_mut();
+        check_status(unsafe { uhd_sys::uhd_meta_range_make(&mut handle) })?;
+        Ok(MetaRangeHandle(handle))
+    }
+
+    /// Copies the ranges out of this meta-range
+    pub fn to_meta_range(&self) -> Result<MetaRange, Error> {
+        let mut length = 0usize;
+        check_status(unsafe {
+            uhd_sys::uhd_meta_range_size(self.0, &mut length as *mut usize as *mut _)
+        })?;
+        (0..length)
+            .map(|index| {
+                let mut range = Range::default();
+                check_status(unsafe {
+                    uhd_sys::uhd_meta_range_at(self.0, index as _, &mut range.0)
+                })?;
+                Ok(range)
+            })
+            .collect()
+    }
+
+    pub fn handle(&self) -> uhd_sys::uhd_meta_range_handle {
+        self.0
+    }
+}
+
+impl Drop for MetaRangeHandle {
+    fn drop(&mut self) {
+        let _ = unsafe { uhd_sys::uhd_meta_range_free(&mut self.0) };
+    }
+}
+
 mod fmt {
     use super::{MetaRange, Range};
     use std::fmt::{Debug, Formatter, Result};
@@ -179,3 +230,71 @@ mod fmt {
         }
     }
 }
+
+#[cfg(feature = "serde")]
+mod serde_impl {
+    use super::{MetaRange, Range};
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    /// The serialized form of a range
+    #[derive(Serialize, Deserialize)]
+    #[serde(rename = "Range")]
+    struct Fields {
+        start: f64,
+        stop: f64,
+        step: f64,
+    }
+
+    impl Serialize for Range {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            Fields {
+                start: self.0.start,
+                stop: self.0.stop,
+                step: self.0.step,
+            }
+            .serialize(serializer)
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Range {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let fields = Fields::deserialize(deserializer)?;
+            Ok(Range::new(fields.start, fields.stop, fields.step))
+        }
+    }
+
+    /// A meta-range is serialized as a list of ranges
+    impl Serialize for MetaRange {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.collect_seq(self.iter())
+        }
+    }
+
+    impl<'de> Deserialize<'de> for MetaRange {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let ranges = Vec::<Range>::deserialize(deserializer)?;
+            Ok(ranges.into_iter().collect())
+        }
+    }
+
+    #[cfg(test)]
+    mod test {
+        use super::{MetaRange, Range};
+
+        #[test]
+        fn meta_range_round_trip() {
+            let range: MetaRange = vec![Range::new(0.0, 10.0, 0.5), Range::new(20.0, 30.0, 0.0)]
+                .into_iter()
+                .collect();
+            let json = serde_json::to_string(&range).unwrap();
+            assert_eq!(
+                json,
+                r#"[{"start":0.0,"stop":10.0,"step":0.5},{"start":20.0,"stop":30.0,"step":0.0}]"#
+            );
+            let parsed: MetaRange = serde_json::from_str(&json).unwrap();
+            assert_eq!(parsed.len(), 2);
+            assert_eq!(parsed.get(1).unwrap().start(), 20.0);
+            assert_eq!(parsed.stop().unwrap(), 30.0);
+        }
+    }
+}
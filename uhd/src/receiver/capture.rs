@@ -0,0 +1,578 @@
+//! Continuous reception on a dedicated thread
+//!
+//! [`ContinuousReceiver`] starts streaming, receives samples on an I/O thread, and passes blocks
+//! of samples to the consumer through a lock-free ring buffer. Buffers for the blocks are
+//! allocated when the capture starts and returned to the I/O thread when blocks are dropped, so
+//! a running capture does not allocate unless the consumer keeps more blocks than the ring can
+//! hold.
+
+use std::fmt;
+use std::iter;
+use std::mem;
+use std::panic;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle, Scope, ScopedJoinHandle};
+use std::time::{Duration, Instant};
+
+use num_complex::Complex64;
+
+use super::ring::{ring, Consumer};
+use crate::device::ReceiveStream;
+use crate::error::Result;
+use crate::stream::{Sample, StreamCommand, StreamCommandType, StreamTime};
+use crate::{ReceiveError, ReceiveErrorKind, TimeSpec};
+
+/// Options for a continuous capture
+#[derive(Debug, Clone)]
+pub struct CaptureOptions {
+    block_length: usize,
+    blocks: usize,
+    timeout: f64,
+    start: StreamTime,
+}
+
+impl CaptureOptions {
+    /// Creates default options: blocks of 4096 samples, a ring of 32 blocks, a receive timeout of
+    /// 0.1 seconds, and streaming that starts immediately
+    pub fn new() -> Self {
+        CaptureOptions {
+            block_length: 4096,
+            blocks: 32,
+            timeout: 0.1,
+            start: StreamTime::Now,
+        }
+    }
+
+    /// Sets the maximum number of samples per channel in each block
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `block_length` is zero.
+    pub fn block_length(self, block_length: usize) -> Self {
+        assert_ne!(block_length, 0, "Block length must not be zero");
+        CaptureOptions {
+            block_length,
+            ..self
+        }
+    }
+
+    /// Sets the number of blocks that the ring can hold before the I/O thread waits for the
+    /// consumer
+    ///
+    /// When the ring is full, the device buffers fill up and reports an overflow. Buffers for
+    /// all of the blocks are allocated when the capture starts.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `blocks` is zero.
+    pub fn blocks(self, blocks: usize) -> Self {
+        assert_ne!(blocks, 0, "Number of blocks must not be zero");
+        CaptureOptions { blocks, ..self }
+    }
+
+    /// Returns the number of blocks that the ring can hold
+    pub(crate) fn num_blocks(&self) -> usize {
+        self.blocks
+    }
+
+    /// Sets the timeout, in seconds, for each receive call on the I/O thread
+    pub fn timeout(self, timeout: f64) -> Self {
+        CaptureOptions { timeout, ..self }
+    }
+
+    /// Sets when streaming should start
+    pub fn start(self, start: StreamTime) -> Self {
+        CaptureOptions { start, ..self }
+    }
+}
+
+impl Default for CaptureOptions {
+    fn default() -> Self {
+        CaptureOptions::new()
+    }
+}
+
+/// A block of samples received on all channels of a stream
+///
+/// A block with an error may contain no samples. Timeouts are reported only after the first
+/// samples have been received, so waiting for a timed start does not produce errors.
+pub struct RxBlock<I> {
+    buffers: Vec<Vec<I>>,
+    samples: usize,
+    time_spec: Option<TimeSpec>,
+    start_of_burst: bool,
+    end_of_burst: bool,
+    error: Option<ReceiveError>,
+    /// Used to return the buffers to the I/O thread
+    recycle: SyncSender<Vec<Vec<I>>>,
+}
+
+impl<I> RxBlock<I> {
+    /// Returns the number of samples per channel in this block
+    pub fn samples(&self) -> usize {
+        self.samples
+    }
+
+    /// Returns true if this block contains no samples
+    pub fn is_empty(&self) -> bool {
+        self.samples == 0
+    }
+
+    /// Returns the number of channels
+    pub fn num_channels(&self) -> usize {
+        self.buffers.len()
+    }
+
+    /// Returns the samples received on a channel of the stream
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `channel` is not less than the number of channels.
+    pub fn channel(&self, channel: usize) -> &[I] {
+        &self.buffers[channel][..self.samples]
+    }
+
+    /// Returns the samples received on each channel of the stream, in order
+    pub fn channels(&self) -> impl Iterator<Item = &[I]> {
+        let samples = self.samples;
+        self.buffers.iter().map(move |buffer| &buffer[..samples])
+    }
+
+    /// Returns the device time of the first sample in this block
+    pub fn time_spec(&self) -> Option<TimeSpec> {
+        self.time_spec
+    }
+
+    /// Returns true if this block starts a burst
+    pub fn start_of_burst(&self) -> bool {
+        self.start_of_burst
+    }
+
+    /// Returns true if this block ends a burst
+    pub fn end_of_burst(&self) -> bool {
+        self.end_of_burst
+    }
+
+    /// Returns the error that the device reported when receiving this block
+    pub fn error(&self) -> Option<&ReceiveError> {
+        self.error.as_ref()
+    }
+}
+
+impl<I> Drop for RxBlock<I> {
+    fn drop(&mut self) {
+        // If the I/O thread has stopped or already has enough spare buffers, they are freed
+        let _ = self.recycle.try_send(mem::take(&mut self.buffers));
+    }
+}
+
+impl<I> fmt::Debug for RxBlock<I> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RxBlock")
+            .field("channels", &self.buffers.len())
+            .field("samples", &self.samples)
+            .field("time_spec", &self.time_spec)
+            .field("start_of_burst", &self.start_of_burst)
+            .field("end_of_burst", &self.end_of_burst)
+            .field("error", &self.error)
+            .finish()
+    }
+}
+
+/// Receives samples continuously on a dedicated I/O thread
+///
+/// Iterating over a `ContinuousReceiver` returns blocks in order, waiting for each one. Iteration
+/// ends when the I/O thread stops, which happens after an error from UHD, after a late command
+/// or broken chain error, or when the stream stops on its own. [`stop`](Self::stop) returns the
+/// error that stopped the thread, if any.
+///
+/// Dropping a `ContinuousReceiver` stops streaming and waits for the I/O thread to finish.
+///
+/// ```no_run
+/// use std::thread;
+/// use num_complex::Complex32;
+/// use uhd::{CaptureOptions, ContinuousReceiver, StreamArgs, Usrp, WireFormat};
+///
+/// let mut usrp = Usrp::open("")?;
+/// let streamer = usrp.get_rx_stream(&StreamArgs::<Complex32>::new(WireFormat::Sc16))?;
+/// thread::scope(|scope| -> Result<(), uhd::Error> {
+///     let capture = ContinuousReceiver::spawn_scoped(scope, streamer, CaptureOptions::new())?;
+///     for block in capture.take(100) {
+///         if let Some(error) = block.error() {
+///             eprintln!("Receive error: {:?}", error.kind());
+///         }
+///         println!("{} samples at {:?}", block.samples(), block.time_spec());
+///     }
+///     Ok(())
+/// })?;
+/// # Ok::<(), uhd::Error>(())
+/// ```
+pub struct ContinuousReceiver<'scope, I> {
+    /// The receiving end of the ring (None only while dropping)
+    blocks: Option<Consumer<RxBlock<I>>>,
+    stop: Arc<AtomicBool>,
+    worker: Option<Worker<'scope>>,
+    num_channels: usize,
+}
+
+enum Worker<'scope> {
+    Static(JoinHandle<Result<()>>),
+    Scoped(ScopedJoinHandle<'scope, Result<()>>),
+}
+
+impl Worker<'_> {
+    fn join(self) -> thread::Result<Result<()>> {
+        match self {
+            Worker::Static(handle) => handle.join(),
+            Worker::Scoped(handle) => handle.join(),
+        }
+    }
+}
+
+impl<I> ContinuousReceiver<'static, I>
+where
+    I: Sample + Send + 'static,
+{
+    /// Starts streaming and receives on a new thread
+    ///
+    /// Streamers that borrow a device can be used with [`spawn_scoped`](Self::spawn_scoped).
+    pub fn spawn<S>(streamer: S, options: CaptureOptions) -> Result<Self>
+    where
+        S: ReceiveStream<I> + Send + 'static,
+    {
+        ContinuousReceiver::start(streamer, options, |io| {
+            thread::Builder::new()
+                .name("uhd-rx".into())
+                .spawn(io)
+                .map(Worker::Static)
+        })
+    }
+}
+
+impl<'scope, I> ContinuousReceiver<'scope, I>
+where
+    I: Sample + Send + 'scope,
+{
+    /// Starts streaming and receives on a new thread in a scope
+    pub fn spawn_scoped<'env, S>(
+        scope: &'scope Scope<'scope, 'env>,
+        streamer: S,
+        options: CaptureOptions,
+    ) -> Result<Self>
+    where
+        S: ReceiveStream<I> + Send + 'scope,
+    {
+        ContinuousReceiver::start(streamer, options, |io| {
+            thread::Builder::new()
+                .name("uhd-rx".into())
+                .spawn_scoped(scope, io)
+                .map(Worker::Scoped)
+        })
+    }
+
+    fn start<S, F>(streamer: S, options: CaptureOptions, spawn: F) -> Result<Self>
+    where
+        S: ReceiveStream<I> + Send + 'scope,
+        F: FnOnce(
+            Box<dyn FnOnce() -> Result<()> + Send + 'scope>,
+        ) -> std::io::Result<Worker<'scope>>,
+    {
+        let (mut producer, blocks) = ring(options.num_blocks());
+        let stop = Arc::new(AtomicBool::new(false));
+        let io = IoThread::start(streamer, options, Arc::clone(&stop))?;
+        let num_channels = io.num_channels();
+        let worker = spawn(Box::new(move || {
+            io.run(|block| producer.push(block).is_ok())
+        }))?;
+        Ok(ContinuousReceiver {
+            blocks: Some(blocks),
+            stop,
+            worker: Some(worker),
+            num_channels,
+        })
+    }
+}
+
+impl<I> ContinuousReceiver<'_, I> {
+    /// Returns the number of channels that this receiver receives
+    pub fn num_channels(&self) -> usize {
+        self.num_channels
+    }
+
+    /// Returns the next block if one is available, without waiting
+    pub fn try_next(&mut self) -> Option<RxBlock<I>> {
+        self.blocks.as_mut()?.try_pop()
+    }
+
+    /// Waits up to `timeout` for the next block
+    ///
+    /// This returns None if the timeout expires or the I/O thread has stopped.
+    pub fn next_timeout(&mut self, timeout: Duration) -> Option<RxBlock<I>> {
+        let deadline = Instant::now() + timeout;
+        self.blocks.as_mut()?.pop(Some(deadline))
+    }
+
+    /// Returns true if the I/O thread has stopped
+    ///
+    /// Blocks that the thread received before it stopped may still be available.
+    pub fn is_finished(&self) -> bool {
+        match &self.worker {
+            Some(Worker::Static(handle)) => handle.is_finished(),
+            Some(Worker::Scoped(handle)) => handle.is_finished(),
+            None => true,
+        }
+    }
+
+    /// Stops streaming, discards any blocks that have not been received, and waits for the I/O
+    /// thread to finish
+    ///
+    /// This returns the error that stopped the I/O thread, or the error from stopping the stream.
+    pub fn stop(mut self) -> Result<()> {
+        self.shut_down()
+    }
+
+    fn shut_down(&mut self) -> Result<()> {
+        self.stop.store(true, Ordering::Relaxed);
+        // Dropping the consumer wakes the I/O thread if it is waiting for space in the ring
+        self.blocks = None;
+        match self.worker.take() {
+            Some(worker) => match worker.join() {
+                Ok(result) => result,
+                Err(panic) => panic::resume_unwind(panic),
+            },
+            None => Ok(()),
+        }
+    }
+}
+
+impl<I> Iterator for ContinuousReceiver<'_, I> {
+    type Item = RxBlock<I>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.blocks.as_mut()?.pop(None)
+    }
+}
+
+impl<I> Drop for ContinuousReceiver<'_, I> {
+    fn drop(&mut self) {
+        if !thread::panicking() {
+            let _ = self.shut_down();
+        }
+    }
+}
+
+impl<I> fmt::Debug for ContinuousReceiver<'_, I> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ContinuousReceiver")
+            .field("num_channels", &self.num_channels)
+            .field("finished", &self.is_finished())
+            .finish()
+    }
+}
+
+/// The receiving side of a capture, which runs on an I/O thread
+pub(crate) struct IoThread<S, I> {
+    streamer: S,
+    options: CaptureOptions,
+    num_channels: usize,
+    recycle: SyncSender<Vec<Vec<I>>>,
+    spare: Receiver<Vec<Vec<I>>>,
+    stop: Arc<AtomicBool>,
+}
+
+impl<S, I> IoThread<S, I>
+where
+    S: ReceiveStream<I>,
+    I: Sample,
+{
+    /// Starts streaming
+    ///
+    /// The I/O thread stops receiving soon after `stop` is set to true.
+    pub(crate) fn start(
+        mut streamer: S,
+        options: CaptureOptions,
+        stop: Arc<AtomicBool>,
+    ) -> Result<Self> {
+        streamer.send_command(&StreamCommand {
+            command_type: StreamCommandType::StartContinuous,
+            time: options.start.clone(),
+        })?;
+        let num_channels = streamer.num_channels();
+        // One set of buffers for each block in the ring and one for the block being received
+        let (recycle, spare) = mpsc::sync_channel(options.blocks + 1);
+        for _ in 0..=options.blocks {
+            let _ = recycle.try_send(new_buffers(num_channels, options.block_length));
+        }
+        Ok(IoThread {
+            num_channels,
+            streamer,
+            options,
+            recycle,
+            spare,
+            stop,
+        })
+    }
+
+    pub(crate) fn num_channels(&self) -> usize {
+        self.num_channels
+    }
+
+    /// Receives blocks and passes them to `deliver` until `deliver` returns false, streaming
+    /// stops, or an error occurs, and then stops streaming
+    pub(crate) fn run<D>(mut self, deliver: D) -> Result<()>
+    where
+        D: FnMut(RxBlock<I>) -> bool,
+    {
+        let result = self.receive_blocks(deliver);
+        let stop_result = self.streamer.send_command(&StreamCommand {
+            command_type: StreamCommandType::StopContinuous,
+            time: StreamTime::Now,
+        });
+        result.and(stop_result)
+    }
+
+    fn receive_blocks<D>(&mut self, mut deliver: D) -> Result<()>
+    where
+        D: FnMut(RxBlock<I>) -> bool,
+    {
+        let mut started = false;
+        while !self.stop.load(Ordering::Relaxed) {
+            let mut buffers = self
+                .spare
+                .try_recv()
+                .unwrap_or_else(|_| new_buffers(self.num_channels, self.options.block_length));
+            let mut slices: Vec<&mut [I]> =
+                buffers.iter_mut().map(|buffer| &mut buffer[..]).collect();
+            let metadata = self
+                .streamer
+                .receive(&mut slices, self.options.timeout, false)?;
+
+            let error = metadata.last_error();
+            let samples = metadata.samples();
+            started |= samples != 0;
+            let kind = error.as_ref().map(ReceiveError::kind);
+            if matches!(kind, Some(ReceiveErrorKind::Timeout)) && !started {
+                let _ = self.recycle.try_send(buffers);
+                continue;
+            }
+            let end_of_burst = metadata.end_of_burst();
+            let block = RxBlock {
+                buffers,
+                samples,
+                time_spec: metadata.time_spec(),
+                start_of_burst: metadata.start_of_burst(),
+                end_of_burst,
+                error,
+                recycle: self.recycle.clone(),
+            };
+            if !deliver(block) {
+                // The consumer has been dropped
+                break;
+            }
+            if matches!(
+                kind,
+                Some(ReceiveErrorKind::LateCommand) | Some(ReceiveErrorKind::BrokenChain)
+            ) {
+                // Streaming did not start or cannot continue
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Allocates a buffer of `length` samples for each channel
+fn new_buffers<I: Sample>(num_channels: usize, length: usize) -> Vec<Vec<I>> {
+    iter::repeat_with(|| {
+        iter::repeat_with(|| I::from_fc64(Complex64::default()))
+            .take(length)
+            .collect()
+    })
+    .take(num_channels)
+    .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use std::thread;
+
+    use num_complex::Complex32;
+
+    use super::{CaptureOptions, ContinuousReceiver};
+    use crate::sim::{Signal, SimUsrp};
+    use crate::{Device, ReceiveErrorKind, StreamArgs, StreamTime, TimeSpec, WireFormat};
+
+    #[test]
+    fn continuous_blocks() {
+        let mut usrp = SimUsrp::new();
+        usrp.set_rx_sample_rate(1e6, 0).unwrap();
+        usrp.add_rx_signal(
+            0,
+            Signal::Tone {
+                frequency: 0.0,
+                amplitude: 0.5,
+            },
+        )
+        .unwrap();
+        let control = usrp.clone();
+        let rate = usrp.get_rx_sample_rate(0).unwrap();
+        let streamer = usrp
+            .get_rx_stream(&StreamArgs::<Complex32>::new(WireFormat::Sc16))
+            .unwrap();
+        let options = CaptureOptions::new()
+            .block_length(100)
+            .blocks(4)
+            .start(StreamTime::At(TimeSpec::new(1, 0.0)));
+
+        thread::scope(|scope| {
+            let mut capture = ContinuousReceiver::spawn_scoped(scope, streamer, options).unwrap();
+            assert_eq!(capture.num_channels(), 1);
+
+            let first = capture.next().unwrap();
+            assert!(first.error().is_none());
+            assert!(first.start_of_burst());
+            assert_eq!(first.samples(), 100);
+            assert_eq!(first.time_spec(), Some(TimeSpec::new(1, 0.0)));
+            assert!((first.channel(0)[0].re - 0.5).abs() < 1e-4);
+            let second = capture.next().unwrap();
+            assert!(!second.start_of_burst());
+            assert_eq!(
+                second.time_spec(),
+                Some(TimeSpec::new(1, 0.0) + TimeSpec::from_ticks(100, rate))
+            );
+
+            control.inject_rx_error(ReceiveErrorKind::Overflow);
+            let overflow = capture
+                .find(|block| block.error().is_some())
+                .expect("No overflow");
+            assert!(matches!(
+                overflow.error().unwrap().kind(),
+                ReceiveErrorKind::Overflow
+            ));
+            assert!(overflow.is_empty());
+            capture.stop().unwrap();
+        });
+    }
+
+    #[test]
+    fn late_start_stops_capture() {
+        let mut usrp = SimUsrp::new();
+        usrp.advance_time(std::time::Duration::from_secs(2));
+        let streamer = usrp
+            .get_rx_stream(&StreamArgs::<Complex32>::new(WireFormat::Sc16))
+            .unwrap();
+        let options = CaptureOptions::new().start(StreamTime::At(TimeSpec::new(1, 0.0)));
+
+        thread::scope(|scope| {
+            let capture = ContinuousReceiver::spawn_scoped(scope, streamer, options).unwrap();
+            let blocks = capture.collect::<Vec<_>>();
+            assert_eq!(blocks.len(), 1);
+            assert!(matches!(
+                blocks[0].error().unwrap().kind(),
+                ReceiveErrorKind::LateCommand
+            ));
+        });
+    }
+}
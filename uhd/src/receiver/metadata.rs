@@ -7,31 +7,57 @@ use crate::TimeSpec;
 
 /// Data about a receive operation
 pub struct ReceiveMetadata {
-    /// Handle to C++ object
-    handle: uhd_sys::uhd_rx_metadata_handle,
+    /// Where the metadata is stored
+    inner: Inner,
     /// Number of samples received
     samples: usize,
 }
 
+enum Inner {
+    /// Handle to C++ object
+    Handle(uhd_sys::uhd_rx_metadata_handle),
+    /// Values created in Rust (for example, by a simulated device)
+    Values(ReceiveMetadataValues),
+}
+
+/// Receive metadata fields for metadata that does not come from UHD
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ReceiveMetadataValues {
+    pub time_spec: Option<TimeSpec>,
+    pub start_of_burst: bool,
+    pub end_of_burst: bool,
+    pub more_fragments: bool,
+    pub fragment_offset: usize,
+    pub error: Option<ReceiveErrorKind>,
+}
+
 impl ReceiveMetadata {
     pub fn new() -> Self {
         Default::default()
     }
 
+    /// Creates metadata from values instead of a C++ object
+    pub(crate) fn from_values(values: ReceiveMetadataValues, samples: usize) -> Self {
+        ReceiveMetadata {
+            inner: Inner::Values(values),
+            samples,
+        }
+    }
+
     /// Returns the timestamp of (the first?) of the received samples, according to the USRP's
     /// internal clock
     #[allow(clippy::useless_conversion)]
     pub fn time_spec(&self) -> Option<TimeSpec> {
-        if self.has_time_spec() {
+        let handle = match &self.inner {
+            Inner::Handle(handle) => *handle,
+            Inner::Values(values) => return values.time_spec,
+        };
+        if Self::has_time_spec(handle) {
             let mut time = TimeSpec::default();
             let mut seconds_time_t: libc::time_t = Default::default();
 
             check_status(unsafe {
-                uhd_sys::uhd_rx_metadata_time_spec(
-                    self.handle,
-                    &mut seconds_time_t,
-                    &mut time.fraction,
-                )
+                uhd_sys::uhd_rx_metadata_time_spec(handle, &mut seconds_time_t, &mut time.fraction)
             })
             .unwrap();
             // Explicitly convert seconds from time_t to i64 (some platforms `time_t` is smaller
@@ -44,26 +70,32 @@ impl ReceiveMetadata {
     }
 
     /// Returns true if this metadata object has a time
-    fn has_time_spec(&self) -> bool {
+    fn has_time_spec(handle: uhd_sys::uhd_rx_metadata_handle) -> bool {
         let mut has = false;
-        check_status(unsafe { uhd_sys::uhd_rx_metadata_has_time_spec(self.handle, &mut has) })
-            .unwrap();
+        check_status(unsafe { uhd_sys::uhd_rx_metadata_has_time_spec(handle, &mut has) }).unwrap();
         has
     }
 
     /// Returns true if the received samples are at the beginning of a burst
     pub fn start_of_burst(&self) -> bool {
+        let handle = match &self.inner {
+            Inner::Handle(handle) => *handle,
+            Inner::Values(values) => return values.start_of_burst,
+        };
         let mut value = false;
-        check_status(unsafe { uhd_sys::uhd_rx_metadata_start_of_burst(self.handle, &mut value) })
+        check_status(unsafe { uhd_sys::uhd_rx_metadata_start_of_burst(handle, &mut value) })
             .unwrap();
         value
     }
 
     /// Returns true if the received samples are at the end of a burst
     pub fn end_of_burst(&self) -> bool {
+        let handle = match &self.inner {
+            Inner::Handle(handle) => *handle,
+            Inner::Values(values) => return values.end_of_burst,
+        };
         let mut value = false;
-        check_status(unsafe { uhd_sys::uhd_rx_metadata_end_of_burst(self.handle, &mut value) })
-            .unwrap();
+        check_status(unsafe { uhd_sys::uhd_rx_metadata_end_of_burst(handle, &mut value) }).unwrap();
         value
     }
 
@@ -72,8 +104,12 @@ impl ReceiveMetadata {
     /// If this is the case, the fragment_offset() function returns the offset from the beginning
     /// of the packet to the first sample received
     pub fn more_fragments(&self) -> bool {
+        let handle = match &self.inner {
+            Inner::Handle(handle) => *handle,
+            Inner::Values(values) => return values.more_fragments,
+        };
         let mut value = false;
-        check_status(unsafe { uhd_sys::uhd_rx_metadata_more_fragments(self.handle, &mut value) })
+        check_status(unsafe { uhd_sys::uhd_rx_metadata_more_fragments(handle, &mut value) })
             .unwrap();
         value
     }
@@ -81,12 +117,13 @@ impl ReceiveMetadata {
     /// If more_fragments() returned true, this function returns the offset from the beginning
     /// of the packet to the first sample received
     pub fn fragment_offset(&self) -> usize {
+        let handle = match &self.inner {
+            Inner::Handle(handle) => *handle,
+            Inner::Values(values) => return values.fragment_offset,
+        };
         let mut value = 0usize;
         check_status(unsafe {
-            uhd_sys::uhd_rx_metadata_fragment_offset(
-                self.handle,
-                &mut value as *mut usize as *mut _,
-            )
+            uhd_sys::uhd_rx_metadata_fragment_offset(handle, &mut value as *mut usize as *mut _)
         })
         .unwrap();
         value
@@ -94,8 +131,14 @@ impl ReceiveMetadata {
 
     /// Returns true if a packet was dropped or received out of order
     pub fn out_of_sequence(&self) -> bool {
+        let handle = match &self.inner {
+            Inner::Handle(handle) => *handle,
+            Inner::Values(values) => {
+                return matches!(values.error, Some(ReceiveErrorKind::OutOfSequence))
+            }
+        };
         let mut value = false;
-        check_status(unsafe { uhd_sys::uhd_rx_metadata_out_of_sequence(self.handle, &mut value) })
+        check_status(unsafe { uhd_sys::uhd_rx_metadata_out_of_sequence(handle, &mut value) })
             .unwrap();
         value
     }
@@ -111,18 +154,28 @@ impl ReceiveMetadata {
     }
 
     /// Returns the error code associated with the receive operation
-    fn error_code(&self) -> uhd_sys::uhd_rx_metadata_error_code_t::Type {
+    fn error_code(
+        handle: uhd_sys::uhd_rx_metadata_handle,
+    ) -> uhd_sys::uhd_rx_metadata_error_code_t::Type {
         let mut code = uhd_sys::uhd_rx_metadata_error_code_t::UHD_RX_METADATA_ERROR_CODE_NONE;
-        check_status(unsafe { uhd_sys::uhd_rx_metadata_error_code(self.handle, &mut code) })
-            .unwrap();
+        check_status(unsafe { uhd_sys::uhd_rx_metadata_error_code(handle, &mut code) }).unwrap();
         code
     }
 
     /// Returns the error associated with the receive operation, if any
     pub fn last_error(&self) -> Option<ReceiveError> {
+        let handle = match &self.inner {
+            Inner::Handle(handle) => *handle,
+            Inner::Values(values) => {
+                return values.error.clone().map(|kind| ReceiveError {
+                    kind,
+                    message: None,
+                })
+            }
+        };
         let out_of_sequence = self.out_of_sequence();
         use uhd_sys::uhd_rx_metadata_error_code_t::*;
-        let kind = match self.error_code() {
+        let kind = match Self::error_code(handle) {
             UHD_RX_METADATA_ERROR_CODE_TIMEOUT => ReceiveErrorKind::Timeout,
             UHD_RX_METADATA_ERROR_CODE_LATE_COMMAND => ReceiveErrorKind::LateCommand,
             UHD_RX_METADATA_ERROR_CODE_BROKEN_CHAIN => ReceiveErrorKind::BrokenChain,
@@ -142,19 +195,28 @@ impl ReceiveMetadata {
             }
         };
         let message = copy_string(|buffer, length| unsafe {
-            uhd_sys::uhd_rx_metadata_strerror(self.handle, buffer, length as _)
+            uhd_sys::uhd_rx_metadata_strerror(handle, buffer, length as _)
         })
         .ok();
 
         Some(ReceiveError { kind, message })
     }
 
+    /// Returns the C++ object handle
+    ///
+    /// # Panics
+    ///
+    /// This function panics if this metadata was created from values.
     pub(crate) fn handle_mut(&mut self) -> &mut uhd_sys::uhd_rx_metadata_handle {
-        &mut self.handle
+        match &mut self.inner {
+            Inner::Handle(handle) => handle,
+            Inner::Values(_) => panic!("Receive metadata does not have a C++ object"),
+        }
     }
 }
 
 // Thread safety: The uhd_rx_metadata struct just stores data. All exposed functions read fields.
+// The values variant contains only owned data.
 unsafe impl Send for ReceiveMetadata {}
 unsafe impl Sync for ReceiveMetadata {}
 
@@ -162,13 +224,18 @@ impl Default for ReceiveMetadata {
     fn default() -> Self {
         let mut handle: uhd_sys::uhd_rx_metadata_handle = ptr::null_mut();
         check_status(unsafe { uhd_sys::uhd_rx_metadata_make(&mut handle) }).unwrap();
-        ReceiveMetadata { handle, samples: 0 }
+        ReceiveMetadata {
+            inner: Inner::Handle(handle),
+            samples: 0,
+        }
     }
 }
 
 impl Drop for ReceiveMetadata {
     fn drop(&mut self) {
-        let _ = unsafe { uhd_sys::uhd_rx_metadata_free(&mut self.handle) };
+        if let Inner::Handle(handle) = &mut self.inner {
+            let _ = unsafe { uhd_sys::uhd_rx_metadata_free(handle) };
+        }
     }
 }
 
@@ -228,4 +295,29 @@ mod test {
         assert_eq!(0, metadata.fragment_offset());
         assert!(metadata.last_error().is_none());
     }
+
+    #[test]
+    fn rx_metadata_from_values() {
+        use super::ReceiveMetadataValues;
+        use crate::{ReceiveErrorKind, TimeSpec};
+
+        let metadata = ReceiveMetadata::from_values(
+            ReceiveMetadataValues {
+                time_spec: Some(TimeSpec::new(2, 0.5)),
+                start_of_burst: true,
+                error: Some(ReceiveErrorKind::OutOfSequence),
+                ..ReceiveMetadataValues::default()
+            },
+            128,
+        );
+        assert_eq!(Some(TimeSpec::new(2, 0.5)), metadata.time_spec());
+        assert!(metadata.start_of_burst());
+        assert!(!metadata.end_of_burst());
+        assert!(metadata.out_of_sequence());
+        assert_eq!(128, metadata.samples());
+        assert!(matches!(
+            metadata.last_error().unwrap().kind(),
+            ReceiveErrorKind::OutOfSequence
+        ));
+    }
 }
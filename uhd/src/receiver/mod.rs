@@ -1,4 +1,6 @@
+pub mod capture;
 pub mod error;
 pub mod info;
 pub mod metadata;
+mod ring;
 pub mod streamer;
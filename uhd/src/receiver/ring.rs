@@ -0,0 +1,271 @@
+//! A lock-free ring buffer with one producer and one consumer
+//!
+//! Pushing and popping only use atomic loads and stores. A side that finds the ring full (or
+//! empty) can wait for the other side; waking a waiting thread is the only time either side
+//! takes a lock.
+
+use std::cell::UnsafeCell;
+use std::mem::MaybeUninit;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, Thread};
+use std::time::Instant;
+
+/// Creates a ring that can hold `capacity` values
+///
+/// # Panics
+///
+/// This function panics if `capacity` is zero.
+pub(crate) fn ring<T>(capacity: usize) -> (Producer<T>, Consumer<T>) {
+    assert_ne!(capacity, 0, "Ring capacity must not be zero");
+    let shared = Arc::new(Shared {
+        slots: (0..capacity)
+            .map(|_| UnsafeCell::new(MaybeUninit::uninit()))
+            .collect(),
+        head: AtomicUsize::new(0),
+        tail: AtomicUsize::new(0),
+        producer_closed: AtomicBool::new(false),
+        consumer_closed: AtomicBool::new(false),
+        producer_waiter: Waiter::new(),
+        consumer_waiter: Waiter::new(),
+    });
+    (
+        Producer {
+            shared: Arc::clone(&shared),
+        },
+        Consumer { shared },
+    )
+}
+
+struct Shared<T> {
+    slots: Box<[UnsafeCell<MaybeUninit<T>>]>,
+    /// The total number of values that have been popped
+    ///
+    /// Only the consumer changes this.
+    head: AtomicUsize,
+    /// The total number of values that have been pushed
+    ///
+    /// Only the producer changes this.
+    tail: AtomicUsize,
+    producer_closed: AtomicBool,
+    consumer_closed: AtomicBool,
+    producer_waiter: Waiter,
+    consumer_waiter: Waiter,
+}
+
+// The slots between head and tail are owned by the consumer, and the other slots are owned by
+// the producer. Storing a new head or tail passes ownership of a slot to the other side.
+unsafe impl<T: Send> Send for Shared<T> {}
+unsafe impl<T: Send> Sync for Shared<T> {}
+
+impl<T> Shared<T> {
+    fn len(&self) -> usize {
+        self.tail
+            .load(Ordering::SeqCst)
+            .wrapping_sub(self.head.load(Ordering::SeqCst))
+    }
+
+    fn slot(&self, index: usize) -> *mut MaybeUninit<T> {
+        self.slots[index % self.slots.len()].get()
+    }
+}
+
+impl<T> Drop for Shared<T> {
+    fn drop(&mut self) {
+        let head = *self.head.get_mut();
+        let tail = *self.tail.get_mut();
+        for index in head..tail {
+            // Safety: The values between head and tail have been pushed and not popped, and
+            // both sides are gone.
+            unsafe { (*self.slot(index)).assume_init_drop() };
+        }
+    }
+}
+
+/// The sending side of a ring
+pub(crate) struct Producer<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> Producer<T> {
+    /// Adds a value to the ring, waiting for space if the ring is full
+    ///
+    /// This returns the value if the consumer has been dropped.
+    pub(crate) fn push(&mut self, value: T) -> Result<(), T> {
+        let shared = &*self.shared;
+        let has_space =
+            || shared.len() < shared.slots.len() || shared.consumer_closed.load(Ordering::SeqCst);
+        shared.producer_waiter.wait(has_space, None);
+        if shared.consumer_closed.load(Ordering::SeqCst) {
+            return Err(value);
+        }
+        let tail = shared.tail.load(Ordering::Relaxed);
+        // Safety: The ring is not full, so the slot at tail belongs to the producer.
+        unsafe { (*shared.slot(tail)).write(value) };
+        shared.tail.store(tail.wrapping_add(1), Ordering::SeqCst);
+        shared.consumer_waiter.wake();
+        Ok(())
+    }
+}
+
+impl<T> Drop for Producer<T> {
+    fn drop(&mut self) {
+        self.shared.producer_closed.store(true, Ordering::SeqCst);
+        self.shared.consumer_waiter.wake();
+    }
+}
+
+/// The receiving side of a ring
+pub(crate) struct Consumer<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> Consumer<T> {
+    /// Removes the oldest value from the ring without waiting
+    pub(crate) fn try_pop(&mut self) -> Option<T> {
+        let shared = &*self.shared;
+        let head = shared.head.load(Ordering::Relaxed);
+        if shared.tail.load(Ordering::SeqCst) == head {
+            return None;
+        }
+        // Safety: The ring is not empty, so the slot at head contains a value that belongs to
+        // the consumer.
+        let value = unsafe { (*shared.slot(head)).assume_init_read() };
+        shared.head.store(head.wrapping_add(1), Ordering::SeqCst);
+        shared.producer_waiter.wake();
+        Some(value)
+    }
+
+    /// Removes the oldest value from the ring, waiting for one until `deadline` (or forever)
+    ///
+    /// This returns None if the deadline passes, or if the ring is empty and the producer has
+    /// been dropped.
+    pub(crate) fn pop(&mut self, deadline: Option<Instant>) -> Option<T> {
+        let shared = &*self.shared;
+        let ready = || shared.len() != 0 || shared.producer_closed.load(Ordering::SeqCst);
+        shared.consumer_waiter.wait(ready, deadline);
+        self.try_pop()
+    }
+}
+
+impl<T> Drop for Consumer<T> {
+    fn drop(&mut self) {
+        self.shared.consumer_closed.store(true, Ordering::SeqCst);
+        self.shared.producer_waiter.wake();
+    }
+}
+
+/// A thread that may be parked until the other side of the ring changes something
+struct Waiter {
+    waiting: AtomicBool,
+    thread: Mutex<Option<Thread>>,
+}
+
+impl Waiter {
+    fn new() -> Self {
+        Waiter {
+            waiting: AtomicBool::new(false),
+            thread: Mutex::new(None),
+        }
+    }
+
+    /// Parks the current thread until `ready` returns true or the deadline passes
+    fn wait<F>(&self, ready: F, deadline: Option<Instant>)
+    where
+        F: Fn() -> bool,
+    {
+        if ready() {
+            return;
+        }
+        *self.thread.lock().unwrap() = Some(thread::current());
+        loop {
+            // The other side changes the ring before checking this flag, and this side sets the
+            // flag before checking the ring, so at least one of them sees the other's change.
+            self.waiting.store(true, Ordering::SeqCst);
+            if ready() {
+                break;
+            }
+            match deadline {
+                None => thread::park(),
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        break;
+                    }
+                    thread::park_timeout(deadline - now);
+                }
+            }
+        }
+        self.waiting.store(false, Ordering::SeqCst);
+    }
+
+    /// Unparks the waiting thread, if any
+    fn wake(&self) {
+        if self.waiting.load(Ordering::SeqCst) {
+            if let Some(thread) = &*self.thread.lock().unwrap() {
+                thread.unpark();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    use super::ring;
+
+    #[test]
+    fn order_and_capacity() {
+        let (mut producer, mut consumer) = ring(2);
+        producer.push(1).unwrap();
+        producer.push(2).unwrap();
+        assert_eq!(consumer.try_pop(), Some(1));
+        producer.push(3).unwrap();
+        assert_eq!(consumer.try_pop(), Some(2));
+        assert_eq!(consumer.try_pop(), Some(3));
+        assert_eq!(consumer.try_pop(), None);
+        let deadline = Instant::now() + Duration::from_millis(10);
+        assert_eq!(consumer.pop(Some(deadline)), None);
+    }
+
+    #[test]
+    fn closed_sides() {
+        let (mut producer, consumer) = ring(1);
+        drop(consumer);
+        assert_eq!(producer.push(1), Err(1));
+
+        let (mut producer, mut consumer) = ring(2);
+        producer.push(1).unwrap();
+        drop(producer);
+        assert_eq!(consumer.pop(None), Some(1));
+        assert_eq!(consumer.pop(None), None);
+    }
+
+    #[test]
+    fn unpopped_values_are_dropped() {
+        let value = Arc::new(());
+        let (mut producer, consumer) = ring(2);
+        producer.push(Arc::clone(&value)).unwrap();
+        drop(producer);
+        drop(consumer);
+        assert_eq!(Arc::strong_count(&value), 1);
+    }
+
+    #[test]
+    fn between_threads() {
+        let (mut producer, mut consumer) = ring(4);
+        let sender = thread::spawn(move || {
+            for i in 0..10_000 {
+                producer.push(i).unwrap();
+            }
+        });
+        for i in 0..10_000 {
+            assert_eq!(consumer.pop(None), Some(i));
+        }
+        assert_eq!(consumer.pop(None), None);
+        sender.join().unwrap();
+    }
+}
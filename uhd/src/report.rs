@@ -0,0 +1,483 @@
+//! Descriptions of the hardware and capabilities of a device
+//!
+//! [`DeviceReport::from_usrp`] collects the same information as `uhd_usrp_probe` without changing
+//! any settings. With the `serde` feature, reports can be serialized (for example, to JSON) and
+//! compared between devices.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::error::Result;
+use crate::master_clock_rate::ChannelDirection;
+use crate::range::{MetaRange, Range};
+use crate::usrp::{SensorValue, Usrp};
+
+/// Motherboard EEPROM keys that are included in reports
+///
+/// The EEPROM does not list its keys, so these are the keys that UHD devices commonly use.
+const MOTHERBOARD_EEPROM_KEYS: &[&str] = &[
+    "hardware",
+    "revision",
+    "revision_compat",
+    "product",
+    "serial",
+    "name",
+    "mac-addr",
+    "mac-addr0",
+    "mac-addr1",
+    "ip-addr",
+    "ip-addr0",
+    "ip-addr1",
+    "ip-addr2",
+    "ip-addr3",
+    "subnet",
+    "subnet0",
+    "subnet1",
+    "subnet2",
+    "subnet3",
+    "gateway",
+];
+
+/// Daughter board EEPROM units and slots that are included in reports
+const DAUGHTER_BOARD_UNITS: &[&str] = &["rx", "tx", "gdb"];
+const DAUGHTER_BOARD_SLOTS: &[&str] = &["A", "B"];
+
+/// A description of a device, its motherboards, and its channels
+///
+/// Devices do not support every query. Information that can't be read is `None`, so a failed read
+/// can be told apart from an empty list (for example, a device with no sensors).
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DeviceReport {
+    pub motherboards: Vec<MotherboardReport>,
+    pub rx_channels: Vec<ChannelReport>,
+    pub tx_channels: Vec<ChannelReport>,
+}
+
+/// A description of a motherboard
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MotherboardReport {
+    pub name: Option<String>,
+    pub master_clock_rate: Option<f64>,
+    /// Motherboard EEPROM values that are set
+    pub eeprom: Option<BTreeMap<String, String>>,
+    /// Daughter boards with EEPROMs that could be read and are not empty
+    pub daughter_boards: Vec<DaughterBoardReport>,
+    /// Sensors that could be read
+    pub sensors: Option<BTreeMap<String, SensorValue>>,
+    pub clock_source: Option<String>,
+    pub clock_sources: Option<Vec<String>>,
+    pub time_source: Option<String>,
+    pub time_sources: Option<Vec<String>>,
+    pub gpio_banks: Option<Vec<String>>,
+}
+
+/// The EEPROM contents of a daughter board
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DaughterBoardReport {
+    /// The EEPROM unit (`rx`, `tx`, or `gdb`)
+    pub unit: String,
+    /// The slot (`A` or `B`)
+    pub slot: String,
+    pub id: String,
+    pub serial: String,
+    /// The revision, or None if it is not set
+    pub revision: Option<i32>,
+}
+
+/// A description of a receive or transmit channel
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ChannelReport {
+    pub subdev_name: Option<String>,
+    pub antennas: Option<Vec<String>>,
+    pub antenna: Option<String>,
+    pub gains: Option<Vec<GainReport>>,
+    pub frequency_range: Option<Vec<Range>>,
+    /// The frequency range of the front end, which can be larger than the range that can be
+    /// tuned to
+    pub fe_frequency_range: Option<Vec<Range>>,
+    pub bandwidth_range: Option<Vec<Range>>,
+    pub sample_rates: Option<Vec<Range>>,
+    pub lo_names: Option<Vec<String>>,
+    /// Sensors that could be read
+    pub sensors: Option<BTreeMap<String, SensorValue>>,
+}
+
+/// A gain element of a channel
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GainReport {
+    pub name: String,
+    pub range: Option<Vec<Range>>,
+    pub value: Option<f64>,
+}
+
+impl DeviceReport {
+    /// Collects information about a device
+    ///
+    /// This only reads from the device. An error is returned only if the numbers of motherboards
+    /// and channels can't be read.
+    pub fn from_usrp(usrp: &Usrp) -> Result<Self> {
+        let motherboards = (0..usrp.get_num_motherboards()?)
+            .map(|mboard| MotherboardReport::from_usrp(usrp, mboard))
+            .collect();
+        let rx_channels = (0..usrp.get_num_rx_channels()?)
+            .map(|channel| ChannelReport::from_usrp(usrp, ChannelDirection::Receive, channel))
+            .collect();
+        let tx_channels = (0..usrp.get_num_tx_channels()?)
+            .map(|channel| ChannelReport::from_usrp(usrp, ChannelDirection::Transmit, channel))
+            .collect();
+        Ok(DeviceReport {
+            motherboards,
+            rx_channels,
+            tx_channels,
+        })
+    }
+}
+
+impl MotherboardReport {
+    fn from_usrp(usrp: &Usrp, mboard: usize) -> Self {
+        let eeprom = usrp.get_motherboard_eeprom(mboard).ok().map(|eeprom| {
+            MOTHERBOARD_EEPROM_KEYS
+                .iter()
+                .filter_map(|&key| match eeprom.get(key) {
+                    Ok(Some(value)) => Some((key.to_owned(), value)),
+                    _ => None,
+                })
+                .collect()
+        });
+        let mut daughter_boards = Vec::new();
+        for &slot in DAUGHTER_BOARD_SLOTS {
+            for &unit in DAUGHTER_BOARD_UNITS {
+                let eeprom = match usrp.get_daughter_board_eeprom(unit, slot, mboard) {
+                    Ok(eeprom) => eeprom,
+                    Err(_) => continue,
+                };
+                let id = eeprom.id().unwrap_or_default();
+                let serial = eeprom.serial().unwrap_or_default();
+                // Slots without a daughter board have an empty EEPROM
+                if id.is_empty() && serial.is_empty() {
+                    continue;
+                }
+                daughter_boards.push(DaughterBoardReport {
+                    unit: unit.to_owned(),
+                    slot: slot.to_owned(),
+                    id,
+                    serial,
+                    // UHD parses the revision as an integer, which fails if it is not set
+                    revision: eeprom.revision().ok(),
+                });
+            }
+        }
+        let sensors = read_sensors(usrp.get_mboard_sensor_names(mboard), |name| {
+            usrp.get_mboard_sensor(name, mboard)
+        });
+
+        MotherboardReport {
+            name: usrp.get_motherboard_name(mboard).ok(),
+            master_clock_rate: usrp.get_master_clock_rate(mboard).ok(),
+            eeprom,
+            daughter_boards,
+            sensors,
+            clock_source: usrp.get_clock_source(mboard).ok(),
+            clock_sources: usrp.get_clock_sources(mboard).ok(),
+            time_source: usrp.get_time_source(mboard).ok(),
+            time_sources: usrp.get_time_sources(mboard).ok(),
+            gpio_banks: usrp.get_gpio_banks(mboard).ok(),
+        }
+    }
+}
+
+impl ChannelReport {
+    fn from_usrp(usrp: &Usrp, direction: ChannelDirection, channel: usize) -> Self {
+        let ranges = |range: Result<MetaRange>| -> Option<Vec<Range>> {
+            range.ok().map(|range| range.iter().collect())
+        };
+        match direction {
+            ChannelDirection::Receive => ChannelReport {
+                subdev_name: usrp.get_rx_subdev_name(channel).ok(),
+                antennas: usrp.get_rx_antennas(channel).ok(),
+                antenna: usrp.get_rx_antenna(channel).ok(),
+                gains: usrp.get_rx_gain_names(channel).ok().map(|names| {
+                    names
+                        .into_iter()
+                        .map(|name| GainReport {
+                            range: ranges(usrp.get_rx_gain_range(channel, &name)),
+                            value: usrp.get_rx_gain(channel, &name).ok(),
+                            name,
+                        })
+                        .collect()
+                }),
+                frequency_range: ranges(usrp.get_rx_frequency_range(channel)),
+                fe_frequency_range: ranges(usrp.get_fe_rx_freq_range(channel)),
+                bandwidth_range: ranges(usrp.get_rx_bandwidth_range(channel)),
+                sample_rates: ranges(usrp.get_rx_sample_rates(channel)),
+                lo_names: usrp.get_rx_lo_names(channel).ok(),
+                sensors: read_sensors(usrp.get_rx_sensor_names(channel), |name| {
+                    usrp.get_rx_sensor(name, channel)
+                }),
+            },
+            ChannelDirection::Transmit => ChannelReport {
+                subdev_name: usrp.get_tx_subdev_name(channel).ok(),
+                antennas: usrp.get_tx_antennas(channel).ok(),
+                antenna: usrp.get_tx_antenna(channel).ok(),
+                gains: usrp.get_tx_gain_names(channel).ok().map(|names| {
+                    names
+                        .into_iter()
+                        .map(|name| GainReport {
+                            range: ranges(usrp.get_tx_gain_range(channel, &name)),
+                            value: usrp.get_tx_gain(channel, &name).ok(),
+                            name,
+                        })
+                        .collect()
+                }),
+                frequency_range: ranges(usrp.get_tx_frequency_range(channel)),
+                fe_frequency_range: ranges(usrp.get_fe_tx_freq_range(channel)),
+                bandwidth_range: ranges(usrp.get_tx_bandwidth_range(channel)),
+                sample_rates: ranges(usrp.get_tx_sample_rates(channel)),
+                lo_names: usrp.get_tx_lo_names(channel).ok(),
+                sensors: read_sensors(usrp.get_tx_sensor_names(channel), |name| {
+                    usrp.get_tx_sensor(name, channel)
+                }),
+            },
+        }
+    }
+}
+
+/// Reads the sensors with the provided names, skipping sensors that can't be read
+///
+/// This returns None if the names can't be read.
+fn read_sensors<F>(names: Result<Vec<String>>, mut read: F) -> Option<BTreeMap<String, SensorValue>>
+where
+    F: FnMut(&str) -> Result<SensorValue>,
+{
+    names.ok().map(|names| {
+        names
+            .into_iter()
+            .filter_map(|name| read(&name).ok().map(|value| (name, value)))
+            .collect()
+    })
+}
+
+/// Formats a list of ranges like `[1e6, 6e9] step 1` or `[0, 10], [20, 30]`, `none`, or `unknown`
+struct Ranges<'r>(&'r Option<Vec<Range>>);
+
+impl fmt::Display for Ranges<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ranges = match self.0 {
+            Some(ranges) if ranges.is_empty() => return write!(f, "none"),
+            Some(ranges) => ranges,
+            None => return write!(f, "unknown"),
+        };
+        for (i, range) in ranges.iter().enumerate() {
+            if i != 0 {
+                write!(f, ", ")?;
+            }
+            if range.start() == range.stop() {
+                write!(f, "{}", range.start())?;
+            } else {
+                write!(f, "[{}, {}]", range.start(), range.stop())?;
+                if range.step() != 0.0 {
+                    write!(f, " step {}", range.step())?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Formats an optional value, or `unknown`
+struct Known<'v, T>(&'v Option<T>);
+
+impl<T: fmt::Display> fmt::Display for Known<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Some(value) => value.fmt(f),
+            None => write!(f, "unknown"),
+        }
+    }
+}
+
+/// Formats an optional list of names separated by commas, `none`, or `unknown`
+struct Names<'n>(&'n Option<Vec<String>>);
+
+impl fmt::Display for Names<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Some(names) if names.is_empty() => write!(f, "none"),
+            Some(names) => write!(f, "{}", names.join(", ")),
+            None => write!(f, "unknown"),
+        }
+    }
+}
+
+/// Writes one line for each sensor, or a line that says that the sensors are unknown
+fn write_sensors(
+    f: &mut fmt::Formatter<'_>,
+    sensors: &Option<BTreeMap<String, SensorValue>>,
+) -> fmt::Result {
+    match sensors {
+        Some(sensors) => {
+            for (name, value) in sensors {
+                writeln!(f, "  Sensor {}: {}", name, value)?;
+            }
+            Ok(())
+        }
+        None => writeln!(f, "  Sensors: unknown"),
+    }
+}
+
+/// Formats the report as an indented table
+impl fmt::Display for DeviceReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, mboard) in self.motherboards.iter().enumerate() {
+            writeln!(f, "Motherboard {}: {}", index, Known(&mboard.name))?;
+            writeln!(
+                f,
+                "  Master clock rate: {}",
+                Known(&mboard.master_clock_rate)
+            )?;
+            writeln!(
+                f,
+                "  Clock source: {} (available: {})",
+                Known(&mboard.clock_source),
+                Names(&mboard.clock_sources)
+            )?;
+            writeln!(
+                f,
+                "  Time source: {} (available: {})",
+                Known(&mboard.time_source),
+                Names(&mboard.time_sources)
+            )?;
+            writeln!(f, "  GPIO banks: {}", Names(&mboard.gpio_banks))?;
+            match &mboard.eeprom {
+                Some(eeprom) => {
+                    for (key, value) in eeprom {
+                        writeln!(f, "  EEPROM {}: {}", key, value)?;
+                    }
+                }
+                None => writeln!(f, "  EEPROM: unknown")?,
+            }
+            for board in &mboard.daughter_boards {
+                writeln!(
+                    f,
+                    "  Daughter board {} {}: ID {}, serial {}, revision {}",
+                    board.slot,
+                    board.unit,
+                    board.id,
+                    board.serial,
+                    Known(&board.revision)
+                )?;
+            }
+            write_sensors(f, &mboard.sensors)?;
+        }
+        let directions = [("RX", &self.rx_channels), ("TX", &self.tx_channels)];
+        for (direction, channels) in directions.iter() {
+            for (index, channel) in channels.iter().enumerate() {
+                writeln!(
+                    f,
+                    "{} channel {}: {}",
+                    direction,
+                    index,
+                    Known(&channel.subdev_name)
+                )?;
+                writeln!(
+                    f,
+                    "  Antennas: {} (selected: {})",
+                    Names(&channel.antennas),
+                    Known(&channel.antenna)
+                )?;
+                writeln!(f, "  Frequency: {}", Ranges(&channel.frequency_range))?;
+                writeln!(
+                    f,
+                    "  Front-end frequency: {}",
+                    Ranges(&channel.fe_frequency_range)
+                )?;
+                writeln!(f, "  Bandwidth: {}", Ranges(&channel.bandwidth_range))?;
+                writeln!(f, "  Sample rate: {}", Ranges(&channel.sample_rates))?;
+                match &channel.gains {
+                    Some(gains) => {
+                        for gain in gains {
+                            writeln!(
+                                f,
+                                "  Gain {}: {} (current: {})",
+                                gain.name,
+                                Ranges(&gain.range),
+                                Known(&gain.value)
+                            )?;
+                        }
+                    }
+                    None => writeln!(f, "  Gains: unknown")?,
+                }
+                writeln!(f, "  LOs: {}", Names(&channel.lo_names))?;
+                write_sensors(f, &channel.sensors)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+
+    use super::{ChannelReport, DeviceReport, GainReport};
+    use crate::range::Range;
+    use crate::SensorValue;
+
+    fn report() -> DeviceReport {
+        let mut sensors = BTreeMap::new();
+        sensors.insert("lo_locked".to_owned(), SensorValue::Boolean(true));
+        DeviceReport {
+            motherboards: vec![],
+            rx_channels: vec![ChannelReport {
+                subdev_name: Some("FE-RX1".to_owned()),
+                antennas: Some(vec!["TX/RX".to_owned(), "RX2".to_owned()]),
+                antenna: Some("RX2".to_owned()),
+                gains: Some(vec![GainReport {
+                    name: "PGA".to_owned(),
+                    range: Some(vec![Range::new(0.0, 76.0, 1.0)]),
+                    value: Some(20.0),
+                }]),
+                frequency_range: Some(vec![Range::new(70e6, 6e9, 0.0)]),
+                fe_frequency_range: Some(vec![]),
+                bandwidth_range: Some(vec![Range::new(200e3, 56e6, 0.0)]),
+                sample_rates: None,
+                lo_names: Some(vec![]),
+                sensors: Some(sensors),
+            }],
+            tx_channels: vec![],
+        }
+    }
+
+    #[test]
+    fn table() {
+        let table = report().to_string();
+        assert!(table.contains("RX channel 0: FE-RX1\n"));
+        assert!(table.contains("  Antennas: TX/RX, RX2 (selected: RX2)\n"));
+        assert!(table.contains("  Frequency: [70000000, 6000000000]\n"));
+        assert!(table.contains("  Front-end frequency: none\n"));
+        assert!(table.contains("  Sample rate: unknown\n"));
+        assert!(table.contains("  Gain PGA: [0, 76] step 1 (current: 20)\n"));
+        assert!(table.contains("  LOs: none\n"));
+        assert!(table.contains("  Sensor lo_locked: true\n"));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn json_round_trip() {
+        let json = serde_json::to_value(report()).unwrap();
+        let channel = &json["rx_channels"][0];
+        assert_eq!(channel["gains"][0]["range"][0]["stop"], 76.0);
+        assert_eq!(channel["sensors"]["lo_locked"]["boolean"], true);
+        // A failed read is distinct from an empty list
+        assert_eq!(channel["sample_rates"], serde_json::Value::Null);
+        assert_eq!(channel["lo_names"], serde_json::json!([]));
+
+        let parsed: DeviceReport = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(serde_json::to_value(parsed).unwrap(), json);
+    }
+}
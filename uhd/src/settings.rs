@@ -0,0 +1,218 @@
+//! Reports of settings applied to a device
+//!
+//! A [`ConfigReport`] compares the values that were requested, from a radio configuration or a
+//! [`DeviceSnapshot`](crate::DeviceSnapshot), with the values that the device actually uses.
+
+use std::fmt;
+
+use crate::error::Result;
+
+/// A value of a setting in a [`ConfigReport`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigValue {
+    Number(f64),
+    Text(String),
+    Bool(bool),
+}
+
+impl ConfigValue {
+    /// Returns true if the values are equal, allowing a relative difference of 10^-9 between
+    /// numbers
+    fn approx_eq(&self, other: &ConfigValue) -> bool {
+        match (self, other) {
+            (ConfigValue::Number(a), ConfigValue::Number(b)) => {
+                (a - b).abs() <= 1e-9 * a.abs().max(b.abs())
+            }
+            _ => self == other,
+        }
+    }
+}
+
+impl fmt::Display for ConfigValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigValue::Number(value) => write!(f, "{}", value),
+            ConfigValue::Text(value) => write!(f, "\"{}\"", value),
+            ConfigValue::Bool(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+/// One setting that was applied to a device
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigSetting {
+    name: String,
+    requested: ConfigValue,
+    actual: Option<ConfigValue>,
+    error: Option<String>,
+}
+
+impl ConfigSetting {
+    /// Returns a description of the setting, like `RX 0 frequency`
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the value that was requested
+    pub fn requested(&self) -> &ConfigValue {
+        &self.requested
+    }
+
+    /// Returns the value that the device uses, or None if the setting can't be read back
+    pub fn actual(&self) -> Option<&ConfigValue> {
+        self.actual.as_ref()
+    }
+
+    /// Returns the error message if the device rejected the setting
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    /// Returns true if the device uses a different value from the requested value
+    pub fn is_coerced(&self) -> bool {
+        self.actual
+            .as_ref()
+            .is_some_and(|actual| !actual.approx_eq(&self.requested))
+    }
+}
+
+impl fmt::Display for ConfigSetting {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: requested {}, ", self.name, self.requested)?;
+        match &self.actual {
+            Some(actual) if self.is_coerced() => write!(f, "actual {} (coerced)", actual)?,
+            Some(actual) => write!(f, "actual {}", actual)?,
+            None => write!(f, "actual unknown")?,
+        }
+        match &self.error {
+            Some(error) => write!(f, " (failed: {})", error),
+            None => Ok(()),
+        }
+    }
+}
+
+/// The requested and actual values of settings applied to a device
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ConfigReport {
+    settings: Vec<ConfigSetting>,
+}
+
+impl ConfigReport {
+    /// Returns all applied settings, in the order they were applied
+    pub fn settings(&self) -> &[ConfigSetting] {
+        &self.settings
+    }
+
+    /// Returns the settings that the device coerced to different values
+    pub fn coerced(&self) -> impl Iterator<Item = &ConfigSetting> {
+        self.settings.iter().filter(|setting| setting.is_coerced())
+    }
+
+    /// Returns the settings that the device rejected
+    pub fn failed(&self) -> impl Iterator<Item = &ConfigSetting> {
+        self.settings
+            .iter()
+            .filter(|setting| setting.error.is_some())
+    }
+
+    /// Returns true if no setting failed and the device uses all requested values that could
+    /// be read back
+    pub fn is_exact(&self) -> bool {
+        self.coerced().next().is_none() && self.failed().next().is_none()
+    }
+
+    /// Adds a setting that may have failed, with the value read back from the device afterwards
+    pub(crate) fn push_result(
+        &mut self,
+        name: String,
+        requested: ConfigValue,
+        result: Result<()>,
+        actual: Option<ConfigValue>,
+    ) {
+        self.settings.push(ConfigSetting {
+            name,
+            requested,
+            actual,
+            error: result.err().map(|e| e.to_string()),
+        });
+    }
+
+    /// Adds a numeric setting of a channel or motherboard, named like `RX 0 gain`
+    pub(crate) fn push_number(
+        &mut self,
+        prefix: &str,
+        setting: &str,
+        requested: f64,
+        result: Result<()>,
+        actual: Result<f64>,
+    ) {
+        self.push_result(
+            format!("{} {}", prefix, setting),
+            ConfigValue::Number(requested),
+            result,
+            actual.ok().map(ConfigValue::Number),
+        );
+    }
+
+    /// Adds a text setting of a channel or motherboard, named like `TX 1 antenna`
+    pub(crate) fn push_text(
+        &mut self,
+        prefix: &str,
+        setting: &str,
+        requested: &str,
+        result: Result<()>,
+        actual: Result<String>,
+    ) {
+        self.push_result(
+            format!("{} {}", prefix, setting),
+            ConfigValue::Text(requested.to_string()),
+            result,
+            actual.ok().map(ConfigValue::Text),
+        );
+    }
+
+    /// Adds a setting that can't be read back from the device
+    #[cfg(feature = "config")]
+    pub(crate) fn push_unread(
+        &mut self,
+        prefix: &str,
+        setting: &str,
+        requested: ConfigValue,
+        result: Result<()>,
+    ) {
+        self.push_result(format!("{} {}", prefix, setting), requested, result, None);
+    }
+}
+
+impl fmt::Display for ConfigReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for setting in &self.settings {
+            writeln!(f, "{}", setting)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ConfigReport, ConfigValue};
+    use crate::Error;
+
+    #[test]
+    fn failed_setting() {
+        let mut report = ConfigReport::default();
+        report.push_result(
+            "TX 1 antenna".to_string(),
+            ConfigValue::Text("RX2".to_string()),
+            Err(Error::Value),
+            Some(ConfigValue::Text("TX/RX".to_string())),
+        );
+        assert!(!report.is_exact());
+        assert_eq!(report.failed().count(), 1);
+        assert_eq!(
+            report.to_string(),
+            "TX 1 antenna: requested \"RX2\", actual \"TX/RX\" (coerced) \
+             (failed: uhd::value_error - Invalid value)\n"
+        );
+    }
+}
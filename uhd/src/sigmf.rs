@@ -0,0 +1,541 @@
+//! Recording received samples in the [SigMF](https://sigmf.org) format
+//!
+//! This module is available with the `sigmf` feature.
+
+use std::convert::TryFrom;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+
+use num_complex::Complex;
+use serde_json::{json, Map, Value};
+
+use crate::device::{Device, ReceiveStream};
+use crate::error::{Error, Result};
+use crate::stream::{Sample, StreamCommand, StreamCommandType, StreamTime};
+use crate::{ReceiveErrorKind, ReceiveInfo, ReceiveMetadata, TimeSpec, Usrp};
+
+/// The SigMF specification version that recordings use
+const SIGMF_VERSION: &str = "1.0.0";
+/// The version of the `uhd` extension namespace, which holds the device settings, hardware
+/// information, and lost sample counts
+const UHD_EXTENSION_VERSION: &str = "0.1.0";
+/// The number of samples to receive at a time in [`SigmfRecorder::record`]
+const RECORD_BLOCK_LENGTH: usize = 4096;
+/// The timeout, in seconds, for each receive call in [`SigmfRecorder::record`]
+const RECORD_TIMEOUT: f64 = 1.0;
+
+/// A sample type that can be written to a SigMF dataset
+///
+/// This is implemented for all complex sample types that UHD supports.
+pub trait SigmfItem: Sample {
+    /// The SigMF `core:datatype` of samples of this type
+    const DATATYPE: &'static str;
+
+    /// Appends samples to a buffer as little-endian bytes
+    fn write_le(samples: &[Self], bytes: &mut Vec<u8>)
+    where
+        Self: Sized;
+}
+
+macro_rules! sigmf_item {
+    ($component:ty, $datatype:expr) => {
+        impl SigmfItem for Complex<$component> {
+            const DATATYPE: &'static str = $datatype;
+
+            fn write_le(samples: &[Self], bytes: &mut Vec<u8>) {
+                for sample in samples {
+                    bytes.extend_from_slice(&sample.re.to_le_bytes());
+                    bytes.extend_from_slice(&sample.im.to_le_bytes());
+                }
+            }
+        }
+    };
+}
+
+sigmf_item!(f64, "cf64_le");
+sigmf_item!(f32, "cf32_le");
+sigmf_item!(i16, "ci16_le");
+sigmf_item!(i8, "ci8");
+
+/// Information about the receive channel that a recording comes from
+#[derive(Debug, Clone, Default)]
+pub struct RecordingInfo {
+    /// Sample rate, samples/second
+    pub sample_rate: f64,
+    /// Center frequency, hertz
+    pub frequency: f64,
+    /// Overall gain, decibels
+    pub gain: f64,
+    /// Antenna name
+    pub antenna: String,
+    /// Information about the hardware, if available
+    pub hardware: Option<ReceiveInfo>,
+    /// A description of the recording
+    pub description: Option<String>,
+}
+
+impl RecordingInfo {
+    /// Reads the settings of a receive channel, including hardware information
+    pub fn from_usrp(usrp: &Usrp, channel: usize) -> Result<Self> {
+        Ok(RecordingInfo {
+            hardware: Some(usrp.get_rx_info(channel)?),
+            ..RecordingInfo::from_device(usrp, channel)?
+        })
+    }
+
+    /// Reads the settings of a receive channel of any device
+    ///
+    /// The returned information has no hardware information.
+    pub fn from_device<D>(device: &D, channel: usize) -> Result<Self>
+    where
+        D: Device,
+    {
+        Ok(RecordingInfo {
+            sample_rate: device.get_rx_sample_rate(channel)?,
+            frequency: device.get_rx_frequency(channel)?,
+            gain: device.get_rx_gain(channel, "")?,
+            antenna: device.get_rx_antenna(channel)?,
+            hardware: None,
+            description: None,
+        })
+    }
+}
+
+/// Writes received samples to a SigMF recording
+///
+/// A recording consists of a `.sigmf-data` file with the samples, written as they are received,
+/// and a `.sigmf-meta` file that is written by [`finish`](Self::finish) (or when the recorder is
+/// dropped, ignoring errors).
+///
+/// Each capture segment has a `core:datetime` with the time of its first sample. The device time
+/// is treated as the number of seconds since the Unix epoch, which is correct if the time was
+/// set from GPS or from the host clock.
+///
+/// The first capture segment starts with the first sample. The recorder starts a new capture
+/// segment whenever the time of a block does not follow the end of the previous block, for
+/// example when streaming restarts. After an overflow or out-of-sequence error, it also adds an
+/// annotation that describes the gap.
+///
+/// ```no_run
+/// use num_complex::Complex32;
+/// use uhd::{RecordingInfo, SigmfRecorder, StreamArgs, Usrp, WireFormat};
+///
+/// let mut usrp = Usrp::open("")?;
+/// let info = RecordingInfo::from_usrp(&usrp, 0)?;
+/// let mut recorder = SigmfRecorder::create("capture", info)?;
+/// let mut streamer = usrp.get_rx_stream(&StreamArgs::<Complex32>::new(WireFormat::Sc16))?;
+/// recorder.record(&mut streamer, 1_000_000)?;
+/// recorder.finish()?;
+/// # Ok::<(), uhd::Error>(())
+/// ```
+#[derive(Debug)]
+pub struct SigmfRecorder<I> {
+    data: BufWriter<File>,
+    meta_path: PathBuf,
+    info: RecordingInfo,
+    /// The SigMF datatype of the samples
+    datatype: &'static str,
+    captures: Vec<Value>,
+    annotations: Vec<Value>,
+    /// The number of samples written
+    samples: u64,
+    /// The time of the next sample, if it is known
+    next_time: Option<TimeSpec>,
+    /// The error that caused the current gap, if samples have been lost since the last block
+    gap: Option<ReceiveErrorKind>,
+    /// Converted samples, reused between blocks
+    bytes: Vec<u8>,
+    finished: bool,
+    item_phantom: PhantomData<I>,
+}
+
+impl<I> SigmfRecorder<I>
+where
+    I: SigmfItem,
+{
+    /// Creates `<base>.sigmf-data` and starts a recording that will write `<base>.sigmf-meta`
+    ///
+    /// The extensions are appended to `base`, so any dots in the file name are kept.
+    pub fn create<P>(base: P, info: RecordingInfo) -> Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let base = base.as_ref();
+        let data = BufWriter::new(File::create(data_path(base))?);
+        Ok(SigmfRecorder {
+            data,
+            meta_path: meta_path(base),
+            info,
+            datatype: I::DATATYPE,
+            captures: Vec::new(),
+            annotations: Vec::new(),
+            samples: 0,
+            next_time: None,
+            gap: None,
+            bytes: Vec::new(),
+            finished: false,
+            item_phantom: PhantomData,
+        })
+    }
+
+    /// Returns the number of samples that have been written
+    pub fn samples(&self) -> u64 {
+        self.samples
+    }
+
+    /// Streams continuously from a single-channel streamer until `samples` samples have been
+    /// written, and then stops streaming
+    ///
+    /// This can be called more than once to record several parts of a stream. Overflows and
+    /// out-of-sequence errors start new capture segments. Other receive errors stop the recording
+    /// and are returned.
+    pub fn record<S>(&mut self, streamer: &mut S, samples: u64) -> Result<()>
+    where
+        S: ReceiveStream<I>,
+    {
+        if streamer.num_channels() != 1 {
+            return Err(Error::Unique(format!(
+                "SigMF recordings need a stream with 1 channel, not {}",
+                streamer.num_channels()
+            )));
+        }
+        let end = self.samples.checked_add(samples).ok_or_else(|| {
+            Error::Unique(format!(
+                "Can't record {} more samples after {} samples",
+                samples, self.samples
+            ))
+        })?;
+        streamer.send_command(&StreamCommand {
+            command_type: StreamCommandType::StartContinuous,
+            time: StreamTime::Now,
+        })?;
+        let result = self.receive_samples(streamer, end);
+        let stop_result = streamer.send_command(&StreamCommand {
+            command_type: StreamCommandType::StopContinuous,
+            time: StreamTime::Now,
+        });
+        result.and(stop_result)
+    }
+
+    /// Receives and writes samples until the recording has `end` samples
+    fn receive_samples<S>(&mut self, streamer: &mut S, end: u64) -> Result<()>
+    where
+        S: ReceiveStream<I>,
+    {
+        let mut buffer: Vec<I> = std::iter::repeat_with(|| I::from_fc64(Default::default()))
+            .take(RECORD_BLOCK_LENGTH)
+            .collect();
+        while self.samples < end {
+            let length = usize::try_from(end - self.samples)
+                .map_or(buffer.len(), |remaining| remaining.min(buffer.len()));
+            let metadata = streamer.receive(&mut [&mut buffer[..length]], RECORD_TIMEOUT, false)?;
+            if let Some(error) = metadata.last_error() {
+                if !matches!(
+                    error.kind,
+                    ReceiveErrorKind::Overflow | ReceiveErrorKind::OutOfSequence
+                ) {
+                    return Err(Error::Other(error.into()));
+                }
+            }
+            self.write_block(&buffer[..metadata.samples()], &metadata)?;
+        }
+        Ok(())
+    }
+
+    /// Writes a block of samples from one channel with the metadata from receiving it
+    ///
+    /// This can be used to record samples that were received in some other way.
+    pub fn write_block(&mut self, samples: &[I], metadata: &ReceiveMetadata) -> Result<()> {
+        if let Some(error) = metadata.last_error() {
+            if matches!(
+                error.kind,
+                ReceiveErrorKind::Overflow | ReceiveErrorKind::OutOfSequence
+            ) && self.gap.is_none()
+            {
+                self.gap = Some(error.kind);
+            }
+        }
+        if samples.is_empty() {
+            return Ok(());
+        }
+
+        let time = metadata.time_spec();
+        let lost = self.lost_samples(time);
+        if self.captures.is_empty() || self.gap.is_some() || matches!(lost, Some(lost) if lost != 0)
+        {
+            self.start_capture(time, lost);
+        }
+        self.bytes.clear();
+        I::write_le(samples, &mut self.bytes);
+        self.data.write_all(&self.bytes)?;
+        self.samples += samples.len() as u64;
+        self.next_time = time
+            .map(|time| time + TimeSpec::from_ticks(samples.len() as i64, self.info.sample_rate));
+        Ok(())
+    }
+
+    /// Flushes the data file and writes the metadata file
+    pub fn finish(mut self) -> Result<()> {
+        self.finished = true;
+        self.write_metadata()
+    }
+}
+
+impl<I> SigmfRecorder<I> {
+    /// Returns the number of samples between the end of the previous block and a block that
+    /// starts at `time`, if both times are known
+    fn lost_samples(&self, time: Option<TimeSpec>) -> Option<i64> {
+        match (self.next_time, time) {
+            (Some(expected), Some(time)) if self.info.sample_rate >= 1.0 => {
+                Some((time - expected).to_ticks(self.info.sample_rate))
+            }
+            _ => None,
+        }
+    }
+
+    /// Adds a capture segment that starts at the next sample, and an annotation if it follows a gap
+    fn start_capture(&mut self, time: Option<TimeSpec>, lost: Option<i64>) {
+        let mut capture = Map::new();
+        capture.insert("core:sample_start".into(), self.samples.into());
+        capture.insert("core:frequency".into(), self.info.frequency.into());
+        if let Some(datetime) = time.and_then(datetime) {
+            capture.insert("core:datetime".into(), datetime.into());
+        }
+        self.captures.push(Value::Object(capture));
+
+        if let Some(kind) = self.gap.take() {
+            let mut annotation = Map::new();
+            annotation.insert("core:sample_start".into(), self.samples.into());
+            annotation.insert("core:sample_count".into(), 0.into());
+            let comment = match lost {
+                Some(lost) => format!("{:?}: {} samples lost", kind, lost),
+                None => format!("{:?}: samples lost", kind),
+            };
+            annotation.insert("core:comment".into(), comment.into());
+            if let Some(lost) = lost {
+                annotation.insert("uhd:lost_samples".into(), lost.into());
+            }
+            self.annotations.push(Value::Object(annotation));
+        }
+    }
+
+    fn write_metadata(&mut self) -> Result<()> {
+        self.data.flush()?;
+        let metadata = json!({
+            "global": self.global(),
+            "captures": self.captures,
+            "annotations": self.annotations,
+        });
+        let file = BufWriter::new(File::create(&self.meta_path)?);
+        serde_json::to_writer_pretty(file, &metadata)
+            .map_err(|e| Error::Unique(format!("Failed to write SigMF metadata: {}", e)))
+    }
+
+    fn global(&self) -> Value {
+        let mut global = Map::new();
+        global.insert("core:datatype".into(), self.datatype.into());
+        global.insert("core:sample_rate".into(), self.info.sample_rate.into());
+        global.insert("core:version".into(), SIGMF_VERSION.into());
+        global.insert(
+            "core:recorder".into(),
+            concat!("uhd-rust ", env!("CARGO_PKG_VERSION")).into(),
+        );
+        global.insert(
+            "core:extensions".into(),
+            json!([{ "name": "uhd", "version": UHD_EXTENSION_VERSION, "optional": true }]),
+        );
+        if let Some(description) = &self.info.description {
+            global.insert("core:description".into(), description.as_str().into());
+        }
+        global.insert("uhd:gain".into(), self.info.gain.into());
+        global.insert("uhd:antenna".into(), self.info.antenna.as_str().into());
+        if let Some(hardware) = &self.info.hardware {
+            global.insert(
+                "core:hw".into(),
+                format!(
+                    "{} ({}), serial {}, daughterboard {} serial {}",
+                    hardware.motherboard_name(),
+                    hardware.motherboard_id(),
+                    hardware.motherboard_serial(),
+                    hardware.daughterboard_id(),
+                    hardware.daughterboard_serial()
+                )
+                .into(),
+            );
+            let fields = [
+                ("uhd:motherboard_id", hardware.motherboard_id()),
+                ("uhd:motherboard_name", hardware.motherboard_name()),
+                ("uhd:motherboard_serial", hardware.motherboard_serial()),
+                ("uhd:daughterboard_id", hardware.daughterboard_id()),
+                ("uhd:daughterboard_serial", hardware.daughterboard_serial()),
+                ("uhd:subdev_name", hardware.subdev_name()),
+                ("uhd:subdev_spec", hardware.subdev_spec()),
+            ];
+            for (key, value) in fields.iter() {
+                global.insert((*key).into(), (*value).into());
+            }
+        }
+        Value::Object(global)
+    }
+}
+
+/// Returns the path of the data file of a recording
+pub(crate) fn data_path(base: &Path) -> PathBuf {
+    with_suffix(base, ".sigmf-data")
+}
+
+/// Returns the path of the metadata file of a recording
+pub(crate) fn meta_path(base: &Path) -> PathBuf {
+    with_suffix(base, ".sigmf-meta")
+}
+
+/// Appends a suffix to a path
+///
+/// Unlike `Path::with_extension`, this does not replace anything after a dot in the file name.
+fn with_suffix(base: &Path, suffix: &str) -> PathBuf {
+    let mut path = base.as_os_str().to_owned();
+    path.push(suffix);
+    PathBuf::from(path)
+}
+
+/// Formats a device time as an ISO 8601 UTC date and time, treating it as the number of seconds
+/// since the Unix epoch
+///
+/// This returns None if the time is before the epoch.
+fn datetime(time: TimeSpec) -> Option<String> {
+    let time = time.normalized();
+    let mut nanoseconds = (time.fraction * 1e9).round() as i64;
+    let seconds = time.seconds + nanoseconds / 1_000_000_000;
+    nanoseconds %= 1_000_000_000;
+    if seconds < 0 {
+        return None;
+    }
+    let (days, seconds) = (seconds / 86_400, seconds % 86_400);
+    // Convert days since the epoch to a date in the proleptic Gregorian calendar
+    // (from Howard Hinnant's civil_from_days)
+    let days = days + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    Some(format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:09}Z",
+        year,
+        month,
+        day,
+        seconds / 3600,
+        seconds % 3600 / 60,
+        seconds % 60,
+        nanoseconds
+    ))
+}
+
+impl<I> Drop for SigmfRecorder<I> {
+    fn drop(&mut self) {
+        if !self.finished {
+            // Write the metadata so that the recording can be read, even though errors
+            // can't be reported here
+            let _ = self.write_metadata();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+    use std::time::Duration;
+
+    use num_complex::Complex;
+    use serde_json::Value;
+
+    use super::{data_path, datetime, meta_path, RecordingInfo, SigmfRecorder};
+    use crate::sim::{Signal, SimUsrp};
+    use crate::{Device, ReceiveErrorKind, StreamArgs, TimeSpec, WireFormat};
+
+    #[test]
+    fn record_with_overflow() {
+        let mut usrp = SimUsrp::new();
+        usrp.set_rx_sample_rate(1e6, 0).unwrap();
+        usrp.add_rx_signal(0, Signal::Noise { amplitude: 0.1 })
+            .unwrap();
+        let control = usrp.clone();
+        let mut info = RecordingInfo::from_device(&usrp, 0).unwrap();
+        info.description = Some("Test recording".into());
+        let base = std::env::temp_dir().join(format!("uhd-sigmf-test.{}", std::process::id()));
+
+        let mut recorder = SigmfRecorder::<Complex<i16>>::create(&base, info).unwrap();
+        let mut streamer = usrp
+            .get_rx_stream(&StreamArgs::<Complex<i16>>::new(WireFormat::Sc16))
+            .unwrap();
+        recorder.record(&mut streamer, 5000).unwrap();
+        control.inject_rx_error(ReceiveErrorKind::Overflow);
+        recorder.record(&mut streamer, 3000).unwrap();
+        // Restarting the stream later starts a new capture segment without an annotation
+        control.advance_time(Duration::from_millis(1));
+        recorder.record(&mut streamer, 1000).unwrap();
+        assert_eq!(recorder.samples(), 9000);
+        assert!(recorder.record(&mut streamer, u64::MAX).is_err());
+        assert_eq!(recorder.samples(), 9000);
+        recorder.finish().unwrap();
+
+        // The extensions are appended after the dot in the base name
+        let data = fs::read(data_path(&base)).unwrap();
+        assert_eq!(data.len(), 9000 * 4);
+        let meta: Value = serde_json::from_slice(&fs::read(meta_path(&base)).unwrap()).unwrap();
+        assert_eq!(meta["global"]["core:datatype"], "ci16_le");
+        assert_eq!(meta["global"]["core:description"], "Test recording");
+        let captures = meta["captures"].as_array().unwrap();
+        assert_eq!(captures.len(), 3);
+        assert_eq!(captures[0]["core:sample_start"], 0);
+        assert_eq!(
+            captures[0]["core:datetime"],
+            "1970-01-01T00:00:00.000000000Z"
+        );
+        assert_eq!(captures[1]["core:sample_start"], 5000);
+        assert_eq!(
+            captures[1]["core:datetime"],
+            "1970-01-01T00:00:00.008000000Z"
+        );
+        assert_eq!(captures[2]["core:sample_start"], 8000);
+        assert_eq!(
+            captures[2]["core:datetime"],
+            "1970-01-01T00:00:00.012000000Z"
+        );
+        let annotations = meta["annotations"].as_array().unwrap();
+        assert_eq!(annotations.len(), 1);
+        assert_eq!(annotations[0]["core:sample_start"], 5000);
+        // The simulated overflow loses the samples that would have filled the buffer
+        assert_eq!(annotations[0]["uhd:lost_samples"], 3000);
+
+        fs::remove_file(data_path(&base)).unwrap();
+        fs::remove_file(meta_path(&base)).unwrap();
+    }
+
+    #[test]
+    fn datetimes() {
+        assert_eq!(
+            datetime(TimeSpec::new(0, 0.0)).unwrap(),
+            "1970-01-01T00:00:00.000000000Z"
+        );
+        assert_eq!(
+            datetime(TimeSpec::new(951_782_400, 0.25)).unwrap(),
+            "2000-02-29T00:00:00.250000000Z"
+        );
+        assert_eq!(
+            datetime(TimeSpec::new(1_700_000_000, 0.9999999999)).unwrap(),
+            "2023-11-14T22:13:21.000000000Z"
+        );
+        assert_eq!(datetime(TimeSpec::new(-1, 0.5)), None);
+    }
+}
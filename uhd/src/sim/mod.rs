@@ -0,0 +1,1116 @@
+//! A simulated USRP that works without hardware
+//!
+//! [`SimUsrp`] implements [`Device`](crate::Device) in Rust. It keeps track of channel settings,
+//! coerces them to configurable ranges, reports sensors, and has a sample clock that advances
+//! as samples are received. Receive streams produce synthetic [`Signal`]s, and errors can be
+//! injected to test error handling.
+//!
+//! ```no_run
+//! use num_complex::Complex32;
+//! use uhd::sim::{Signal, SimUsrp};
+//! use uhd::{
+//!     Device, ReceiveStream, StreamArgs, StreamCommand, StreamCommandType, StreamTime,
+//!     TuneRequest, WireFormat,
+//! };
+//!
+//! # fn main() -> Result<(), uhd::Error> {
+//! let mut usrp = SimUsrp::new();
+//! usrp.set_rx_frequency(&TuneRequest::with_frequency(915e6), 0)?;
+//! usrp.add_rx_signal(0, Signal::Tone { frequency: 915.1e6, amplitude: 0.5 })?;
+//!
+//! let mut streamer = usrp.get_rx_stream(&StreamArgs::<Complex32>::new(WireFormat::Sc16))?;
+//! streamer.send_command(&StreamCommand {
+//!     command_type: StreamCommandType::CountAndDone(1000),
+//!     time: StreamTime::Now,
+//! })?;
+//! let mut buffer = vec![Complex32::default(); 1000];
+//! let metadata = streamer.receive(&mut [&mut buffer], 0.1, false)?;
+//! assert_eq!(metadata.samples(), 1000);
+//! # Ok(())
+//! # }
+//! ```
+
+mod signal;
+mod stream;
+
+pub use self::signal::Signal;
+pub use self::stream::{SimReceiveStreamer, SimTransmitStreamer};
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use num_complex::Complex64;
+
+use crate::device::Device;
+use crate::error::Error;
+use crate::range::{MetaRange, Range};
+use crate::stream::{Sample, StreamArgs};
+use crate::waveform::Rng;
+use crate::{
+    AsyncEventCode, AsyncMetadata, ChannelDirection, ReceiveErrorKind, SensorValue, TimeSpec,
+    TuneRequest, TuneRequestPolicy, TuneResult,
+};
+
+/// A simulated USRP
+///
+/// Clones of a `SimUsrp` share the same simulated device. This can be used to inject errors or
+/// change signals while a streamer borrows the device.
+#[derive(Debug, Clone)]
+pub struct SimUsrp {
+    state: Arc<Mutex<State>>,
+}
+
+impl SimUsrp {
+    /// Creates a simulated device with the default configuration
+    ///
+    /// The default configuration is similar to a B210: two receive and two transmit channels,
+    /// 70 MHz to 6 GHz, and sample rates up to 61.44 MHz.
+    pub fn new() -> Self {
+        SimUsrp::builder().build()
+    }
+
+    /// Creates a builder that can configure a simulated device
+    pub fn builder() -> SimUsrpBuilder {
+        SimUsrpBuilder::default()
+    }
+
+    /// Replaces the signals that a receive channel produces
+    pub fn set_rx_signals(&self, channel: usize, signals: Vec<Signal>) -> Result<(), Error> {
+        self.state().rx_channel_mut(channel)?.signals = signals;
+        Ok(())
+    }
+
+    /// Adds a signal to the signals that a receive channel produces
+    pub fn add_rx_signal(&self, channel: usize, signal: Signal) -> Result<(), Error> {
+        self.state().rx_channel_mut(channel)?.signals.push(signal);
+        Ok(())
+    }
+
+    /// Makes a future `receive` call on a receive stream report an error
+    ///
+    /// Injected errors are reported in order, one per `receive` call. An overflow or an
+    /// out-of-sequence error also skips the samples that a `receive` call would have returned,
+    /// so the next time spec has a gap.
+    pub fn inject_rx_error(&self, kind: ReceiveErrorKind) {
+        self.state().rx_errors.push_back(kind);
+    }
+
+    /// Makes the next `send` call on a transmit stream report an asynchronous event
+    pub fn inject_tx_event(&self, event_code: AsyncEventCode) {
+        self.state().tx_events.push_back(event_code);
+    }
+
+    /// Sets the value of a motherboard sensor, adding the sensor if it does not exist
+    pub fn set_mboard_sensor(&self, name: &str, value: SensorValue) {
+        set_sensor(&mut self.state().mboard_sensors, name, value)
+    }
+
+    /// Sets the value of a sensor on a receive channel, adding the sensor if it does not exist
+    pub fn set_rx_sensor(
+        &self,
+        name: &str,
+        value: SensorValue,
+        channel: usize,
+    ) -> Result<(), Error> {
+        set_sensor(
+            &mut self.state().rx_channel_mut(channel)?.sensors,
+            name,
+            value,
+        );
+        Ok(())
+    }
+
+    /// Sets the value of a sensor on a transmit channel, adding the sensor if it does not exist
+    pub fn set_tx_sensor(
+        &self,
+        name: &str,
+        value: SensorValue,
+        channel: usize,
+    ) -> Result<(), Error> {
+        set_sensor(
+            &mut self.state().tx_channel_mut(channel)?.sensors,
+            name,
+            value,
+        );
+        Ok(())
+    }
+
+    /// Advances the device time without producing samples
+    pub fn advance_time(&self, duration: Duration) {
+        self.state().time += duration;
+    }
+
+    /// Returns the number of samples that have been transmitted on a channel
+    pub fn transmitted_samples(&self, channel: usize) -> Result<u64, Error> {
+        Ok(self.state().tx_channel(channel)?.transmitted)
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        lock(&self.state)
+    }
+}
+
+impl Default for SimUsrp {
+    fn default() -> Self {
+        SimUsrp::new()
+    }
+}
+
+/// Configures a simulated device
+///
+/// The gain range applies to one gain element named `PGA`. The same ranges, antennas, and
+/// sensors apply to all channels.
+#[derive(Debug, Clone)]
+pub struct SimUsrpBuilder {
+    motherboard_name: String,
+    rx_channels: usize,
+    tx_channels: usize,
+    frequency_range: Range,
+    rx_gain_range: Range,
+    tx_gain_range: Range,
+    sample_rate_range: Range,
+    bandwidth_range: Range,
+    rx_antennas: Vec<String>,
+    tx_antennas: Vec<String>,
+    master_clock_rate: f64,
+    samples_per_packet: usize,
+    mboard_sensors: Vec<(String, SensorValue)>,
+    channel_sensors: Vec<(String, SensorValue)>,
+    seed: u64,
+}
+
+impl SimUsrpBuilder {
+    /// Sets the name that the motherboard reports
+    pub fn motherboard_name<S: Into<String>>(self, name: S) -> Self {
+        SimUsrpBuilder {
+            motherboard_name: name.into(),
+            ..self
+        }
+    }
+
+    /// Sets the number of receive channels
+    pub fn rx_channels(self, rx_channels: usize) -> Self {
+        SimUsrpBuilder {
+            rx_channels,
+            ..self
+        }
+    }
+
+    /// Sets the number of transmit channels
+    pub fn tx_channels(self, tx_channels: usize) -> Self {
+        SimUsrpBuilder {
+            tx_channels,
+            ..self
+        }
+    }
+
+    /// Sets the range of RF frequencies for all channels
+    pub fn frequency_range(self, frequency_range: Range) -> Self {
+        SimUsrpBuilder {
+            frequency_range,
+            ..self
+        }
+    }
+
+    /// Sets the range of receive gains
+    pub fn rx_gain_range(self, rx_gain_range: Range) -> Self {
+        SimUsrpBuilder {
+            rx_gain_range,
+            ..self
+        }
+    }
+
+    /// Sets the range of transmit gains
+    pub fn tx_gain_range(self, tx_gain_range: Range) -> Self {
+        SimUsrpBuilder {
+            tx_gain_range,
+            ..self
+        }
+    }
+
+    /// Sets the range of sample rates for all channels
+    ///
+    /// Sample rates are also coerced to integer divisions of the master clock rate.
+    pub fn sample_rate_range(self, sample_rate_range: Range) -> Self {
+        SimUsrpBuilder {
+            sample_rate_range,
+            ..self
+        }
+    }
+
+    /// Sets the range of front-end bandwidths for all channels
+    pub fn bandwidth_range(self, bandwidth_range: Range) -> Self {
+        SimUsrpBuilder {
+            bandwidth_range,
+            ..self
+        }
+    }
+
+    /// Sets the receive antennas (the first one is selected initially)
+    pub fn rx_antennas(self, rx_antennas: Vec<String>) -> Self {
+        SimUsrpBuilder {
+            rx_antennas,
+            ..self
+        }
+    }
+
+    /// Sets the transmit antennas (the first one is selected initially)
+    pub fn tx_antennas(self, tx_antennas: Vec<String>) -> Self {
+        SimUsrpBuilder {
+            tx_antennas,
+            ..self
+        }
+    }
+
+    /// Sets the master clock rate
+    pub fn master_clock_rate(self, master_clock_rate: f64) -> Self {
+        SimUsrpBuilder {
+            master_clock_rate,
+            ..self
+        }
+    }
+
+    /// Sets the maximum number of samples that one `receive` call returns when `one_packet`
+    /// is true
+    pub fn samples_per_packet(self, samples_per_packet: usize) -> Self {
+        SimUsrpBuilder {
+            samples_per_packet,
+            ..self
+        }
+    }
+
+    /// Adds a motherboard sensor
+    pub fn mboard_sensor<S: Into<String>>(mut self, name: S, value: SensorValue) -> Self {
+        set_sensor(&mut self.mboard_sensors, &name.into(), value);
+        self
+    }
+
+    /// Adds a sensor to every receive and transmit channel
+    pub fn channel_sensor<S: Into<String>>(mut self, name: S, value: SensorValue) -> Self {
+        set_sensor(&mut self.channel_sensors, &name.into(), value);
+        self
+    }
+
+    /// Sets the seed used to generate noise
+    pub fn seed(self, seed: u64) -> Self {
+        SimUsrpBuilder { seed, ..self }
+    }
+
+    /// Creates a simulated device with this configuration
+    pub fn build(self) -> SimUsrp {
+        let tx_channels = self.tx_channels;
+        let channel = |antennas: &[String], gain_range: &Range| ChannelState {
+            antenna: antennas.first().cloned().unwrap_or_default(),
+            antennas: antennas.to_vec(),
+            bandwidth: self.bandwidth_range.stop(),
+            frequency: self.frequency_range.start(),
+            gain: gain_range.start(),
+            gain_range: gain_range.clone(),
+            sample_rate: coerce_sample_rate(1e6, &self.sample_rate_range, self.master_clock_rate),
+            sensors: self.channel_sensors.clone(),
+            signals: Vec::new(),
+            loopback: vec![VecDeque::new(); tx_channels],
+            transmitted: 0,
+        };
+        let state = State {
+            rx: (0..self.rx_channels)
+                .map(|_| channel(&self.rx_antennas, &self.rx_gain_range))
+                .collect(),
+            tx: (0..self.tx_channels)
+                .map(|_| channel(&self.tx_antennas, &self.tx_gain_range))
+                .collect(),
+            clock_source: "internal".to_owned(),
+            time_source: "internal".to_owned(),
+            time: TimeSpec::default(),
+            command_time: None,
+            rx_errors: VecDeque::new(),
+            tx_events: VecDeque::new(),
+            async_messages: VecDeque::new(),
+            mboard_sensors: self.mboard_sensors.clone(),
+            rng: Rng::new(self.seed),
+            config: self,
+        };
+        SimUsrp {
+            state: Arc::new(Mutex::new(state)),
+        }
+    }
+}
+
+impl Default for SimUsrpBuilder {
+    fn default() -> Self {
+        SimUsrpBuilder {
+            motherboard_name: "B210".to_owned(),
+            rx_channels: 2,
+            tx_channels: 2,
+            frequency_range: Range::new(70e6, 6e9, 0.0),
+            rx_gain_range: Range::new(0.0, 76.0, 1.0),
+            tx_gain_range: Range::new(0.0, 89.75, 0.25),
+            sample_rate_range: Range::new(62.5e3, 61.44e6, 0.0),
+            bandwidth_range: Range::new(200e3, 56e6, 0.0),
+            rx_antennas: vec!["TX/RX".to_owned(), "RX2".to_owned()],
+            tx_antennas: vec!["TX/RX".to_owned()],
+            master_clock_rate: 32e6,
+            samples_per_packet: 2040,
+            mboard_sensors: vec![("ref_locked".to_owned(), SensorValue::Boolean(true))],
+            channel_sensors: vec![("lo_locked".to_owned(), SensorValue::Boolean(true))],
+            seed: 0,
+        }
+    }
+}
+
+/// The name of the only gain element of a simulated channel
+const GAIN_NAME: &str = "PGA";
+const CLOCK_SOURCES: [&str; 3] = ["internal", "external", "gpsdo"];
+const TIME_SOURCES: [&str; 4] = ["none", "internal", "external", "gpsdo"];
+
+#[derive(Debug)]
+pub(crate) struct State {
+    config: SimUsrpBuilder,
+    rx: Vec<ChannelState>,
+    tx: Vec<ChannelState>,
+    clock_source: String,
+    time_source: String,
+    /// The device time of the next received sample
+    time: TimeSpec,
+    command_time: Option<TimeSpec>,
+    rx_errors: VecDeque<ReceiveErrorKind>,
+    tx_events: VecDeque<AsyncEventCode>,
+    async_messages: VecDeque<AsyncMetadata>,
+    mboard_sensors: Vec<(String, SensorValue)>,
+    rng: Rng,
+}
+
+#[derive(Debug, Clone)]
+struct ChannelState {
+    antenna: String,
+    antennas: Vec<String>,
+    bandwidth: f64,
+    frequency: f64,
+    gain: f64,
+    gain_range: Range,
+    sample_rate: f64,
+    sensors: Vec<(String, SensorValue)>,
+    /// Receive channels only
+    signals: Vec<Signal>,
+    /// Receive channels only: transmitted samples waiting to be received by loopback signals,
+    /// for each transmit channel
+    loopback: Vec<VecDeque<Complex64>>,
+    /// Transmit channels only: the number of samples transmitted
+    transmitted: u64,
+}
+
+impl State {
+    fn rx_channel(&self, channel: usize) -> Result<&ChannelState, Error> {
+        self.rx.get(channel).ok_or(Error::Index)
+    }
+    fn rx_channel_mut(&mut self, channel: usize) -> Result<&mut ChannelState, Error> {
+        self.rx.get_mut(channel).ok_or(Error::Index)
+    }
+    fn tx_channel(&self, channel: usize) -> Result<&ChannelState, Error> {
+        self.tx.get(channel).ok_or(Error::Index)
+    }
+    fn tx_channel_mut(&mut self, channel: usize) -> Result<&mut ChannelState, Error> {
+        self.tx.get_mut(channel).ok_or(Error::Index)
+    }
+
+    fn check_mboard(&self, mboard: usize) -> Result<(), Error> {
+        if mboard == 0 {
+            Ok(())
+        } else {
+            Err(Error::Index)
+        }
+    }
+
+    /// Produces one received sample on a channel at the current device time plus an offset
+    fn rx_sample(&mut self, channel: usize, time: TimeSpec) -> Complex64 {
+        let State { rx, rng, .. } = self;
+        let channel = &mut rx[channel];
+        let mut sample = Complex64::new(0.0, 0.0);
+        for signal in &channel.signals {
+            sample += match *signal {
+                Signal::Tone {
+                    frequency,
+                    amplitude,
+                } => signal::tone(frequency - channel.frequency, amplitude, time),
+                Signal::Noise { amplitude } => rng.complex_gaussian(amplitude),
+                Signal::Loopback {
+                    channel: tx_channel,
+                } => channel
+                    .loopback
+                    .get(tx_channel)
+                    .and_then(VecDeque::front)
+                    .copied()
+                    .unwrap_or_default(),
+            };
+        }
+        for queue in &mut channel.loopback {
+            queue.pop_front();
+        }
+        let sample = sample * 10f64.powf(channel.gain / 20.0);
+        Complex64::new(sample.re.clamp(-1.0, 1.0), sample.im.clamp(-1.0, 1.0))
+    }
+
+    /// Queues transmitted samples for the receive channels that have loopback signals from a
+    /// transmit channel
+    fn loop_back<S>(&mut self, tx_channel: usize, samples: S)
+    where
+        S: Iterator<Item = Complex64> + Clone,
+    {
+        for rx in &mut self.rx {
+            let looped_back = rx.signals.iter().any(
+                |signal| matches!(signal, Signal::Loopback { channel } if *channel == tx_channel),
+            );
+            if looped_back {
+                rx.loopback[tx_channel].extend(samples.clone());
+            }
+        }
+    }
+
+    fn push_async_message(&mut self, channel: usize, event_code: AsyncEventCode) {
+        let time = self.time;
+        self.async_messages
+            .push_back(AsyncMetadata::new(channel, Some(time), event_code, [0; 4]));
+    }
+}
+
+fn lock(state: &Mutex<State>) -> MutexGuard<'_, State> {
+    // A panic while holding the lock cannot leave the state inconsistent in a way that matters
+    // for a simulation
+    state.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn set_sensor(sensors: &mut Vec<(String, SensorValue)>, name: &str, value: SensorValue) {
+    match sensors.iter_mut().find(|(existing, _)| existing == name) {
+        Some(entry) => entry.1 = value,
+        None => sensors.push((name.to_owned(), value)),
+    }
+}
+
+fn get_sensor(sensors: &[(String, SensorValue)], name: &str) -> Result<SensorValue, Error> {
+    sensors
+        .iter()
+        .find(|(existing, _)| existing == name)
+        .map(|(_, value)| value.clone())
+        .ok_or(Error::Key)
+}
+
+fn sensor_names(sensors: &[(String, SensorValue)]) -> Vec<String> {
+    sensors.iter().map(|(name, _)| name.clone()).collect()
+}
+
+/// Clips a value to a range, and optionally rounds it to a multiple of the step from the start
+fn clip(range: &Range, value: f64) -> f64 {
+    let value = value.max(range.start()).min(range.stop());
+    if range.step() > 0.0 {
+        let steps = ((value - range.start()) / range.step()).round();
+        (range.start() + steps * range.step()).min(range.stop())
+    } else {
+        value
+    }
+}
+
+/// Clips a sample rate to a range and then to an integer division of the master clock rate
+fn coerce_sample_rate(rate: f64, range: &Range, master_clock_rate: f64) -> f64 {
+    let rate = clip(range, rate);
+    let decimation = (master_clock_rate / rate).round().max(1.0);
+    master_clock_rate / decimation
+}
+
+fn meta_range(range: &Range) -> MetaRange {
+    std::iter::once(range.clone()).collect()
+}
+
+fn check_gain_name(name: &str) -> Result<(), Error> {
+    if name.is_empty() || name == GAIN_NAME {
+        Ok(())
+    } else {
+        Err(Error::Key)
+    }
+}
+
+fn set_antenna(channel: &mut ChannelState, antenna: &str) -> Result<(), Error> {
+    if channel.antennas.iter().any(|existing| existing == antenna) {
+        channel.antenna = antenna.to_owned();
+        Ok(())
+    } else {
+        Err(Error::Value)
+    }
+}
+
+fn check_source(sources: &[&str], source: &str) -> Result<String, Error> {
+    if sources.contains(&source) {
+        Ok(source.to_owned())
+    } else {
+        Err(Error::Value)
+    }
+}
+
+/// Tunes a channel like UHD's automatic tuning: the RF frontend is tuned as close as possible,
+/// and the DSP makes up the difference
+fn tune(channel: &mut ChannelState, range: &Range, request: &TuneRequest) -> TuneResult {
+    let clipped_rf = clip(range, request.target_frequency);
+    let target_rf = match request.rf {
+        TuneRequestPolicy::Manual(frequency) => frequency,
+        TuneRequestPolicy::Auto => request.target_frequency,
+        TuneRequestPolicy::None => channel.frequency,
+    };
+    let actual_rf = clip(range, target_rf);
+    let target_dsp = match request.dsp {
+        TuneRequestPolicy::Manual(frequency) => frequency,
+        TuneRequestPolicy::Auto => request.target_frequency - actual_rf,
+        TuneRequestPolicy::None => 0.0,
+    };
+    // Limit the DSP offset to the Nyquist bandwidth of the current sample rate
+    let nyquist = channel.sample_rate / 2.0;
+    let actual_dsp = target_dsp.max(-nyquist).min(nyquist);
+    channel.frequency = actual_rf + actual_dsp;
+    TuneResult::new(target_rf, clipped_rf, actual_rf, target_dsp, actual_dsp)
+}
+
+impl Device for SimUsrp {
+    type ReceiveStream<'device, I>
+        = SimReceiveStreamer<'device, I>
+    where
+        I: Sample + 'device;
+    type TransmitStream<'device, I>
+        = SimTransmitStreamer<'device, I>
+    where
+        I: Sample + 'device;
+
+    fn get_num_motherboards(&self) -> Result<usize, Error> {
+        Ok(1)
+    }
+    fn get_num_rx_channels(&self) -> Result<usize, Error> {
+        Ok(self.state().rx.len())
+    }
+    fn get_num_tx_channels(&self) -> Result<usize, Error> {
+        Ok(self.state().tx.len())
+    }
+    fn get_motherboard_name(&self, mboard: usize) -> Result<String, Error> {
+        let state = self.state();
+        state.check_mboard(mboard)?;
+        Ok(state.config.motherboard_name.clone())
+    }
+    fn get_master_clock_rate(&self, mboard: usize) -> Result<f64, Error> {
+        let state = self.state();
+        state.check_mboard(mboard)?;
+        Ok(state.config.master_clock_rate)
+    }
+
+    fn get_rx_antennas(&self, channel: usize) -> Result<Vec<String>, Error> {
+        Ok(self.state().rx_channel(channel)?.antennas.clone())
+    }
+    fn get_rx_antenna(&self, channel: usize) -> Result<String, Error> {
+        Ok(self.state().rx_channel(channel)?.antenna.clone())
+    }
+    fn set_rx_antenna(&mut self, antenna: &str, channel: usize) -> Result<(), Error> {
+        set_antenna(self.state().rx_channel_mut(channel)?, antenna)
+    }
+    fn get_tx_antennas(&self, channel: usize) -> Result<Vec<String>, Error> {
+        Ok(self.state().tx_channel(channel)?.antennas.clone())
+    }
+    fn get_tx_antenna(&self, channel: usize) -> Result<String, Error> {
+        Ok(self.state().tx_channel(channel)?.antenna.clone())
+    }
+    fn set_tx_antenna(&mut self, antenna: &str, channel: usize) -> Result<(), Error> {
+        set_antenna(self.state().tx_channel_mut(channel)?, antenna)
+    }
+
+    fn get_rx_bandwidth(&self, channel: usize) -> Result<f64, Error> {
+        Ok(self.state().rx_channel(channel)?.bandwidth)
+    }
+    fn get_rx_bandwidth_range(&self, channel: usize) -> Result<MetaRange, Error> {
+        let state = self.state();
+        state.rx_channel(channel)?;
+        Ok(meta_range(&state.config.bandwidth_range))
+    }
+    fn set_rx_bandwidth(&mut self, bandwidth: f64, channel: usize) -> Result<(), Error> {
+        let mut state = self.state();
+        let bandwidth = clip(&state.config.bandwidth_range, bandwidth);
+        state.rx_channel_mut(channel)?.bandwidth = bandwidth;
+        Ok(())
+    }
+    fn get_tx_bandwidth(&self, channel: usize) -> Result<f64, Error> {
+        Ok(self.state().tx_channel(channel)?.bandwidth)
+    }
+    fn get_tx_bandwidth_range(&self, channel: usize) -> Result<MetaRange, Error> {
+        let state = self.state();
+        state.tx_channel(channel)?;
+        Ok(meta_range(&state.config.bandwidth_range))
+    }
+    fn set_tx_bandwidth(&mut self, bandwidth: f64, channel: usize) -> Result<(), Error> {
+        let mut state = self.state();
+        let bandwidth = clip(&state.config.bandwidth_range, bandwidth);
+        state.tx_channel_mut(channel)?.bandwidth = bandwidth;
+        Ok(())
+    }
+
+    fn get_rx_frequency(&self, channel: usize) -> Result<f64, Error> {
+        Ok(self.state().rx_channel(channel)?.frequency)
+    }
+    fn get_rx_frequency_range(&self, channel: usize) -> Result<MetaRange, Error> {
+        let state = self.state();
+        state.rx_channel(channel)?;
+        Ok(meta_range(&state.config.frequency_range))
+    }
+    fn set_rx_frequency(
+        &mut self,
+        request: &TuneRequest,
+        channel: usize,
+    ) -> Result<TuneResult, Error> {
+        let mut state = self.state();
+        let range = state.config.frequency_range.clone();
+        Ok(tune(state.rx_channel_mut(channel)?, &range, request))
+    }
+    fn get_tx_frequency(&self, channel: usize) -> Result<f64, Error> {
+        Ok(self.state().tx_channel(channel)?.frequency)
+    }
+    fn get_tx_frequency_range(&self, channel: usize) -> Result<MetaRange, Error> {
+        let state = self.state();
+        state.tx_channel(channel)?;
+        Ok(meta_range(&state.config.frequency_range))
+    }
+    fn set_tx_frequency(
+        &mut self,
+        request: &TuneRequest,
+        channel: usize,
+    ) -> Result<TuneResult, Error> {
+        let mut state = self.state();
+        let range = state.config.frequency_range.clone();
+        Ok(tune(state.tx_channel_mut(channel)?, &range, request))
+    }
+
+    fn get_rx_gain(&self, channel: usize, name: &str) -> Result<f64, Error> {
+        check_gain_name(name)?;
+        Ok(self.state().rx_channel(channel)?.gain)
+    }
+    fn get_rx_gain_names(&self, channel: usize) -> Result<Vec<String>, Error> {
+        self.state().rx_channel(channel)?;
+        Ok(vec![GAIN_NAME.to_owned()])
+    }
+    fn get_rx_gain_range(&self, channel: usize, name: &str) -> Result<MetaRange, Error> {
+        check_gain_name(name)?;
+        Ok(meta_range(&self.state().rx_channel(channel)?.gain_range))
+    }
+    fn set_rx_gain(&mut self, gain: f64, channel: usize, name: &str) -> Result<(), Error> {
+        check_gain_name(name)?;
+        let mut state = self.state();
+        let channel = state.rx_channel_mut(channel)?;
+        channel.gain = clip(&channel.gain_range, gain);
+        Ok(())
+    }
+    fn get_tx_gain(&self, channel: usize, name: &str) -> Result<f64, Error> {
+        check_gain_name(name)?;
+        Ok(self.state().tx_channel(channel)?.gain)
+    }
+    fn get_tx_gain_names(&self, channel: usize) -> Result<Vec<String>, Error> {
+        self.state().tx_channel(channel)?;
+        Ok(vec![GAIN_NAME.to_owned()])
+    }
+    fn get_tx_gain_range(&self, channel: usize, name: &str) -> Result<MetaRange, Error> {
+        check_gain_name(name)?;
+        Ok(meta_range(&self.state().tx_channel(channel)?.gain_range))
+    }
+    fn set_tx_gain(&mut self, gain: f64, channel: usize, name: &str) -> Result<(), Error> {
+        check_gain_name(name)?;
+        let mut state = self.state();
+        let channel = state.tx_channel_mut(channel)?;
+        channel.gain = clip(&channel.gain_range, gain);
+        Ok(())
+    }
+
+    fn get_rx_sample_rate(&self, channel: usize) -> Result<f64, Error> {
+        Ok(self.state().rx_channel(channel)?.sample_rate)
+    }
+    fn get_rx_sample_rates(&self, channel: usize) -> Result<MetaRange, Error> {
+        let state = self.state();
+        state.rx_channel(channel)?;
+        Ok(meta_range(&state.config.sample_rate_range))
+    }
+    fn set_rx_sample_rate(&mut self, rate: f64, channel: usize) -> Result<(), Error> {
+        let mut state = self.state();
+        let rate = coerce_sample_rate(
+            rate,
+            &state.config.sample_rate_range,
+            state.config.master_clock_rate,
+        );
+        state.rx_channel_mut(channel)?.sample_rate = rate;
+        Ok(())
+    }
+    fn get_tx_sample_rate(&self, channel: usize) -> Result<f64, Error> {
+        Ok(self.state().tx_channel(channel)?.sample_rate)
+    }
+    fn get_tx_sample_rates(&self, channel: usize) -> Result<MetaRange, Error> {
+        let state = self.state();
+        state.tx_channel(channel)?;
+        Ok(meta_range(&state.config.sample_rate_range))
+    }
+    fn set_tx_sample_rate(&mut self, rate: f64, channel: usize) -> Result<(), Error> {
+        let mut state = self.state();
+        let rate = coerce_sample_rate(
+            rate,
+            &state.config.sample_rate_range,
+            state.config.master_clock_rate,
+        );
+        state.tx_channel_mut(channel)?.sample_rate = rate;
+        Ok(())
+    }
+
+    fn get_mboard_sensor_names(&self, mboard: usize) -> Result<Vec<String>, Error> {
+        let state = self.state();
+        state.check_mboard(mboard)?;
+        Ok(sensor_names(&state.mboard_sensors))
+    }
+    fn get_mboard_sensor(&self, name: &str, mboard: usize) -> Result<SensorValue, Error> {
+        let state = self.state();
+        state.check_mboard(mboard)?;
+        get_sensor(&state.mboard_sensors, name)
+    }
+    fn get_rx_sensor_names(&self, channel: usize) -> Result<Vec<String>, Error> {
+        Ok(sensor_names(&self.state().rx_channel(channel)?.sensors))
+    }
+    fn get_rx_sensor(&self, name: &str, channel: usize) -> Result<SensorValue, Error> {
+        get_sensor(&self.state().rx_channel(channel)?.sensors, name)
+    }
+    fn get_tx_sensor_names(&self, channel: usize) -> Result<Vec<String>, Error> {
+        Ok(sensor_names(&self.state().tx_channel(channel)?.sensors))
+    }
+    fn get_tx_sensor(&self, name: &str, channel: usize) -> Result<SensorValue, Error> {
+        get_sensor(&self.state().tx_channel(channel)?.sensors, name)
+    }
+
+    fn get_clock_source(&self, mboard: usize) -> Result<String, Error> {
+        let state = self.state();
+        state.check_mboard(mboard)?;
+        Ok(state.clock_source.clone())
+    }
+    fn get_clock_sources(&self, mboard: usize) -> Result<Vec<String>, Error> {
+        self.state().check_mboard(mboard)?;
+        Ok(CLOCK_SOURCES
+            .iter()
+            .map(|&source| source.to_owned())
+            .collect())
+    }
+    fn set_clock_source(&self, source: &str, mboard: usize) -> Result<(), Error> {
+        let mut state = self.state();
+        state.check_mboard(mboard)?;
+        state.clock_source = check_source(&CLOCK_SOURCES, source)?;
+        Ok(())
+    }
+    fn get_time_source(&self, mboard: usize) -> Result<String, Error> {
+        let state = self.state();
+        state.check_mboard(mboard)?;
+        Ok(state.time_source.clone())
+    }
+    fn get_time_sources(&self, mboard: usize) -> Result<Vec<String>, Error> {
+        self.state().check_mboard(mboard)?;
+        Ok(TIME_SOURCES
+            .iter()
+            .map(|&source| source.to_owned())
+            .collect())
+    }
+    fn set_time_source(&self, source: &str, mboard: usize) -> Result<(), Error> {
+        let mut state = self.state();
+        state.check_mboard(mboard)?;
+        state.time_source = check_source(&TIME_SOURCES, source)?;
+        Ok(())
+    }
+
+    fn get_current_time(&self, mboard: usize) -> Result<TimeSpec, Error> {
+        let state = self.state();
+        state.check_mboard(mboard)?;
+        Ok(state.time)
+    }
+    fn set_time_unknown_pps(&self, full_secs: i64, frac_secs: f64) -> Result<(), Error> {
+        self.state().time = TimeSpec::new(full_secs, frac_secs).normalized();
+        Ok(())
+    }
+    /// Sets the device time immediately (the simulated device has no PPS signal)
+    fn set_time_next_pps(
+        &self,
+        full_secs: i64,
+        frac_secs: f64,
+        mboard: usize,
+    ) -> Result<(), Error> {
+        let mut state = self.state();
+        state.check_mboard(mboard)?;
+        state.time = TimeSpec::new(full_secs, frac_secs).normalized();
+        Ok(())
+    }
+    fn get_time_synchronized(&self) -> Result<bool, Error> {
+        Ok(true)
+    }
+    /// Records the command time (commands to the simulated device always take effect
+    /// immediately)
+    fn set_command_time(&mut self, time: TimeSpec, mboard: usize) -> Result<(), Error> {
+        let mut state = self.state();
+        state.check_mboard(mboard)?;
+        state.command_time = Some(time);
+        Ok(())
+    }
+    fn clear_command_time(&mut self, mboard: usize) -> Result<(), Error> {
+        let mut state = self.state();
+        state.check_mboard(mboard)?;
+        state.command_time = None;
+        Ok(())
+    }
+
+    fn get_rx_stream<I>(&mut self, args: &StreamArgs<I>) -> Result<SimReceiveStreamer<'_, I>, Error>
+    where
+        I: Sample,
+    {
+        let channels = args.validate(self.state().rx.len(), ChannelDirection::Receive)?;
+        Ok(SimReceiveStreamer::new(self.state.clone(), channels))
+    }
+
+    fn get_tx_stream<I>(
+        &mut self,
+        args: &StreamArgs<I>,
+    ) -> Result<SimTransmitStreamer<'_, I>, Error>
+    where
+        I: Sample,
+    {
+        let channels = args.validate(self.state().tx.len(), ChannelDirection::Transmit)?;
+        Ok(SimTransmitStreamer::new(self.state.clone(), channels))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use num_complex::{Complex, Complex32};
+
+    use super::{Signal, SimUsrp};
+    use crate::range::Range;
+    use crate::{
+        AsyncEventCode, Device, Error, ReceiveErrorKind, ReceiveStream, SensorValue, StreamArgs,
+        StreamCommand, StreamCommandType, StreamTime, TimeSpec, TransmitMetadata, TransmitStream,
+        TuneRequest, WireFormat,
+    };
+
+    fn receive_command(samples: u64, time: StreamTime) -> StreamCommand {
+        StreamCommand {
+            command_type: StreamCommandType::CountAndDone(samples),
+            time,
+        }
+    }
+
+    #[test]
+    fn settings_are_coerced() {
+        let mut usrp = SimUsrp::builder()
+            .rx_gain_range(Range::new(0.0, 60.0, 0.5))
+            .master_clock_rate(30.72e6)
+            .build();
+        usrp.set_rx_gain(70.0, 0, "").unwrap();
+        assert_eq!(usrp.get_rx_gain(0, "PGA").unwrap(), 60.0);
+        usrp.set_rx_gain(10.3, 1, "").unwrap();
+        assert_eq!(usrp.get_rx_gain(1, "").unwrap(), 10.5);
+        assert!(matches!(usrp.get_rx_gain(0, "LNA"), Err(Error::Key)));
+
+        usrp.set_rx_sample_rate(1e6, 0).unwrap();
+        assert_eq!(usrp.get_rx_sample_rate(0).unwrap(), 30.72e6 / 31.0);
+
+        let result = usrp
+            .set_rx_frequency(&TuneRequest::with_frequency(10e9), 0)
+            .unwrap();
+        assert_eq!(result.clipped_rf_freq(), 6e9);
+        assert!(matches!(usrp.get_rx_antenna(2), Err(Error::Index)));
+        assert!(usrp.set_rx_antenna("RX3", 0).is_err());
+        usrp.set_rx_antenna("RX2", 0).unwrap();
+        assert_eq!(usrp.get_rx_antenna(0).unwrap(), "RX2");
+        assert!(matches!(
+            usrp.get_rx_stream(&StreamArgs::<Complex32>::builder().channels(vec![2]).build()),
+            Err(Error::NoSuchChannel { channel: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn sensors() {
+        let usrp = SimUsrp::new();
+        assert_eq!(
+            usrp.get_mboard_sensor("ref_locked", 0).unwrap(),
+            SensorValue::Boolean(true)
+        );
+        usrp.set_rx_sensor("rssi", SensorValue::Real(-40.0), 1)
+            .unwrap();
+        assert_eq!(
+            usrp.get_rx_sensor_names(1).unwrap(),
+            vec!["lo_locked".to_owned(), "rssi".to_owned()]
+        );
+        assert!(matches!(usrp.get_tx_sensor("rssi", 0), Err(Error::Key)));
+    }
+
+    #[test]
+    fn receive_tone() {
+        let mut usrp = SimUsrp::new();
+        usrp.set_rx_sample_rate(1e6, 0).unwrap();
+        usrp.set_rx_frequency(&TuneRequest::with_frequency(100e6), 0)
+            .unwrap();
+        usrp.add_rx_signal(
+            0,
+            Signal::Tone {
+                frequency: 100.25e6,
+                amplitude: 0.5,
+            },
+        )
+        .unwrap();
+        let rate = usrp.get_rx_sample_rate(0).unwrap();
+
+        let mut streamer = usrp
+            .get_rx_stream(&StreamArgs::<Complex32>::new(WireFormat::Sc16))
+            .unwrap();
+        streamer
+            .send_command(&receive_command(64, StreamTime::Now))
+            .unwrap();
+        let mut buffer = vec![Complex32::default(); 100];
+        let metadata = streamer.receive(&mut [&mut buffer], 0.1, false).unwrap();
+        assert_eq!(metadata.samples(), 64);
+        assert!(metadata.start_of_burst() && metadata.end_of_burst());
+        assert_eq!(metadata.time_spec(), Some(TimeSpec::new(0, 0.0)));
+        // A 250 kHz tone at 1 MS/s advances by a quarter cycle per sample
+        assert!(rate == 1e6);
+        assert!((buffer[1] - Complex32::new(0.0, 0.5)).norm() < 1e-6);
+        assert!((buffer[2] - Complex32::new(-0.5, 0.0)).norm() < 1e-6);
+
+        // The stream has finished
+        let metadata = streamer.receive(&mut [&mut buffer], 0.1, false).unwrap();
+        assert_eq!(metadata.samples(), 0);
+        assert!(matches!(
+            metadata.last_error().unwrap().kind(),
+            ReceiveErrorKind::Timeout
+        ));
+        drop(streamer);
+        assert_eq!(
+            usrp.get_current_time(0).unwrap(),
+            TimeSpec::from_ticks(64, rate)
+        );
+    }
+
+    #[test]
+    fn timed_receive_and_late_command() {
+        let mut usrp = SimUsrp::new();
+        usrp.set_rx_sample_rate(1e6, 0).unwrap();
+        let mut streamer = usrp
+            .get_rx_stream(&StreamArgs::<Complex<i16>>::new(WireFormat::Sc16))
+            .unwrap();
+        let mut buffer = vec![Complex::default(); 10];
+
+        streamer
+            .send_command(&receive_command(10, StreamTime::At(TimeSpec::new(2, 0.5))))
+            .unwrap();
+        let metadata = streamer.receive(&mut [&mut buffer], 0.1, false).unwrap();
+        assert_eq!(metadata.time_spec(), Some(TimeSpec::new(2, 0.5)));
+
+        streamer
+            .send_command(&receive_command(10, StreamTime::At(TimeSpec::new(1, 0.0))))
+            .unwrap();
+        let metadata = streamer.receive(&mut [&mut buffer], 0.1, false).unwrap();
+        assert_eq!(metadata.samples(), 0);
+        assert!(matches!(
+            metadata.last_error().unwrap().kind(),
+            ReceiveErrorKind::LateCommand
+        ));
+    }
+
+    #[test]
+    fn injected_overflow_skips_samples() {
+        let mut usrp = SimUsrp::new();
+        usrp.set_rx_sample_rate(1e6, 0).unwrap();
+        let control = usrp.clone();
+        let mut streamer = usrp
+            .get_rx_stream(&StreamArgs::<Complex32>::new(WireFormat::Sc16))
+            .unwrap();
+        streamer
+            .send_command(&StreamCommand {
+                command_type: StreamCommandType::StartContinuous,
+                time: StreamTime::Now,
+            })
+            .unwrap();
+        let mut buffer = vec![Complex32::default(); 100];
+        streamer.receive(&mut [&mut buffer], 0.1, false).unwrap();
+
+        control.inject_rx_error(ReceiveErrorKind::Overflow);
+        let metadata = streamer.receive(&mut [&mut buffer], 0.1, false).unwrap();
+        assert_eq!(metadata.samples(), 0);
+        assert!(matches!(
+            metadata.last_error().unwrap().kind(),
+            ReceiveErrorKind::Overflow
+        ));
+
+        let metadata = streamer.receive(&mut [&mut buffer], 0.1, false).unwrap();
+        assert_eq!(metadata.samples(), 100);
+        assert_eq!(metadata.time_spec(), Some(TimeSpec::from_ticks(200, 1e6)));
+    }
+
+    #[test]
+    fn loopback_and_async_messages() {
+        let mut usrp = SimUsrp::new();
+        usrp.add_rx_signal(0, Signal::Loopback { channel: 1 })
+            .unwrap();
+        let samples: Vec<Complex32> = (0..8)
+            .map(|i| Complex32::new(i as f32 / 10.0, 0.0))
+            .collect();
+        {
+            let mut streamer = usrp
+                .get_tx_stream(&StreamArgs::<Complex32>::builder().channels(vec![1]).build())
+                .unwrap();
+            let mut metadata = TransmitMetadata::with_flags(true, true, None);
+            let sent = streamer.send(&mut [&samples], &mut metadata, 0.1).unwrap();
+            assert_eq!(sent, 8);
+            let message = streamer.recv_async_msg(0.1).unwrap().unwrap();
+            assert_eq!(message.event_code(), AsyncEventCode::BurstAck);
+            assert_eq!(message.channel(), 1);
+            assert!(streamer.recv_async_msg(0.1).unwrap().is_none());
+        }
+        assert_eq!(usrp.transmitted_samples(1).unwrap(), 8);
+
+        let mut streamer = usrp
+            .get_rx_stream(&StreamArgs::<Complex32>::new(WireFormat::Sc16))
+            .unwrap();
+        streamer
+            .send_command(&receive_command(8, StreamTime::Now))
+            .unwrap();
+        let mut buffer = vec![Complex32::default(); 8];
+        streamer.receive(&mut [&mut buffer], 0.1, false).unwrap();
+        assert_eq!(buffer, samples);
+    }
+
+    #[test]
+    fn loopback_to_two_channels() {
+        let mut usrp = SimUsrp::new();
+        for channel in 0..2 {
+            usrp.add_rx_signal(channel, Signal::Loopback { channel: 1 })
+                .unwrap();
+        }
+        let samples: Vec<Complex32> = (0..8)
+            .map(|i| Complex32::new(0.0, i as f32 / 10.0))
+            .collect();
+        {
+            let mut streamer = usrp
+                .get_tx_stream(&StreamArgs::<Complex32>::builder().channels(vec![1]).build())
+                .unwrap();
+            let mut metadata = TransmitMetadata::default();
+            streamer.send(&mut [&samples], &mut metadata, 0.1).unwrap();
+        }
+
+        // Each receive channel gets its own copy of the transmitted samples
+        let mut streamer = usrp
+            .get_rx_stream(
+                &StreamArgs::<Complex32>::builder()
+                    .channels(vec![0, 1])
+                    .build(),
+            )
+            .unwrap();
+        streamer
+            .send_command(&receive_command(8, StreamTime::Now))
+            .unwrap();
+        let mut buffer0 = vec![Complex32::default(); 8];
+        let mut buffer1 = vec![Complex32::default(); 8];
+        let metadata = streamer
+            .receive(&mut [&mut buffer0, &mut buffer1], 0.1, false)
+            .unwrap();
+        assert_eq!(metadata.samples(), 8);
+        assert_eq!(buffer0, samples);
+        assert_eq!(buffer1, samples);
+    }
+}
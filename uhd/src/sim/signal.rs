@@ -0,0 +1,50 @@
+use std::f64::consts::PI;
+
+use num_complex::Complex64;
+
+use crate::TimeSpec;
+
+/// A synthetic signal that a simulated receive channel produces
+///
+/// The signals of a channel are added together and then scaled by the receive gain
+/// (`10^(gain / 20)`). The result is clipped to [-1, 1], like the output of an ADC.
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq)]
+pub enum Signal {
+    /// A complex sinusoid at an RF frequency in hertz
+    ///
+    /// The received tone is offset from 0 Hz by the difference between its frequency and the
+    /// center frequency of the channel.
+    Tone { frequency: f64, amplitude: f64 },
+    /// Complex Gaussian noise with an RMS amplitude
+    Noise { amplitude: f64 },
+    /// The samples that were transmitted on a transmit channel
+    ///
+    /// Samples transmitted after this signal is added are queued and returned in order. Each
+    /// receive channel with this signal gets its own copy of the samples. When the queue is
+    /// empty, this signal is zero.
+    Loopback { channel: usize },
+}
+
+/// Returns the value of a tone at a device time
+pub(crate) fn tone(frequency: f64, amplitude: f64, time: TimeSpec) -> Complex64 {
+    // Calculate the phase in cycles separately for whole and fractional seconds to keep it
+    // accurate for large times
+    let cycles = (frequency * time.seconds as f64).fract() + frequency * time.fraction;
+    Complex64::from_polar(amplitude, 2.0 * PI * cycles.fract())
+}
+
+#[cfg(test)]
+mod test {
+    use super::tone;
+    use crate::TimeSpec;
+
+    #[test]
+    fn tone_phase() {
+        let at_zero = tone(1e3, 0.5, TimeSpec::new(0, 0.0));
+        assert!((at_zero.re - 0.5).abs() < 1e-12 && at_zero.im.abs() < 1e-12);
+        // A quarter cycle later
+        let later = tone(1e3, 0.5, TimeSpec::new(1000, 0.25e-3));
+        assert!(later.re.abs() < 1e-9 && (later.im - 0.5).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,237 @@
+use std::convert::TryFrom;
+use std::marker::PhantomData;
+use std::sync::{Arc, Mutex};
+
+use super::{lock, SimUsrp, State};
+use crate::device::{ReceiveStream, TransmitStream};
+use crate::error::Error;
+use crate::receiver::metadata::ReceiveMetadataValues;
+use crate::stream::{Sample, StreamCommand, StreamCommandType, StreamTime};
+use crate::utils::check_equal_buffer_lengths;
+use crate::{
+    AsyncEventCode, AsyncMetadata, ReceiveErrorKind, ReceiveMetadata, TimeSpec, TransmitMetadata,
+};
+
+/// A stream that receives samples from a simulated USRP
+///
+/// Receiving does not wait: each call to `receive` returns immediately with the samples that
+/// the device would have produced, and advances the device time. If streaming has not been
+/// started (or has finished), `receive` reports a timeout error.
+#[derive(Debug)]
+pub struct SimReceiveStreamer<'device, I> {
+    state: Arc<Mutex<State>>,
+    channels: Vec<usize>,
+    mode: ReceiveMode,
+    /// The time when streaming should start, if streaming has not started yet
+    start_time: Option<TimeSpec>,
+    /// True if the next samples are the first samples of a burst
+    start_of_burst: bool,
+    device: PhantomData<&'device SimUsrp>,
+    item_phantom: PhantomData<I>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum ReceiveMode {
+    Stopped,
+    Continuous,
+    Count { remaining: u64, more: bool },
+}
+
+impl<I> SimReceiveStreamer<'_, I> {
+    pub(crate) fn new(state: Arc<Mutex<State>>, channels: Vec<usize>) -> Self {
+        SimReceiveStreamer {
+            state,
+            channels,
+            mode: ReceiveMode::Stopped,
+            start_time: None,
+            start_of_burst: false,
+            device: PhantomData,
+            item_phantom: PhantomData,
+        }
+    }
+}
+
+impl<I> ReceiveStream<I> for SimReceiveStreamer<'_, I>
+where
+    I: Sample,
+{
+    fn num_channels(&self) -> usize {
+        self.channels.len()
+    }
+
+    fn send_command(&mut self, command: &StreamCommand) -> Result<(), Error> {
+        self.mode = match command.command_type {
+            StreamCommandType::StartContinuous => ReceiveMode::Continuous,
+            StreamCommandType::StopContinuous => ReceiveMode::Stopped,
+            StreamCommandType::CountAndDone(remaining) => ReceiveMode::Count {
+                remaining,
+                more: false,
+            },
+            StreamCommandType::CountAndMore(remaining) => ReceiveMode::Count {
+                remaining,
+                more: true,
+            },
+        };
+        self.start_time = match &command.time {
+            StreamTime::Now => None,
+            StreamTime::Later(duration) => Some(TimeSpec::from(*duration)),
+            StreamTime::At(time) => Some(*time),
+        };
+        self.start_of_burst = true;
+        Ok(())
+    }
+
+    fn receive(
+        &mut self,
+        buffers: &mut [&mut [I]],
+        _timeout: f64,
+        one_packet: bool,
+    ) -> Result<ReceiveMetadata, Error> {
+        assert_eq!(
+            buffers.len(),
+            self.channels.len(),
+            "Number of buffers is not equal to this streamer's number of channels"
+        );
+        let buffer_length = check_equal_buffer_lengths(buffers);
+        let mut state = lock(&self.state);
+        let rate = state.rx_channel(self.channels[0])?.sample_rate;
+
+        if let Some(kind) = state.rx_errors.pop_front() {
+            if matches!(
+                kind,
+                ReceiveErrorKind::Overflow | ReceiveErrorKind::OutOfSequence
+            ) {
+                // The samples that did not fit in the buffers are lost
+                state.time += TimeSpec::from_ticks(buffer_length as i64, rate);
+            }
+            return Ok(error_metadata(kind));
+        }
+
+        let remaining = match self.mode {
+            ReceiveMode::Stopped => return Ok(error_metadata(ReceiveErrorKind::Timeout)),
+            ReceiveMode::Continuous => u64::MAX,
+            ReceiveMode::Count { remaining, .. } => remaining,
+        };
+        if let Some(start_time) = self.start_time.take() {
+            if start_time < state.time {
+                self.mode = ReceiveMode::Stopped;
+                return Ok(error_metadata(ReceiveErrorKind::LateCommand));
+            }
+            state.time = start_time;
+        }
+
+        let mut samples = buffer_length.min(usize::try_from(remaining).unwrap_or(usize::MAX));
+        if one_packet {
+            samples = samples.min(state.config.samples_per_packet);
+        }
+        let start = state.time;
+        for (buffer, &channel) in buffers.iter_mut().zip(&self.channels) {
+            for (i, item) in buffer[..samples].iter_mut().enumerate() {
+                let time = start + TimeSpec::from_ticks(i as i64, rate);
+                *item = I::from_fc64(state.rx_sample(channel, time));
+            }
+        }
+        state.time = start + TimeSpec::from_ticks(samples as i64, rate);
+
+        let mut end_of_burst = false;
+        if let ReceiveMode::Count { remaining, more } = self.mode {
+            let remaining = remaining - samples as u64;
+            if remaining == 0 {
+                self.mode = ReceiveMode::Stopped;
+                end_of_burst = !more;
+            } else {
+                self.mode = ReceiveMode::Count { remaining, more };
+            }
+        }
+        let values = ReceiveMetadataValues {
+            time_spec: Some(start),
+            start_of_burst: self.start_of_burst,
+            end_of_burst,
+            ..ReceiveMetadataValues::default()
+        };
+        self.start_of_burst = false;
+        Ok(ReceiveMetadata::from_values(values, samples))
+    }
+}
+
+fn error_metadata(kind: ReceiveErrorKind) -> ReceiveMetadata {
+    ReceiveMetadata::from_values(
+        ReceiveMetadataValues {
+            error: Some(kind),
+            ..ReceiveMetadataValues::default()
+        },
+        0,
+    )
+}
+
+/// A stream that transmits samples to a simulated USRP
+///
+/// Transmitted samples are counted and queued for [`Signal::Loopback`](super::Signal::Loopback)
+/// receive signals. Asynchronous messages report the end of each burst, packets with times in
+/// the past, and injected events.
+#[derive(Debug)]
+pub struct SimTransmitStreamer<'device, I> {
+    state: Arc<Mutex<State>>,
+    channels: Vec<usize>,
+    device: PhantomData<&'device SimUsrp>,
+    item_phantom: PhantomData<I>,
+}
+
+impl<I> SimTransmitStreamer<'_, I> {
+    pub(crate) fn new(state: Arc<Mutex<State>>, channels: Vec<usize>) -> Self {
+        SimTransmitStreamer {
+            state,
+            channels,
+            device: PhantomData,
+            item_phantom: PhantomData,
+        }
+    }
+}
+
+impl<I> TransmitStream<I> for SimTransmitStreamer<'_, I>
+where
+    I: Sample,
+{
+    fn num_channels(&self) -> usize {
+        self.channels.len()
+    }
+
+    fn send(
+        &mut self,
+        buffers: &mut [&[I]],
+        metadata: &mut TransmitMetadata,
+        _timeout: f64,
+    ) -> Result<usize, Error> {
+        assert_eq!(
+            buffers.len(),
+            self.channels.len(),
+            "Number of buffers is not equal to this streamer's number of channels"
+        );
+        let buffer_length = check_equal_buffer_lengths(buffers);
+        let mut state = lock(&self.state);
+
+        let late = matches!(metadata.time_spec(), Some(time) if time < state.time);
+        for (buffer, &channel) in buffers.iter().zip(&self.channels) {
+            state.tx_channel_mut(channel)?.transmitted += buffer_length as u64;
+            if late {
+                state.push_async_message(channel, AsyncEventCode::TimeError);
+            } else {
+                state.loop_back(channel, buffer.iter().map(Sample::to_fc64));
+            }
+        }
+        while let Some(event_code) = state.tx_events.pop_front() {
+            state.push_async_message(self.channels[0], event_code);
+        }
+        if metadata.end_of_burst() {
+            for &channel in &self.channels {
+                state.push_async_message(channel, AsyncEventCode::BurstAck);
+            }
+        }
+        metadata.set_samples(buffer_length);
+        Ok(buffer_length)
+    }
+
+    fn recv_async_msg(&mut self, _timeout: f64) -> Result<Option<AsyncMetadata>, Error> {
+        Ok(lock(&self.state).async_messages.pop_front())
+    }
+}
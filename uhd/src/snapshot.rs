@@ -0,0 +1,408 @@
+//! Snapshots of the settings of a device
+//!
+//! [`Usrp::snapshot`] reads the settings that can be read back from a device, and
+//! [`Usrp::restore`] applies them again, for example after a test has changed them. With the
+//! `serde` feature, snapshots can be saved and loaded.
+
+use std::collections::BTreeMap;
+
+use crate::error::{Error, Result};
+use crate::settings::{ConfigReport, ConfigValue};
+use crate::usrp::Usrp;
+use crate::{ChannelDirection, SubdevSpec, TuneRequest, TuneRequestPolicy};
+
+/// The settings of a device
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DeviceSnapshot {
+    pub motherboards: Vec<MotherboardSnapshot>,
+    /// Receive channels, in order of channel index
+    pub rx_channels: Vec<ChannelSnapshot>,
+    /// Transmit channels, in order of channel index
+    pub tx_channels: Vec<ChannelSnapshot>,
+}
+
+/// The settings of a motherboard
+///
+/// Settings that could not be read are `None`.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MotherboardSnapshot {
+    pub clock_source: Option<String>,
+    pub time_source: Option<String>,
+    pub master_clock_rate: Option<f64>,
+    /// The receive subdevice specification, like `A:A A:B`
+    pub rx_subdev_spec: Option<String>,
+    /// The transmit subdevice specification
+    pub tx_subdev_spec: Option<String>,
+}
+
+/// The settings of a receive or transmit channel
+///
+/// Settings that could not be read are `None` or missing.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ChannelSnapshot {
+    pub sample_rate: Option<f64>,
+    /// The center frequency, including the DSP offset
+    pub frequency: Option<f64>,
+    /// The RF frontend frequency, read as the frequency of all local oscillators
+    pub rf_frequency: Option<f64>,
+    /// The gain of each gain element, decibels
+    pub gains: BTreeMap<String, f64>,
+    pub antenna: Option<String>,
+    pub bandwidth: Option<f64>,
+    /// The selected source of each local oscillator
+    pub lo_sources: BTreeMap<String, String>,
+    /// Whether each local oscillator is exported
+    pub lo_exports: BTreeMap<String, bool>,
+    /// The frequency of each local oscillator
+    pub lo_frequencies: BTreeMap<String, f64>,
+}
+
+impl DeviceSnapshot {
+    /// Reads the settings of a device
+    ///
+    /// An error is returned only if the numbers of motherboards and channels can't be read.
+    pub(crate) fn from_usrp(usrp: &Usrp) -> Result<Self> {
+        let motherboards = (0..usrp.get_num_motherboards()?)
+            .map(|mboard| MotherboardSnapshot {
+                clock_source: usrp.get_clock_source(mboard).ok(),
+                time_source: usrp.get_time_source(mboard).ok(),
+                master_clock_rate: usrp.get_master_clock_rate(mboard).ok(),
+                rx_subdev_spec: usrp
+                    .get_rx_subdev_spec(mboard)
+                    .ok()
+                    .map(|spec| spec.to_string()),
+                tx_subdev_spec: usrp
+                    .get_tx_subdev_spec(mboard)
+                    .ok()
+                    .map(|spec| spec.to_string()),
+            })
+            .collect();
+        let rx_channels = (0..usrp.get_num_rx_channels()?)
+            .map(|channel| ChannelSnapshot {
+                sample_rate: usrp.get_rx_sample_rate(channel).ok(),
+                frequency: usrp.get_rx_frequency(channel).ok(),
+                rf_frequency: usrp.get_rx_lo_frequency(channel, Usrp::ALL_LOS).ok(),
+                gains: read_map(usrp.get_rx_gain_names(channel), |name| {
+                    usrp.get_rx_gain(channel, name)
+                }),
+                antenna: usrp.get_rx_antenna(channel).ok(),
+                bandwidth: usrp.get_rx_bandwidth(channel).ok(),
+                lo_sources: read_map(usrp.get_rx_lo_names(channel), |name| {
+                    usrp.get_rx_lo_source(channel, name)
+                }),
+                lo_exports: read_map(usrp.get_rx_lo_names(channel), |name| {
+                    usrp.get_rx_lo_export_enabled(channel, name)
+                }),
+                lo_frequencies: read_map(usrp.get_rx_lo_names(channel), |name| {
+                    usrp.get_rx_lo_frequency(channel, name)
+                }),
+            })
+            .collect();
+        let tx_channels = (0..usrp.get_num_tx_channels()?)
+            .map(|channel| ChannelSnapshot {
+                sample_rate: usrp.get_tx_sample_rate(channel).ok(),
+                frequency: usrp.get_tx_frequency(channel).ok(),
+                rf_frequency: usrp.get_tx_lo_frequency(channel, Usrp::ALL_LOS).ok(),
+                gains: read_map(usrp.get_tx_gain_names(channel), |name| {
+                    usrp.get_tx_gain(channel, name)
+                }),
+                antenna: usrp.get_tx_antenna(channel).ok(),
+                bandwidth: usrp.get_tx_bandwidth(channel).ok(),
+                lo_sources: read_map(usrp.get_tx_lo_names(channel), |name| {
+                    usrp.get_tx_lo_source(channel, name)
+                }),
+                lo_exports: read_map(usrp.get_tx_lo_names(channel), |name| {
+                    usrp.get_tx_lo_export_enabled(channel, name)
+                }),
+                lo_frequencies: read_map(usrp.get_tx_lo_names(channel), |name| {
+                    usrp.get_tx_lo_frequency(channel, name)
+                }),
+            })
+            .collect();
+        Ok(DeviceSnapshot {
+            motherboards,
+            rx_channels,
+            tx_channels,
+        })
+    }
+
+    /// Applies the settings in this snapshot to a device
+    ///
+    /// Motherboard settings are applied first. Channel settings are applied in the order sample
+    /// rate, antenna, bandwidth, local oscillator sources and exports, frequency, local
+    /// oscillator frequencies, and gains. A setting that the device rejects is recorded in the
+    /// report, and the other settings are still applied.
+    ///
+    /// When the RF frequency of a channel is known, the channel is tuned with the RF frontend at
+    /// that frequency and the DSP covering the rest, so that the split between them is the same
+    /// as when the snapshot was taken.
+    ///
+    /// An error is returned without changing anything if the device has fewer motherboards than
+    /// the snapshot. If the device has fewer channels than the snapshot after the motherboard
+    /// settings (including subdevice specifications) are restored, an error is returned before
+    /// any channel settings are changed.
+    pub(crate) fn restore(&self, usrp: &mut Usrp) -> Result<ConfigReport> {
+        check_count(
+            "motherboards",
+            self.motherboards.len(),
+            usrp.get_num_motherboards()?,
+        )?;
+
+        let mut report = ConfigReport::default();
+        for (mboard, snapshot) in self.motherboards.iter().enumerate() {
+            let prefix = format!("motherboard {}", mboard);
+            if let Some(spec) = &snapshot.rx_subdev_spec {
+                let result = spec
+                    .parse::<SubdevSpec>()
+                    .and_then(|spec| usrp.set_rx_subdev_spec(&spec, mboard));
+                let actual = usrp.get_rx_subdev_spec(mboard).map(|spec| spec.to_string());
+                report.push_text(&prefix, "RX subdevice specification", spec, result, actual);
+            }
+            if let Some(spec) = &snapshot.tx_subdev_spec {
+                let result = spec
+                    .parse::<SubdevSpec>()
+                    .and_then(|spec| usrp.set_tx_subdev_spec(&spec, mboard));
+                let actual = usrp.get_tx_subdev_spec(mboard).map(|spec| spec.to_string());
+                report.push_text(&prefix, "TX subdevice specification", spec, result, actual);
+            }
+            if let Some(source) = &snapshot.clock_source {
+                let result = usrp.set_clock_source(source, mboard);
+                let actual = usrp.get_clock_source(mboard);
+                report.push_text(&prefix, "clock source", source, result, actual);
+            }
+            if let Some(source) = &snapshot.time_source {
+                let result = usrp.set_time_source(source, mboard);
+                let actual = usrp.get_time_source(mboard);
+                report.push_text(&prefix, "time source", source, result, actual);
+            }
+            if let Some(rate) = snapshot.master_clock_rate {
+                let result = usrp.set_master_clock_rate(rate, mboard).map(|_| ());
+                let actual = usrp.get_master_clock_rate(mboard);
+                report.push_number(&prefix, "master clock rate", rate, result, actual);
+            }
+        }
+
+        // The subdevice specifications set the numbers of channels
+        check_count(
+            "RX channels",
+            self.rx_channels.len(),
+            usrp.get_num_rx_channels()?,
+        )?;
+        check_count(
+            "TX channels",
+            self.tx_channels.len(),
+            usrp.get_num_tx_channels()?,
+        )?;
+        for (channel, snapshot) in self.rx_channels.iter().enumerate() {
+            let prefix = format!("{} {}", ChannelDirection::Receive, channel);
+            let mut rx = usrp.rx_channel(channel)?;
+            if let Some(rate) = snapshot.sample_rate {
+                let result = rx.set_sample_rate(rate);
+                report.push_number(&prefix, "sample rate", rate, result, rx.get_sample_rate());
+            }
+            if let Some(antenna) = &snapshot.antenna {
+                let result = rx.set_antenna(antenna);
+                report.push_text(&prefix, "antenna", antenna, result, rx.get_antenna());
+            }
+            if let Some(bandwidth) = snapshot.bandwidth {
+                let result = rx.set_bandwidth(bandwidth);
+                let actual = rx.get_bandwidth();
+                report.push_number(&prefix, "bandwidth", bandwidth, result, actual);
+            }
+            for (name, source) in &snapshot.lo_sources {
+                let result = rx.set_lo_source(source, name);
+                let setting = format!("LO {} source", name);
+                let actual = rx.get_lo_source(name);
+                report.push_text(&prefix, &setting, source, result, actual);
+            }
+            for (name, &enabled) in &snapshot.lo_exports {
+                let result = rx.set_lo_export_enabled(enabled, name);
+                report.push_result(
+                    format!("{} LO {} export", prefix, name),
+                    ConfigValue::Bool(enabled),
+                    result,
+                    rx.get_lo_export_enabled(name).ok().map(ConfigValue::Bool),
+                );
+            }
+            if let Some(frequency) = snapshot.frequency {
+                let request = frequency_request(frequency, snapshot.rf_frequency);
+                let result = rx.set_frequency(&request).map(|_| ());
+                let actual = rx.get_frequency();
+                report.push_number(&prefix, "frequency", frequency, result, actual);
+            }
+            for (name, &frequency) in &snapshot.lo_frequencies {
+                let result = rx.set_lo_frequency(frequency, name).map(|_| ());
+                let setting = format!("LO {} frequency", name);
+                let actual = rx.get_lo_frequency(name);
+                report.push_number(&prefix, &setting, frequency, result, actual);
+            }
+            for (name, &gain) in &snapshot.gains {
+                let result = rx.set_gain(gain, name);
+                let setting = format!("gain {}", name);
+                let actual = rx.get_gain(name);
+                report.push_number(&prefix, &setting, gain, result, actual);
+            }
+        }
+        for (channel, snapshot) in self.tx_channels.iter().enumerate() {
+            let prefix = format!("{} {}", ChannelDirection::Transmit, channel);
+            let mut tx = usrp.tx_channel(channel)?;
+            if let Some(rate) = snapshot.sample_rate {
+                let result = tx.set_sample_rate(rate);
+                report.push_number(&prefix, "sample rate", rate, result, tx.get_sample_rate());
+            }
+            if let Some(antenna) = &snapshot.antenna {
+                let result = tx.set_antenna(antenna);
+                report.push_text(&prefix, "antenna", antenna, result, tx.get_antenna());
+            }
+            if let Some(bandwidth) = snapshot.bandwidth {
+                let result = tx.set_bandwidth(bandwidth);
+                let actual = tx.get_bandwidth();
+                report.push_number(&prefix, "bandwidth", bandwidth, result, actual);
+            }
+            for (name, source) in &snapshot.lo_sources {
+                let result = tx.set_lo_source(source, name);
+                let setting = format!("LO {} source", name);
+                let actual = tx.get_lo_source(name);
+                report.push_text(&prefix, &setting, source, result, actual);
+            }
+            for (name, &enabled) in &snapshot.lo_exports {
+                let result = tx.set_lo_export_enabled(enabled, name);
+                report.push_result(
+                    format!("{} LO {} export", prefix, name),
+                    ConfigValue::Bool(enabled),
+                    result,
+                    tx.get_lo_export_enabled(name).ok().map(ConfigValue::Bool),
+                );
+            }
+            if let Some(frequency) = snapshot.frequency {
+                let request = frequency_request(frequency, snapshot.rf_frequency);
+                let result = tx.set_frequency(&request).map(|_| ());
+                let actual = tx.get_frequency();
+                report.push_number(&prefix, "frequency", frequency, result, actual);
+            }
+            for (name, &frequency) in &snapshot.lo_frequencies {
+                let result = tx.set_lo_frequency(frequency, name).map(|_| ());
+                let setting = format!("LO {} frequency", name);
+                let actual = tx.get_lo_frequency(name);
+                report.push_number(&prefix, &setting, frequency, result, actual);
+            }
+            for (name, &gain) in &snapshot.gains {
+                let result = tx.set_gain(gain, name);
+                let setting = format!("gain {}", name);
+                let actual = tx.get_gain(name);
+                report.push_number(&prefix, &setting, gain, result, actual);
+            }
+        }
+        Ok(report)
+    }
+}
+
+/// Returns a request that tunes to a frequency, with the RF frontend at `rf_frequency` if it is
+/// known
+fn frequency_request(frequency: f64, rf_frequency: Option<f64>) -> TuneRequest {
+    let mut request = TuneRequest::with_frequency(frequency);
+    if let Some(rf_frequency) = rf_frequency {
+        request.set_rf_policy(TuneRequestPolicy::Manual(rf_frequency));
+    }
+    request
+}
+
+/// Reads a value for each name, leaving out values that can't be read
+fn read_map<T, F>(names: Result<Vec<String>>, mut read: F) -> BTreeMap<String, T>
+where
+    F: FnMut(&str) -> Result<T>,
+{
+    names
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|name| read(&name).ok().map(|value| (name, value)))
+        .collect()
+}
+
+/// Returns an error if a device has fewer items than a snapshot
+fn check_count(items: &'static str, snapshot: usize, device: usize) -> Result<()> {
+    if snapshot <= device {
+        Ok(())
+    } else {
+        Err(Error::SnapshotMismatch {
+            items,
+            snapshot,
+            device,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{check_count, frequency_request, read_map};
+    use crate::Error;
+    use crate::TuneRequestPolicy;
+
+    #[test]
+    fn partial_reads() {
+        let names = Ok(vec!["PGA".to_string(), "LNA".to_string()]);
+        let gains = read_map(names, |name| match name {
+            "PGA" => Ok(10.0),
+            _ => Err(Error::Key),
+        });
+        assert_eq!(gains.len(), 1);
+        assert_eq!(gains["PGA"], 10.0);
+        assert!(read_map::<f64, _>(Err(Error::NotImplemented), |_| Ok(0.0)).is_empty());
+    }
+
+    #[test]
+    fn frequency_requests() {
+        let request = frequency_request(915e6, None);
+        assert_eq!(request.target_frequency, 915e6);
+        assert_eq!(request.rf, TuneRequestPolicy::Auto);
+        assert_eq!(request.dsp, TuneRequestPolicy::Auto);
+        let request = frequency_request(915e6, Some(917e6));
+        assert_eq!(request.rf, TuneRequestPolicy::Manual(917e6));
+        assert_eq!(request.dsp, TuneRequestPolicy::Auto);
+    }
+
+    #[test]
+    fn channel_counts() {
+        assert!(check_count("RX channels", 1, 2).is_ok());
+        let error = check_count("RX channels", 2, 1).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "The snapshot has 2 RX channels, but the device has 1"
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn json_round_trip() {
+        use super::{ChannelSnapshot, DeviceSnapshot, MotherboardSnapshot};
+
+        let snapshot = DeviceSnapshot {
+            motherboards: vec![MotherboardSnapshot {
+                clock_source: Some("internal".to_string()),
+                master_clock_rate: Some(30.72e6),
+                rx_subdev_spec: Some("A:A A:B".to_string()),
+                ..MotherboardSnapshot::default()
+            }],
+            rx_channels: vec![ChannelSnapshot {
+                frequency: Some(915e6),
+                rf_frequency: Some(917e6),
+                gains: vec![("PGA".to_string(), 20.0)].into_iter().collect(),
+                lo_sources: vec![("lo1".to_string(), "internal".to_string())]
+                    .into_iter()
+                    .collect(),
+                lo_exports: vec![("lo1".to_string(), true)].into_iter().collect(),
+                lo_frequencies: vec![("lo1".to_string(), 917e6)].into_iter().collect(),
+                ..ChannelSnapshot::default()
+            }],
+            tx_channels: vec![],
+        };
+        let json = serde_json::to_string(&snapshot).unwrap();
+        assert_eq!(
+            serde_json::from_str::<DeviceSnapshot>(&json).unwrap(),
+            snapshot
+        );
+    }
+}
@@ -1,7 +1,93 @@
 use num_complex::{Complex, Complex32, Complex64};
 use std::convert::{TryFrom, TryInto};
 use std::ffi::{CString, NulError};
+use std::fmt;
 use std::marker::PhantomData;
+use std::str::FromStr;
+
+use crate::device::check_channel;
+use crate::error::{Error, Result};
+use crate::{ChannelDirection, TimeSpec};
+
+/// A format of samples sent between the host and the device
+///
+/// A wire format can be parsed from its UHD name:
+/// ```
+/// use uhd::WireFormat;
+/// assert_eq!("sc8".parse::<WireFormat>().unwrap(), WireFormat::Sc8);
+/// assert!("s16".parse::<WireFormat>().is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum WireFormat {
+    /// Complex 16-bit integers (`sc16`)
+    #[default]
+    Sc16,
+    /// Complex 12-bit integers (`sc12`)
+    Sc12,
+    /// Complex 8-bit integers (`sc8`)
+    Sc8,
+    /// Complex 32-bit floating-point numbers (`fc32`)
+    Fc32,
+    /// Another format, passed to UHD without checking
+    Other(String),
+}
+
+impl WireFormat {
+    /// Returns the name that UHD uses for this format
+    pub fn as_str(&self) -> &str {
+        match self {
+            WireFormat::Sc16 => "sc16",
+            WireFormat::Sc12 => "sc12",
+            WireFormat::Sc8 => "sc8",
+            WireFormat::Fc32 => "fc32",
+            WireFormat::Other(name) => name,
+        }
+    }
+}
+
+impl fmt::Display for WireFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for WireFormat {
+    type Err = Error;
+
+    /// Parses one of the formats that have their own variants
+    ///
+    /// Other formats can be used by creating a `WireFormat::Other` directly.
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim() {
+            "sc16" => Ok(WireFormat::Sc16),
+            "sc12" => Ok(WireFormat::Sc12),
+            "sc8" => Ok(WireFormat::Sc8),
+            "fc32" => Ok(WireFormat::Fc32),
+            _ => Err(Error::InvalidStreamArgs(format!(
+                "Unknown wire format \"{}\" (expected sc16, sc12, sc8, or fc32)",
+                s
+            ))),
+        }
+    }
+}
+
+/// What a transmit stream does after an underflow
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnderflowPolicy {
+    /// Drop samples until the start of the next packet (`next_packet`)
+    NextPacket,
+    /// Drop samples until the start of the next burst (`next_burst`)
+    NextBurst,
+}
+
+impl UnderflowPolicy {
+    fn as_str(&self) -> &'static str {
+        match self {
+            UnderflowPolicy::NextPacket => "next_packet",
+            UnderflowPolicy::NextBurst => "next_burst",
+        }
+    }
+}
 
 /// Arguments used to create a stream
 ///
@@ -9,15 +95,22 @@ use std::marker::PhantomData;
 ///
 /// The default stream arguments use wire format `sc16` and host format `fc32`:
 /// ```
-/// use uhd::StreamArgs;
+/// use uhd::{StreamArgs, WireFormat};
 /// use num_complex::Complex32;
-/// let args = StreamArgs::<Complex32>::new("sc16");
+/// let args = StreamArgs::<Complex32>::new(WireFormat::Sc16);
 /// ```
 ///
+/// Stream arguments are checked against the device when a stream is opened. An unavailable
+/// channel causes an `Error::NoSuchChannel`, and other problems cause an
+/// `Error::InvalidStreamArgs`.
 #[derive(Debug, Clone)]
 pub struct StreamArgs<I> {
     host_format: PhantomData<I>,
-    wire_format: String,
+    wire_format: WireFormat,
+    samples_per_packet: Option<usize>,
+    fullscale: Option<f64>,
+    peak: Option<f64>,
+    underflow_policy: Option<UnderflowPolicy>,
     args: String,
     channels: Vec<usize>,
 }
@@ -25,16 +118,82 @@ pub struct StreamArgs<I> {
 impl<I> StreamArgs<I> {
     /// Creates stream arguments with the provided wire format, no additional
     /// arguments, and channel 0
-    pub fn new<S>(wire_format: S) -> Self
-    where
-        S: Into<String>,
-    {
+    pub fn new(wire_format: WireFormat) -> Self {
         StreamArgs {
-            wire_format: wire_format.into(),
+            wire_format,
             ..StreamArgs::default()
         }
     }
 
+    /// Returns the wire format
+    pub fn wire_format(&self) -> &WireFormat {
+        &self.wire_format
+    }
+
+    /// Returns the typed arguments and the additional arguments, in the `key=value,key=value`
+    /// form that UHD expects
+    pub fn args_string(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(spp) = self.samples_per_packet {
+            parts.push(format!("spp={}", spp));
+        }
+        if let Some(fullscale) = self.fullscale {
+            parts.push(format!("fullscale={}", fullscale));
+        }
+        if let Some(peak) = self.peak {
+            parts.push(format!("peak={}", peak));
+        }
+        if let Some(policy) = self.underflow_policy {
+            parts.push(format!("underflow_policy={}", policy.as_str()));
+        }
+        if !self.args.is_empty() {
+            parts.push(self.args.clone());
+        }
+        parts.join(",")
+    }
+
+    /// Checks these arguments for a device with `num_channels` channels in `direction`, and
+    /// returns the channels to stream
+    pub(crate) fn validate(
+        &self,
+        num_channels: usize,
+        direction: ChannelDirection,
+    ) -> Result<Vec<usize>> {
+        let invalid = |message: String| Err(Error::InvalidStreamArgs(message));
+        if self.wire_format.as_str().is_empty() {
+            return invalid("Wire format is empty".to_string());
+        }
+        if self.samples_per_packet == Some(0) {
+            return invalid("Samples per packet must be greater than zero".to_string());
+        }
+        if let Some(fullscale) = self.fullscale {
+            if !(fullscale.is_finite() && fullscale > 0.0) {
+                return invalid(format!("Full scale {} must be positive", fullscale));
+            }
+        }
+        if let Some(peak) = self.peak {
+            if !(peak > 0.0 && peak <= 1.0) {
+                return invalid(format!("Peak {} must be in the range (0, 1]", peak));
+            }
+        }
+
+        let channels = if self.channels.is_empty() {
+            vec![0]
+        } else {
+            self.channels.clone()
+        };
+        for (i, &channel) in channels.iter().enumerate() {
+            check_channel(channel, num_channels, direction)?;
+            if channels[..i].contains(&channel) {
+                return invalid(format!(
+                    "{} channel {} is listed more than once",
+                    direction, channel
+                ));
+            }
+        }
+        Ok(channels)
+    }
+
     /// Creates a builder, initialized with default arguments, that can be used to configure
     /// the stream arguments
     pub fn builder() -> StreamArgsBuilder<I> {
@@ -50,7 +209,11 @@ impl<I> Default for StreamArgs<I> {
     fn default() -> Self {
         StreamArgs {
             host_format: PhantomData,
-            wire_format: "sc16".to_string(),
+            wire_format: WireFormat::Sc16,
+            samples_per_packet: None,
+            fullscale: None,
+            peak: None,
+            underflow_policy: None,
             args: "".to_string(),
             // Empty list = just channel 0
             channels: vec![],
@@ -64,7 +227,7 @@ pub struct StreamArgsBuilder<I> {
 
 impl<I> StreamArgsBuilder<I> {
     /// Sets the wire data format
-    pub fn wire_format(self, wire_format: String) -> Self {
+    pub fn wire_format(self, wire_format: WireFormat) -> Self {
         StreamArgsBuilder {
             args: StreamArgs {
                 wire_format,
@@ -73,7 +236,51 @@ impl<I> StreamArgsBuilder<I> {
         }
     }
 
-    /// Sets additional arguments for the stream
+    /// Sets the number of samples in each packet (`spp`)
+    pub fn samples_per_packet(self, samples_per_packet: usize) -> Self {
+        StreamArgsBuilder {
+            args: StreamArgs {
+                samples_per_packet: Some(samples_per_packet),
+                ..self.args
+            },
+        }
+    }
+
+    /// Sets the host sample value that corresponds to the full scale of the wire format, when
+    /// the host format is floating-point (`fullscale`, default 1.0)
+    pub fn fullscale(self, fullscale: f64) -> Self {
+        StreamArgsBuilder {
+            args: StreamArgs {
+                fullscale: Some(fullscale),
+                ..self.args
+            },
+        }
+    }
+
+    /// Sets the fraction of full scale that is used to scale samples with the `sc8` wire
+    /// format (`peak`)
+    pub fn peak(self, peak: f64) -> Self {
+        StreamArgsBuilder {
+            args: StreamArgs {
+                peak: Some(peak),
+                ..self.args
+            },
+        }
+    }
+
+    /// Sets what a transmit stream does after an underflow (`underflow_policy`)
+    pub fn underflow_policy(self, underflow_policy: UnderflowPolicy) -> Self {
+        StreamArgsBuilder {
+            args: StreamArgs {
+                underflow_policy: Some(underflow_policy),
+                ..self.args
+            },
+        }
+    }
+
+    /// Sets additional arguments for the stream, in the form `key=value,key=value`
+    ///
+    /// These are passed to UHD after the typed arguments.
     pub fn args(self, args: String) -> Self {
         StreamArgsBuilder {
             args: StreamArgs { args, ..self.args },
@@ -113,8 +320,8 @@ where
     fn try_from(args: &'args StreamArgs<I>) -> Result<Self, Self::Error> {
         Ok(StreamArgsC {
             host_format: CString::new(I::FORMAT)?,
-            wire_format: CString::new(&*args.wire_format)?,
-            args: CString::new(&*args.args)?,
+            wire_format: CString::new(args.wire_format.as_str())?,
+            args: CString::new(args.args_string())?,
             channels: &args.channels,
         })
     }
@@ -139,6 +346,77 @@ impl Item for Complex<i8> {
     const FORMAT: &'static str = "sc8";
 }
 
+/// A stream item that can be converted to and from a `Complex64` sample
+///
+/// This is implemented for the complex sample types that UHD supports, and cannot be implemented
+/// outside this crate. It is used to generate and inspect samples without knowing their type,
+/// for example in [`SimUsrp`](crate::sim::SimUsrp).
+pub trait Sample: Item + private::Sealed {
+    /// Converts a sample with components in the range [-1, 1] into this item type
+    ///
+    /// Integer formats scale the components to their full range, rounding and saturating.
+    fn from_fc64(sample: Complex64) -> Self;
+
+    /// Converts this item into a sample with components in the range [-1, 1]
+    fn to_fc64(&self) -> Complex64;
+}
+
+mod private {
+    use num_complex::Complex;
+
+    pub trait Sealed {}
+
+    impl Sealed for Complex<f64> {}
+    impl Sealed for Complex<f32> {}
+    impl Sealed for Complex<i16> {}
+    impl Sealed for Complex<i8> {}
+}
+
+impl Sample for Complex64 {
+    fn from_fc64(sample: Complex64) -> Self {
+        sample
+    }
+    fn to_fc64(&self) -> Complex64 {
+        *self
+    }
+}
+impl Sample for Complex32 {
+    fn from_fc64(sample: Complex64) -> Self {
+        Complex32::new(sample.re as f32, sample.im as f32)
+    }
+    fn to_fc64(&self) -> Complex64 {
+        Complex64::new(self.re.into(), self.im.into())
+    }
+}
+impl Sample for Complex<i16> {
+    fn from_fc64(sample: Complex64) -> Self {
+        Complex::new(
+            (sample.re * f64::from(i16::MAX)).round() as i16,
+            (sample.im * f64::from(i16::MAX)).round() as i16,
+        )
+    }
+    fn to_fc64(&self) -> Complex64 {
+        Complex64::new(
+            f64::from(self.re) / f64::from(i16::MAX),
+            f64::from(self.im) / f64::from(i16::MAX),
+        )
+    }
+}
+impl Sample for Complex<i8> {
+    fn from_fc64(sample: Complex64) -> Self {
+        Complex::new(
+            (sample.re * f64::from(i8::MAX)).round() as i8,
+            (sample.im * f64::from(i8::MAX)).round() as i8,
+        )
+    }
+    fn to_fc64(&self) -> Complex64 {
+        Complex64::new(
+            f64::from(self.re) / f64::from(i8::MAX),
+            f64::from(self.im) / f64::from(i8::MAX),
+        )
+    }
+}
+
 /// A stream command that can be sent to a USRP to control streaming
 #[derive(Debug, Clone)]
 pub struct StreamCommand {
@@ -165,7 +443,10 @@ pub enum StreamCommandType {
 #[derive(Debug, Clone)]
 pub enum StreamTime {
     Now,
+    /// At a device time, expressed as a duration since device time zero
     Later(std::time::Duration),
+    /// At a device time
+    At(TimeSpec),
 }
 
 impl StreamCommand {
@@ -173,7 +454,7 @@ impl StreamCommand {
     ///
     /// # Panics
     ///
-    /// This function panics if this command is `Later` and contains a time with
+    /// This function panics if this command is `Later` or `At` and contains a time with
     /// a seconds field that is too large for a time_t.
     pub(crate) fn as_c_command(&self) -> uhd_sys::uhd_stream_cmd_t {
         let mut c_cmd = uhd_sys::uhd_stream_cmd_t {
@@ -184,11 +465,23 @@ impl StreamCommand {
             time_spec_frac_secs: 0.0,
         };
 
-        match &self.time {
-            StreamTime::Now => c_cmd.stream_now = true,
-            StreamTime::Later(dur) => {
-                c_cmd.time_spec_full_secs = dur.as_secs() as i64;
-                c_cmd.time_spec_frac_secs = dur.subsec_millis() as f64 / 1000.0
+        let time = match &self.time {
+            StreamTime::Now => None,
+            StreamTime::Later(dur) => Some(TimeSpec::from(*dur)),
+            StreamTime::At(time) => Some(*time),
+        };
+        match time {
+            None => c_cmd.stream_now = true,
+            Some(time) => {
+                let (full_secs, frac_secs) = time.normalized().into_parts();
+                // In some versions of UHD, time_spec_full_secs is a time_t. In other versions,
+                // it's an int64_t.
+                #[allow(clippy::useless_conversion)]
+                {
+                    c_cmd.time_spec_full_secs =
+                        full_secs.try_into().expect("Seconds too large for time_t");
+                }
+                c_cmd.time_spec_frac_secs = frac_secs;
             }
         }
 
@@ -219,3 +512,139 @@ impl StreamCommand {
         c_cmd
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{
+        Sample, StreamArgs, StreamCommand, StreamCommandType, StreamTime, UnderflowPolicy,
+        WireFormat,
+    };
+    use crate::{ChannelDirection, Error, TimeSpec};
+    use num_complex::{Complex, Complex64};
+    use std::time::Duration;
+
+    #[test]
+    fn command_now() {
+        let command = StreamCommand {
+            time: StreamTime::Now,
+            command_type: StreamCommandType::CountAndDone(4096),
+        };
+        let c_cmd = command.as_c_command();
+        assert!(c_cmd.stream_now);
+        assert_eq!(
+            c_cmd.stream_mode,
+            uhd_sys::uhd_stream_mode_t::UHD_STREAM_MODE_NUM_SAMPS_AND_DONE
+        );
+        assert_eq!(c_cmd.num_samps, 4096);
+    }
+
+    #[test]
+    fn command_later_keeps_sub_millisecond_time() {
+        let command = StreamCommand {
+            time: StreamTime::Later(Duration::new(3, 250_500)),
+            command_type: StreamCommandType::StartContinuous,
+        };
+        let c_cmd = command.as_c_command();
+        assert!(!c_cmd.stream_now);
+        assert_eq!(c_cmd.time_spec_full_secs, 3);
+        assert!((c_cmd.time_spec_frac_secs - 0.000_250_5).abs() < 1e-15);
+    }
+
+    #[test]
+    fn command_at_time_spec() {
+        let command = StreamCommand {
+            time: StreamTime::At(TimeSpec::new(7, 1.000_000_125)),
+            command_type: StreamCommandType::CountAndMore(10),
+        };
+        let c_cmd = command.as_c_command();
+        assert!(!c_cmd.stream_now);
+        assert_eq!(c_cmd.time_spec_full_secs, 8);
+        assert!((c_cmd.time_spec_frac_secs - 0.000_000_125).abs() < 1e-12);
+        assert_eq!(
+            c_cmd.stream_mode,
+            uhd_sys::uhd_stream_mode_t::UHD_STREAM_MODE_NUM_SAMPS_AND_MORE
+        );
+    }
+
+    #[test]
+    fn item_conversions() {
+        let sample = Complex64::new(0.5, -1.0);
+        assert_eq!(
+            Complex::<i16>::from_fc64(sample),
+            Complex::new(16384, -32767)
+        );
+        assert_eq!(Complex::<i8>::from_fc64(sample), Complex::new(64, -127));
+        assert_eq!(
+            Complex::<i16>::from_fc64(Complex64::new(2.0, -2.0)),
+            Complex::new(i16::MAX, i16::MIN)
+        );
+        assert_eq!(Complex::new(127i8, 0).to_fc64(), Complex64::new(1.0, 0.0));
+    }
+
+    #[test]
+    fn wire_format_names() {
+        for format in [
+            WireFormat::Sc16,
+            WireFormat::Sc12,
+            WireFormat::Sc8,
+            WireFormat::Fc32,
+        ] {
+            assert_eq!(format.to_string().parse::<WireFormat>().unwrap(), format);
+        }
+        assert!(matches!(
+            "s16".parse::<WireFormat>(),
+            Err(Error::InvalidStreamArgs(_))
+        ));
+        assert_eq!(
+            WireFormat::Other("sc12_item32".into()).as_str(),
+            "sc12_item32"
+        );
+    }
+
+    #[test]
+    fn typed_args() {
+        let args = StreamArgs::<Complex64>::builder()
+            .samples_per_packet(200)
+            .fullscale(32768.0)
+            .underflow_policy(UnderflowPolicy::NextBurst)
+            .args("noclear=1".to_string())
+            .build();
+        assert_eq!(
+            args.args_string(),
+            "spp=200,fullscale=32768,underflow_policy=next_burst,noclear=1"
+        );
+        assert_eq!(StreamArgs::<Complex64>::default().args_string(), "");
+    }
+
+    #[test]
+    fn validate_channels_and_values() {
+        let args = StreamArgs::<Complex64>::default();
+        assert_eq!(
+            args.validate(1, ChannelDirection::Receive).unwrap(),
+            vec![0]
+        );
+
+        let args = StreamArgs::<Complex64>::builder()
+            .channels(vec![0, 2])
+            .build();
+        assert!(matches!(
+            args.validate(2, ChannelDirection::Transmit),
+            Err(Error::NoSuchChannel {
+                direction: ChannelDirection::Transmit,
+                channel: 2,
+                num_channels: 2,
+            })
+        ));
+
+        let args = StreamArgs::<Complex64>::builder()
+            .channels(vec![1, 1])
+            .build();
+        assert!(args.validate(2, ChannelDirection::Receive).is_err());
+        let args = StreamArgs::<Complex64>::builder().peak(1.5).build();
+        assert!(args.validate(1, ChannelDirection::Receive).is_err());
+        let args = StreamArgs::<Complex64>::builder()
+            .samples_per_packet(0)
+            .build();
+        assert!(args.validate(1, ChannelDirection::Receive).is_err());
+    }
+}
@@ -19,6 +19,7 @@ impl StringVector {
     }
 
     /// Returns the number of strings in this vector
+    #[allow(clippy::useless_conversion)]
     pub fn len(&self) -> usize {
         let mut len = 0;
         let status = unsafe { uhd_sys::uhd_string_vector_size(self.0, &mut len) };
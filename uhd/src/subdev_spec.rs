@@ -0,0 +1,254 @@
+use std::ffi::{CStr, CString};
+use std::fmt;
+use std::iter::FromIterator;
+use std::ptr;
+use std::str::FromStr;
+
+use crate::error::{check_status, Error};
+
+/// One entry in a subdevice specification: a daughterboard slot and a subdevice (frontend)
+/// on that daughterboard
+///
+/// In markup form, a pair is written as `db_name:sd_name`, for example `A:0` or `B:RX2`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SubdevSpecPair {
+    db_name: String,
+    sd_name: String,
+}
+
+impl SubdevSpecPair {
+    /// Creates a pair from a daughterboard name and a subdevice name
+    pub fn new<D, S>(db_name: D, sd_name: S) -> Self
+    where
+        D: Into<String>,
+        S: Into<String>,
+    {
+        SubdevSpecPair {
+            db_name: db_name.into(),
+            sd_name: sd_name.into(),
+        }
+    }
+
+    /// Returns the name of the daughterboard slot
+    pub fn db_name(&self) -> &str {
+        &self.db_name
+    }
+
+    /// Returns the name of the subdevice on the daughterboard
+    pub fn sd_name(&self) -> &str {
+        &self.sd_name
+    }
+}
+
+impl fmt::Display for SubdevSpecPair {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.db_name, self.sd_name)
+    }
+}
+
+impl FromStr for SubdevSpecPair {
+    type Err = Error;
+
+    /// Parses a pair from the form `db_name:sd_name` or `db_name`
+    ///
+    /// If only a daughterboard name is provided, the subdevice name is empty.
+    fn from_str(markup: &str) -> Result<Self, Self::Err> {
+        let mut parts = markup.trim().split(':');
+        match (parts.next(), parts.next(), parts.next()) {
+            (Some(db_name), sd_name, None) if !db_name.is_empty() => {
+                Ok(SubdevSpecPair::new(db_name, sd_name.unwrap_or("")))
+            }
+            _ => Err(Error::Unique(format!(
+                "Invalid subdevice specification pair \"{}\"",
+                markup
+            ))),
+        }
+    }
+}
+
+/// A subdevice specification, which maps channels to daughterboard frontends
+///
+/// The pair at index n is used for channel n. In markup form, pairs are separated by
+/// whitespace: `A:0 B:0` routes channel 0 to frontend 0 of slot A and channel 1 to frontend 0
+/// of slot B.
+///
+/// ```
+/// use uhd::SubdevSpec;
+/// let spec: SubdevSpec = "A:0 B:0".parse().unwrap();
+/// assert_eq!(spec.len(), 2);
+/// assert_eq!(spec.get(1).unwrap().db_name(), "B");
+/// assert_eq!(spec.to_string(), "A:0 B:0");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct SubdevSpec(Vec<SubdevSpecPair>);
+
+impl SubdevSpec {
+    /// Creates an empty subdevice specification
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Returns the number of pairs (channels) in this specification
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns true if this specification has no pairs
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Returns the pair for a channel, if one exists
+    pub fn get(&self, index: usize) -> Option<&SubdevSpecPair> {
+        self.0.get(index)
+    }
+
+    /// Appends a pair (a new channel) to the end of this specification
+    pub fn push(&mut self, pair: SubdevSpecPair) {
+        self.0.push(pair)
+    }
+
+    /// Returns an iterator over the pairs in this specification
+    pub fn iter(&self) -> std::slice::Iter<'_, SubdevSpecPair> {
+        self.0.iter()
+    }
+}
+
+impl fmt::Display for SubdevSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, pair) in self.0.iter().enumerate() {
+            if i != 0 {
+                f.write_str(" ")?;
+            }
+            write!(f, "{}", pair)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for SubdevSpec {
+    type Err = Error;
+
+    fn from_str(markup: &str) -> Result<Self, Self::Err> {
+        markup.split_whitespace().map(str::parse).collect()
+    }
+}
+
+impl From<Vec<SubdevSpecPair>> for SubdevSpec {
+    fn from(pairs: Vec<SubdevSpecPair>) -> Self {
+        SubdevSpec(pairs)
+    }
+}
+
+impl FromIterator<SubdevSpecPair> for SubdevSpec {
+    fn from_iter<T: IntoIterator<Item = SubdevSpecPair>>(iter: T) -> Self {
+        SubdevSpec(iter.into_iter().collect())
+    }
+}
+
+impl IntoIterator for SubdevSpec {
+    type Item = SubdevSpecPair;
+    type IntoIter = std::vec::IntoIter<SubdevSpecPair>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'s> IntoIterator for &'s SubdevSpec {
+    type Item = &'s SubdevSpecPair;
+    type IntoIter = std::slice::Iter<'s, SubdevSpecPair>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// A handle to a C++ subdev_spec_t
+pub(crate) struct SubdevSpecHandle(uhd_sys::uhd_subdev_spec_handle);
+
+impl SubdevSpecHandle {
+    /// Creates a C++ subdevice specification from a Rust one
+    pub fn new(spec: &SubdevSpec) -> Result<Self, Error> {
+        let markup = CString::new(spec.to_string())?;
+        let mut handle: uhd_sys::uhd_subdev_spec_handle = ptr::null_mut();
+        check_status(unsafe { uhd_sys::uhd_subdev_spec_make(&mut handle, markup.as_ptr()) })?;
+        Ok(SubdevSpecHandle(handle))
+    }
+
+    /// Copies the pairs out of the C++ subdevice specification
+    pub fn to_spec(&self) -> Result<SubdevSpec, Error> {
+        let mut size = 0usize;
+        check_status(unsafe {
+            uhd_sys::uhd_subdev_spec_size(self.0, &mut size as *mut usize as *mut _)
+        })?;
+        (0..size).map(|index| self.pair(index)).collect()
+    }
+
+    fn pair(&self, index: usize) -> Result<SubdevSpecPair, Error> {
+        let mut pair_c = uhd_sys::uhd_subdev_spec_pair_t {
+            db_name: ptr::null_mut(),
+            sd_name: ptr::null_mut(),
+        };
+        check_status(unsafe { uhd_sys::uhd_subdev_spec_at(self.0, index as _, &mut pair_c) })?;
+        let pair = unsafe {
+            CStr::from_ptr(pair_c.db_name).to_str().and_then(|db_name| {
+                Ok(SubdevSpecPair::new(
+                    db_name,
+                    CStr::from_ptr(pair_c.sd_name).to_str()?,
+                ))
+            })
+        };
+        let _ = unsafe { uhd_sys::uhd_subdev_spec_pair_free(&mut pair_c) };
+        Ok(pair?)
+    }
+
+    pub fn handle(&self) -> uhd_sys::uhd_subdev_spec_handle {
+        self.0
+    }
+}
+
+impl Drop for SubdevSpecHandle {
+    fn drop(&mut self) {
+        let _ = unsafe { uhd_sys::uhd_subdev_spec_free(&mut self.0) };
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{SubdevSpec, SubdevSpecHandle, SubdevSpecPair};
+
+    #[test]
+    fn parse_and_display() {
+        let spec: SubdevSpec = "A:0  B:RX2".parse().unwrap();
+        assert_eq!(
+            spec,
+            SubdevSpec::from(vec![
+                SubdevSpecPair::new("A", "0"),
+                SubdevSpecPair::new("B", "RX2")
+            ])
+        );
+        assert_eq!("A:0 B:RX2", spec.to_string());
+    }
+
+    #[test]
+    fn parse_without_subdevice_name() {
+        let spec: SubdevSpec = "A".parse().unwrap();
+        assert_eq!(Some(&SubdevSpecPair::new("A", "")), spec.get(0));
+        assert_eq!("A:", spec.to_string());
+    }
+
+    #[test]
+    fn parse_empty_and_invalid() {
+        assert!("".parse::<SubdevSpec>().unwrap().is_empty());
+        assert!("A:0:1".parse::<SubdevSpec>().is_err());
+        assert!(":0".parse::<SubdevSpec>().is_err());
+    }
+
+    #[test]
+    fn handle_round_trip() {
+        let spec: SubdevSpec = "A:0 A:1".parse().unwrap();
+        let handle = SubdevSpecHandle::new(&spec).unwrap();
+        assert_eq!(spec, handle.to_spec().unwrap());
+    }
+}
@@ -0,0 +1,486 @@
+use std::cmp::Ordering;
+use std::convert::{TryFrom, TryInto};
+use std::fmt;
+use std::ops::{Add, AddAssign, Sub, SubAssign};
+use std::time::Duration;
+
+use crate::error::Error;
+
+const NANOS_PER_SECOND: i64 = 1_000_000_000;
+
+/// A UHD device time.
+///
+/// This type keeps UHD's split integer/floating-point representation contained in one value.
+///
+/// Arithmetic operations return normalized times, with the fractional component in the range
+/// `[0, 1)`. Comparisons normalize both operands first, so `TimeSpec::new(1, 0.5)` and
+/// `TimeSpec::new(0, 1.5)` are equal.
+#[derive(Debug, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TimeSpec {
+    // In some versions of UHD, the corresponding field of uhd::time_spec_t is a time_t.
+    // In other versions, it's a int64_t. The Rust code does conversion to keep this
+    // an i64.
+    pub seconds: i64,
+    pub fraction: f64,
+}
+
+impl TimeSpec {
+    /// Creates a device time from whole and fractional seconds.
+    pub const fn new(full_secs: i64, frac_secs: f64) -> Self {
+        Self {
+            seconds: full_secs,
+            fraction: frac_secs,
+        }
+    }
+
+    /// Creates a device time from a total number of nanoseconds.
+    ///
+    /// Negative values are normalized so that the fractional component is in the range `[0, 1)`.
+    pub fn from_nanos(nanoseconds: i64) -> Self {
+        let full_secs = nanoseconds.div_euclid(NANOS_PER_SECOND);
+        let fractional_nanos = nanoseconds.rem_euclid(NANOS_PER_SECOND);
+        Self::new(full_secs, fractional_nanos as f64 / NANOS_PER_SECOND as f64)
+    }
+
+    /// Converts this device time to a total number of nanoseconds.
+    ///
+    /// The fractional component is rounded to the nearest nanosecond.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the fractional component is not finite or the result does not fit in an `i64`.
+    pub fn into_nanos(self) -> i64 {
+        self.total_nanos()
+            .try_into()
+            .expect("TimeSpec does not fit in i64 nanoseconds")
+    }
+
+    /// Creates a device time from a number of ticks of a clock with the provided rate in hertz
+    ///
+    /// This uses the same algorithm as `uhd::time_spec_t::from_ticks`, which stays exact for
+    /// large tick counts when the rate is not an integer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the rate is not finite or is less than 1 Hz.
+    pub fn from_ticks(ticks: i64, rate: f64) -> Self {
+        let (rate_i, rate_f) = split_rate(rate);
+        let secs_full = ticks / rate_i;
+        let ticks_error = ticks - secs_full * rate_i;
+        let ticks_frac = ticks_error as f64 - secs_full as f64 * rate_f;
+        TimeSpec::new(secs_full, ticks_frac / rate).normalized()
+    }
+
+    /// Converts this device time to a number of ticks of a clock with the provided rate in hertz,
+    /// rounding to the nearest tick
+    ///
+    /// This uses the same algorithm as `uhd::time_spec_t::to_ticks`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the rate is not finite or is less than 1 Hz, or if the number of ticks does not
+    /// fit in an `i64`. [`checked_to_ticks`](Self::checked_to_ticks) returns None instead of
+    /// panicking when the ticks do not fit.
+    pub fn to_ticks(self, rate: f64) -> i64 {
+        self.checked_to_ticks(rate)
+            .expect("TimeSpec ticks do not fit in i64")
+    }
+
+    /// Converts this device time to a number of ticks like [`to_ticks`](Self::to_ticks), or
+    /// returns None if the number of ticks does not fit in an `i64` or the fractional component
+    /// is not finite
+    ///
+    /// # Panics
+    ///
+    /// Panics if the rate is not finite or is less than 1 Hz.
+    pub fn checked_to_ticks(self, rate: f64) -> Option<i64> {
+        let (rate_i, rate_f) = split_rate(rate);
+        let ticks_full = self.seconds.checked_mul(rate_i)?;
+        let ticks_error = self.seconds as f64 * rate_f;
+        let ticks_frac = self.fraction * rate;
+        let ticks_rest = (ticks_error + ticks_frac).round();
+        // i64::MAX as f64 rounds up to 2^63, which does not fit
+        if !(ticks_rest >= i64::MIN as f64 && ticks_rest < i64::MAX as f64) {
+            return None;
+        }
+        ticks_full.checked_add(ticks_rest as i64)
+    }
+
+    /// Adds two device times, returning None if the whole seconds overflow
+    pub fn checked_add(self, rhs: TimeSpec) -> Option<TimeSpec> {
+        TimeSpec::new(
+            self.seconds.checked_add(rhs.seconds)?,
+            self.fraction + rhs.fraction,
+        )
+        .checked_normalized()
+    }
+
+    /// Subtracts a device time from this one, returning None if the whole seconds overflow
+    pub fn checked_sub(self, rhs: TimeSpec) -> Option<TimeSpec> {
+        TimeSpec::new(
+            self.seconds.checked_sub(rhs.seconds)?,
+            self.fraction - rhs.fraction,
+        )
+        .checked_normalized()
+    }
+
+    /// Returns this device time as a floating-point number of seconds
+    ///
+    /// This loses precision for large times.
+    pub fn as_secs_f64(self) -> f64 {
+        self.seconds as f64 + self.fraction
+    }
+
+    /// Returns an equivalent device time with the fractional component in the range `[0, 1)`
+    ///
+    /// A fractional component that is not finite is left unchanged.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the whole seconds overflow an `i64`.
+    pub fn normalized(self) -> Self {
+        self.checked_normalized()
+            .expect("TimeSpec seconds overflow i64")
+    }
+
+    fn checked_normalized(self) -> Option<Self> {
+        if !self.fraction.is_finite() {
+            return Some(self);
+        }
+        let whole = self.fraction.floor();
+        if !(whole >= i64::MIN as f64 && whole < i64::MAX as f64) {
+            return None;
+        }
+        let mut seconds = self.seconds.checked_add(whole as i64)?;
+        let mut fraction = self.fraction - whole;
+        // Rounding can produce exactly 1.0 for tiny negative fractions
+        if fraction >= 1.0 {
+            seconds = seconds.checked_add(1)?;
+            fraction -= 1.0;
+        }
+        // Adding zero turns -0.0 into 0.0
+        Some(TimeSpec::new(seconds, fraction + 0.0))
+    }
+
+    /// Returns the normalized whole and fractional seconds, with the whole seconds as an `i128`
+    fn wide_parts(self) -> (i128, f64) {
+        if !self.fraction.is_finite() {
+            return (i128::from(self.seconds), self.fraction);
+        }
+        let whole = self.fraction.floor();
+        let mut seconds = i128::from(self.seconds) + whole as i128;
+        let mut fraction = self.fraction - whole;
+        if fraction >= 1.0 {
+            seconds += 1;
+            fraction -= 1.0;
+        }
+        (seconds, fraction + 0.0)
+    }
+
+    pub(crate) fn into_parts(self) -> (i64, f64) {
+        (self.seconds, self.fraction)
+    }
+
+    fn total_nanos(self) -> i128 {
+        assert!(
+            self.fraction.is_finite(),
+            "TimeSpec fractional seconds must be finite"
+        );
+        let full_nanos = i128::from(self.seconds) * i128::from(NANOS_PER_SECOND);
+        let fractional_nanos = (self.fraction * NANOS_PER_SECOND as f64).round() as i128;
+        full_nanos + fractional_nanos
+    }
+}
+
+/// Splits a tick rate into integer and fractional parts
+fn split_rate(rate: f64) -> (i64, f64) {
+    assert!(
+        rate.is_finite() && rate >= 1.0,
+        "Tick rate must be finite and at least 1 Hz"
+    );
+    let rate_i = rate as i64;
+    (rate_i, rate - rate_i as f64)
+}
+
+impl PartialEq for TimeSpec {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for TimeSpec {}
+
+impl PartialOrd for TimeSpec {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for TimeSpec {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Normalizing with wider seconds can't overflow
+        let (seconds, fraction) = self.wide_parts();
+        let (other_seconds, other_fraction) = other.wide_parts();
+        seconds
+            .cmp(&other_seconds)
+            .then_with(|| fraction.total_cmp(&other_fraction))
+    }
+}
+
+impl Add for TimeSpec {
+    type Output = TimeSpec;
+
+    /// Adds two device times
+    ///
+    /// # Panics
+    ///
+    /// Panics if the whole seconds overflow an `i64`. [`TimeSpec::checked_add`] returns None
+    /// instead.
+    fn add(self, rhs: TimeSpec) -> Self::Output {
+        self.checked_add(rhs)
+            .expect("overflow when adding TimeSpecs")
+    }
+}
+
+impl Sub for TimeSpec {
+    type Output = TimeSpec;
+
+    /// Subtracts two device times
+    ///
+    /// # Panics
+    ///
+    /// Panics if the whole seconds overflow an `i64`. [`TimeSpec::checked_sub`] returns None
+    /// instead.
+    fn sub(self, rhs: TimeSpec) -> Self::Output {
+        self.checked_sub(rhs)
+            .expect("overflow when subtracting TimeSpecs")
+    }
+}
+
+impl Add<Duration> for TimeSpec {
+    type Output = TimeSpec;
+
+    fn add(self, rhs: Duration) -> Self::Output {
+        self + TimeSpec::from(rhs)
+    }
+}
+
+impl Sub<Duration> for TimeSpec {
+    type Output = TimeSpec;
+
+    fn sub(self, rhs: Duration) -> Self::Output {
+        self - TimeSpec::from(rhs)
+    }
+}
+
+impl AddAssign for TimeSpec {
+    fn add_assign(&mut self, rhs: TimeSpec) {
+        *self = *self + rhs;
+    }
+}
+
+impl SubAssign for TimeSpec {
+    fn sub_assign(&mut self, rhs: TimeSpec) {
+        *self = *self - rhs;
+    }
+}
+
+impl AddAssign<Duration> for TimeSpec {
+    fn add_assign(&mut self, rhs: Duration) {
+        *self = *self + rhs;
+    }
+}
+
+impl SubAssign<Duration> for TimeSpec {
+    fn sub_assign(&mut self, rhs: Duration) {
+        *self = *self - rhs;
+    }
+}
+
+impl From<Duration> for TimeSpec {
+    /// Converts a duration into a device time measured from time zero
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of whole seconds does not fit in an `i64`.
+    fn from(duration: Duration) -> Self {
+        TimeSpec::new(
+            duration
+                .as_secs()
+                .try_into()
+                .expect("Duration does not fit in TimeSpec"),
+            f64::from(duration.subsec_nanos()) / NANOS_PER_SECOND as f64,
+        )
+    }
+}
+
+impl TryFrom<TimeSpec> for Duration {
+    type Error = Error;
+
+    /// Converts a non-negative device time into a duration, rounding to the nearest nanosecond
+    fn try_from(time: TimeSpec) -> Result<Self, Self::Error> {
+        if !time.fraction.is_finite() {
+            return Err(Error::Unique(format!(
+                "TimeSpec {:?} has a fractional component that is not finite",
+                time
+            )));
+        }
+        let nanos = time.total_nanos();
+        let seconds = u64::try_from(nanos.div_euclid(i128::from(NANOS_PER_SECOND)))
+            .map_err(|_| Error::Unique(format!("TimeSpec {} is negative", time)))?;
+        let subsec_nanos = nanos.rem_euclid(i128::from(NANOS_PER_SECOND)) as u32;
+        Ok(Duration::new(seconds, subsec_nanos))
+    }
+}
+
+impl fmt::Display for TimeSpec {
+    /// Formats this time as seconds with nine decimal places, for example `12.000250000`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.fraction.is_finite() {
+            return write!(f, "{} + {}", self.seconds, self.fraction);
+        }
+        let nanos = self.total_nanos();
+        let sign = if nanos < 0 { "-" } else { "" };
+        let nanos = nanos.unsigned_abs();
+        let nanos_per_second = NANOS_PER_SECOND as u128;
+        write!(
+            f,
+            "{}{}.{:09}",
+            sign,
+            nanos / nanos_per_second,
+            nanos % nanos_per_second
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::TimeSpec;
+    use std::convert::TryFrom;
+    use std::time::Duration;
+
+    #[test]
+    fn constructs_from_parts() {
+        assert_eq!(
+            TimeSpec::new(42, 0.25),
+            TimeSpec {
+                seconds: 42,
+                fraction: 0.25,
+            }
+        );
+    }
+
+    #[test]
+    fn constructs_from_nanoseconds() {
+        assert_eq!(TimeSpec::from_nanos(1_500_000_000), TimeSpec::new(1, 0.5));
+        assert_eq!(TimeSpec::from_nanos(-500_000_000), TimeSpec::new(-1, 0.5));
+    }
+
+    #[test]
+    fn nanoseconds_round_trip() {
+        for nanoseconds in [i64::MIN, -1_500_000_001, -1, 0, 1, 1_500_000_001, i64::MAX] {
+            assert_eq!(TimeSpec::from_nanos(nanoseconds).into_nanos(), nanoseconds);
+        }
+    }
+
+    #[test]
+    fn normalizes() {
+        let time = TimeSpec::new(1, 2.25).normalized();
+        assert_eq!((time.seconds, time.fraction), (3, 0.25));
+        let time = TimeSpec::new(1, -0.25).normalized();
+        assert_eq!((time.seconds, time.fraction), (0, 0.75));
+        let time = TimeSpec::new(4, -0.0).normalized();
+        assert!(time.fraction.is_sign_positive());
+    }
+
+    #[test]
+    fn compares_unnormalized() {
+        assert_eq!(TimeSpec::new(1, 0.5), TimeSpec::new(0, 1.5));
+        assert!(TimeSpec::new(2, -0.1) < TimeSpec::new(1, 0.95));
+        assert!(TimeSpec::new(-1, 0.5) > TimeSpec::new(-1, 0.25));
+        assert_eq!(TimeSpec::new(3, 0.0), TimeSpec::new(3, -0.0));
+    }
+
+    #[test]
+    fn arithmetic() {
+        assert_eq!(
+            TimeSpec::new(1, 0.75) + TimeSpec::new(2, 0.5),
+            TimeSpec::new(4, 0.25)
+        );
+        assert_eq!(
+            TimeSpec::new(1, 0.25) - TimeSpec::new(0, 0.5),
+            TimeSpec::new(0, 0.75)
+        );
+        let mut time = TimeSpec::new(10, 0.9);
+        time += Duration::from_millis(200);
+        assert_eq!(time.seconds, 11);
+        assert!((time.fraction - 0.1).abs() < 1e-12);
+        time -= Duration::from_secs(12);
+        assert_eq!(time.seconds, -1);
+    }
+
+    #[test]
+    fn duration_conversion() {
+        let duration = Duration::new(5, 250_000_001);
+        let time = TimeSpec::from(duration);
+        assert_eq!(Duration::try_from(time).unwrap(), duration);
+        assert!(Duration::try_from(TimeSpec::new(-1, 0.5)).is_err());
+        assert!(Duration::try_from(TimeSpec::new(0, f64::NAN)).is_err());
+    }
+
+    #[test]
+    fn ticks() {
+        let rate = 30.72e6;
+        for &ticks in &[0, 1, 30_719_999, 30_720_000, 1_000_000_000_000, -7] {
+            assert_eq!(TimeSpec::from_ticks(ticks, rate).to_ticks(rate), ticks);
+        }
+        let rate = 200e6 / 3.0;
+        let time = TimeSpec::from_ticks(10_000_000_000_001, rate);
+        assert_eq!(time.to_ticks(rate), 10_000_000_000_001);
+        assert_eq!(TimeSpec::new(2, 0.5).to_ticks(1e6), 2_500_000);
+        assert_eq!(TimeSpec::from_ticks(1_500, 1e3), TimeSpec::new(1, 0.5));
+        assert_eq!(
+            TimeSpec::new(i64::MAX / 1000, 0.0).checked_to_ticks(1e6),
+            None
+        );
+        assert_eq!(TimeSpec::new(0, f64::NAN).checked_to_ticks(1e6), None);
+    }
+
+    #[test]
+    fn checked_arithmetic() {
+        let max = TimeSpec::new(i64::MAX, 0.5);
+        assert_eq!(
+            max.checked_add(TimeSpec::new(0, 0.25)),
+            Some(TimeSpec::new(i64::MAX, 0.75))
+        );
+        assert_eq!(max.checked_add(TimeSpec::new(0, 0.5)), None);
+        assert_eq!(max.checked_add(TimeSpec::new(1, 0.0)), None);
+        assert_eq!(
+            TimeSpec::new(i64::MIN, 0.0).checked_sub(TimeSpec::new(0, 0.5)),
+            None
+        );
+        assert_eq!(
+            TimeSpec::new(1, 0.25).checked_sub(TimeSpec::new(2, 0.5)),
+            Some(TimeSpec::new(-2, 0.75))
+        );
+        assert!(TimeSpec::new(i64::MAX, 1.5) > max);
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(TimeSpec::new(12, 0.00025).to_string(), "12.000250000");
+        assert_eq!(TimeSpec::new(-1, 0.5).to_string(), "-0.500000000");
+        assert_eq!(TimeSpec::new(0, 0.0).to_string(), "0.000000000");
+        assert!((TimeSpec::new(3, 0.5).as_secs_f64() - 3.5).abs() < f64::EPSILON);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_form() {
+        let time = TimeSpec::new(12, 0.25);
+        let json = serde_json::to_string(&time).unwrap();
+        assert_eq!(json, r#"{"seconds":12,"fraction":0.25}"#);
+        assert_eq!(serde_json::from_str::<TimeSpec>(&json).unwrap(), time);
+    }
+}
@@ -0,0 +1,115 @@
+use std::mem::ManuallyDrop;
+
+use crate::error::Error;
+use crate::usrp::Usrp;
+use crate::{TimeSpec, TuneRequest, TuneResult};
+
+/// A scope in which commands sent to a USRP take effect at a specified time
+///
+/// This is returned by [`Usrp::timed_commands`]. It provides the tuning and gain setters of
+/// [`Usrp`]. When the guard is dropped (including on an early return with `?`), the command time
+/// is cleared so that later commands take effect immediately.
+///
+/// Dropping the guard ignores any error from clearing the command time. Use
+/// [`finish`](Self::finish) to find out whether clearing succeeded.
+///
+/// ```no_run
+/// # fn main() -> Result<(), uhd::Error> {
+/// use uhd::{TimeSpec, TuneRequest, Usrp};
+/// let mut usrp = Usrp::open("")?;
+/// let mut timed = usrp.timed_commands(TimeSpec::new(10, 0.0), 0)?;
+/// timed.set_rx_frequency(&TuneRequest::with_frequency(915e6), 0)?;
+/// timed.set_rx_gain(30.0, 0, "")?;
+/// timed.finish()?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct TimedCommands<'usrp> {
+    usrp: &'usrp mut Usrp,
+    mboard: usize,
+}
+
+impl<'usrp> TimedCommands<'usrp> {
+    pub(crate) fn new(usrp: &'usrp mut Usrp, time: TimeSpec, mboard: usize) -> Result<Self, Error> {
+        usrp.set_command_time(time, mboard)?;
+        Ok(TimedCommands { usrp, mboard })
+    }
+
+    /// Returns the index of the motherboard that the command time applies to
+    pub fn mboard(&self) -> usize {
+        self.mboard
+    }
+
+    /// Clears the command time and returns any error that happened
+    pub fn finish(self) -> Result<(), Error> {
+        let mut this = ManuallyDrop::new(self);
+        let mboard = this.mboard;
+        this.usrp.clear_command_time(mboard)
+    }
+
+    /// Sets the receive center frequency
+    pub fn set_rx_frequency(
+        &mut self,
+        request: &TuneRequest,
+        channel: usize,
+    ) -> Result<TuneResult, Error> {
+        self.usrp.set_rx_frequency(request, channel)
+    }
+
+    /// Sets the transmit center frequency
+    pub fn set_tx_frequency(
+        &mut self,
+        request: &TuneRequest,
+        channel: usize,
+    ) -> Result<TuneResult, Error> {
+        self.usrp.set_tx_frequency(request, channel)
+    }
+
+    /// Sets the frequency of a receive local oscillator and returns the frequency that it was
+    /// actually tuned to
+    pub fn set_rx_lo_frequency(
+        &mut self,
+        frequency: f64,
+        channel: usize,
+        name: &str,
+    ) -> Result<f64, Error> {
+        self.usrp.set_rx_lo_frequency(frequency, channel, name)
+    }
+
+    /// Sets the frequency of a transmit local oscillator and returns the frequency that it was
+    /// actually tuned to
+    pub fn set_tx_lo_frequency(
+        &mut self,
+        frequency: f64,
+        channel: usize,
+        name: &str,
+    ) -> Result<f64, Error> {
+        self.usrp.set_tx_lo_frequency(frequency, channel, name)
+    }
+
+    /// Sets the receive gain
+    pub fn set_rx_gain(&mut self, gain: f64, channel: usize, name: &str) -> Result<(), Error> {
+        self.usrp.set_rx_gain(gain, channel, name)
+    }
+
+    /// Sets the transmit gain
+    pub fn set_tx_gain(&mut self, gain: f64, channel: usize, name: &str) -> Result<(), Error> {
+        self.usrp.set_tx_gain(gain, channel, name)
+    }
+
+    /// Sets the receive gain, normalized to [0, 1]
+    pub fn set_normalized_rx_gain(&mut self, gain: f64, channel: usize) -> Result<(), Error> {
+        self.usrp.set_normalized_rx_gain(gain, channel)
+    }
+
+    /// Sets the transmit gain, normalized to [0, 1]
+    pub fn set_normalized_tx_gain(&mut self, gain: f64, channel: usize) -> Result<(), Error> {
+        self.usrp.set_normalized_tx_gain(gain, channel)
+    }
+}
+
+impl Drop for TimedCommands<'_> {
+    fn drop(&mut self) {
+        let _ = self.usrp.clear_command_time(self.mboard);
+    }
+}
@@ -0,0 +1,193 @@
+use std::ptr;
+
+use crate::error::{check_status, Error};
+use crate::TimeSpec;
+
+/// An asynchronous message about a transmit operation
+///
+/// Transmit streamers report events that happen after samples have been handed to UHD (for
+/// example underflows, late packets, and acknowledgements of the end of a burst) through
+/// [`TransmitStreamer::recv_async_msg`](crate::TransmitStreamer::recv_async_msg).
+#[derive(Debug, Clone, PartialEq)]
+pub struct AsyncMetadata {
+    channel: usize,
+    time_spec: Option<TimeSpec>,
+    event_code: AsyncEventCode,
+    user_payload: [u32; 4],
+}
+
+impl AsyncMetadata {
+    pub(crate) fn new(
+        channel: usize,
+        time_spec: Option<TimeSpec>,
+        event_code: AsyncEventCode,
+        user_payload: [u32; 4],
+    ) -> Self {
+        AsyncMetadata {
+            channel,
+            time_spec,
+            event_code,
+            user_payload,
+        }
+    }
+
+    /// Returns the index of the channel that this event relates to
+    pub fn channel(&self) -> usize {
+        self.channel
+    }
+
+    /// Returns the time when the event happened, according to the USRP's internal clock
+    pub fn time_spec(&self) -> Option<TimeSpec> {
+        self.time_spec
+    }
+
+    /// Returns the kind of event that happened
+    pub fn event_code(&self) -> AsyncEventCode {
+        self.event_code
+    }
+
+    /// Returns the user-defined payload words
+    ///
+    /// These are only meaningful if the event code is [`AsyncEventCode::UserPayload`].
+    pub fn user_payload(&self) -> [u32; 4] {
+        self.user_payload
+    }
+}
+
+/// Kinds of asynchronous transmit events
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AsyncEventCode {
+    /// A burst was transmitted successfully (the end of the burst was acknowledged)
+    BurstAck,
+    /// An internal send buffer ran out of samples between packets
+    Underflow,
+    /// A packet was lost between the host and the device
+    SequenceError,
+    /// A packet had a time that was in the past
+    TimeError,
+    /// An internal send buffer ran out of samples within a packet
+    UnderflowInPacket,
+    /// A packet was lost within a burst
+    SequenceErrorInBurst,
+    /// The event carries a user-defined payload
+    UserPayload,
+    /// An event code that this library does not recognize
+    Other(u32),
+}
+
+impl AsyncEventCode {
+    /// Returns true if this event indicates a problem with the transmission
+    pub fn is_error(&self) -> bool {
+        !matches!(self, AsyncEventCode::BurstAck | AsyncEventCode::UserPayload)
+    }
+
+    #[allow(clippy::unnecessary_cast)]
+    fn from_c(code: uhd_sys::uhd_async_metadata_event_code_t::Type) -> Self {
+        use uhd_sys::uhd_async_metadata_event_code_t::*;
+        match code {
+            UHD_ASYNC_METADATA_EVENT_CODE_BURST_ACK => AsyncEventCode::BurstAck,
+            UHD_ASYNC_METADATA_EVENT_CODE_UNDERFLOW => AsyncEventCode::Underflow,
+            UHD_ASYNC_METADATA_EVENT_CODE_SEQ_ERROR => AsyncEventCode::SequenceError,
+            UHD_ASYNC_METADATA_EVENT_CODE_TIME_ERROR => AsyncEventCode::TimeError,
+            UHD_ASYNC_METADATA_EVENT_CODE_UNDERFLOW_IN_PACKET => AsyncEventCode::UnderflowInPacket,
+            UHD_ASYNC_METADATA_EVENT_CODE_SEQ_ERROR_IN_BURST => {
+                AsyncEventCode::SequenceErrorInBurst
+            }
+            UHD_ASYNC_METADATA_EVENT_CODE_USER_PAYLOAD => AsyncEventCode::UserPayload,
+            other => AsyncEventCode::Other(other as u32),
+        }
+    }
+}
+
+/// An owned handle to a C++ async_metadata_t
+pub(crate) struct AsyncMetadataHandle(uhd_sys::uhd_async_metadata_handle);
+
+impl AsyncMetadataHandle {
+    pub fn new() -> Result<Self, Error> {
+        let mut handle: uhd_sys::uhd_async_metadata_handle = ptr::null_mut();
+        check_status(unsafe { uhd_sys::uhd_async_metadata_make(&mut handle) })?;
+        Ok(AsyncMetadataHandle(handle))
+    }
+
+    /// Copies all fields out of the C++ object
+    #[allow(clippy::useless_conversion)]
+    pub fn read(&self) -> Result<AsyncMetadata, Error> {
+        let mut channel = 0usize;
+        check_status(unsafe {
+            uhd_sys::uhd_async_metadata_channel(self.0, &mut channel as *mut usize as *mut _)
+        })?;
+
+        let mut has_time_spec = false;
+        check_status(unsafe {
+            uhd_sys::uhd_async_metadata_has_time_spec(self.0, &mut has_time_spec)
+        })?;
+        let time_spec = if has_time_spec {
+            let mut time = TimeSpec::default();
+            let mut seconds_time_t: libc::time_t = Default::default();
+            check_status(unsafe {
+                uhd_sys::uhd_async_metadata_time_spec(
+                    self.0,
+                    &mut seconds_time_t,
+                    &mut time.fraction,
+                )
+            })?;
+            // Explicitly convert seconds from time_t to i64 (some platforms `time_t` is smaller
+            // than `i64`)
+            time.seconds = seconds_time_t.into();
+            Some(time)
+        } else {
+            None
+        };
+
+        let mut event_code = 0;
+        check_status(unsafe { uhd_sys::uhd_async_metadata_event_code(self.0, &mut event_code) })?;
+
+        let mut user_payload = [0u32; 4];
+        check_status(unsafe {
+            uhd_sys::uhd_async_metadata_user_payload(self.0, user_payload.as_mut_ptr())
+        })?;
+
+        Ok(AsyncMetadata {
+            channel,
+            time_spec,
+            event_code: AsyncEventCode::from_c(event_code),
+            user_payload,
+        })
+    }
+
+    pub fn handle_mut(&mut self) -> &mut uhd_sys::uhd_async_metadata_handle {
+        &mut self.0
+    }
+}
+
+impl Drop for AsyncMetadataHandle {
+    fn drop(&mut self) {
+        let _ = unsafe { uhd_sys::uhd_async_metadata_free(&mut self.0) };
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::AsyncEventCode;
+
+    #[test]
+    fn event_codes_from_c() {
+        use uhd_sys::uhd_async_metadata_event_code_t::*;
+        assert_eq!(
+            AsyncEventCode::BurstAck,
+            AsyncEventCode::from_c(UHD_ASYNC_METADATA_EVENT_CODE_BURST_ACK)
+        );
+        assert_eq!(
+            AsyncEventCode::Underflow,
+            AsyncEventCode::from_c(UHD_ASYNC_METADATA_EVENT_CODE_UNDERFLOW)
+        );
+        assert_eq!(
+            AsyncEventCode::SequenceErrorInBurst,
+            AsyncEventCode::from_c(UHD_ASYNC_METADATA_EVENT_CODE_SEQ_ERROR_IN_BURST)
+        );
+        assert_eq!(AsyncEventCode::Other(0), AsyncEventCode::from_c(0));
+        assert!(!AsyncEventCode::BurstAck.is_error());
+        assert!(AsyncEventCode::TimeError.is_error());
+    }
+}
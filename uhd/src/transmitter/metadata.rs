@@ -1,12 +1,18 @@
 use std::ptr;
 
-use crate::error::check_status;
+use crate::error::{check_status, Error};
 
 use crate::TimeSpec;
 
 /// Data about a transmit operation
 pub struct TransmitMetadata {
-    /// Handle to C++ object
+    time_spec: Option<TimeSpec>,
+    start_of_burst: bool,
+    end_of_burst: bool,
+    /// Handle to C++ object, or null if it has not been created yet
+    ///
+    /// The C++ object is only needed to send samples to a USRP, so it is created the first
+    /// time that this metadata is sent.
     handle: uhd_sys::uhd_tx_metadata_handle,
     /// Number of samples transmitted
     samples: usize,
@@ -31,73 +37,29 @@ impl TransmitMetadata {
         end_of_burst: bool,
         time_spec: Option<TimeSpec>,
     ) -> Self {
-        let (has_time_spec, full_secs, frac_secs) = match time_spec {
-            Some(t) => (true, t.seconds, t.fraction),
-            None => (false, 0i64, 0.0f64),
-        };
-        let mut handle: uhd_sys::uhd_tx_metadata_handle = ptr::null_mut();
-        check_status(unsafe {
-            uhd_sys::uhd_tx_metadata_make(
-                &mut handle,
-                has_time_spec,
-                full_secs,
-                frac_secs,
-                start_of_burst,
-                end_of_burst,
-            )
-        })
-        .unwrap();
-        TransmitMetadata { handle, samples: 0 }
+        TransmitMetadata {
+            time_spec,
+            start_of_burst,
+            end_of_burst,
+            handle: ptr::null_mut(),
+            samples: 0,
+        }
     }
 
     /// Returns the timestamp of (the first?) of the transmitted samples, according to the USRP's
     /// internal clock
-    #[allow(clippy::useless_conversion)]
     pub fn time_spec(&self) -> Option<TimeSpec> {
-        if self.has_time_spec() {
-            let mut time = TimeSpec::default();
-            let mut seconds_time_t: libc::time_t = Default::default();
-
-            check_status(unsafe {
-                uhd_sys::uhd_tx_metadata_time_spec(
-                    self.handle,
-                    &mut seconds_time_t,
-                    &mut time.fraction,
-                )
-            })
-            .unwrap();
-
-            // Explicitly convert seconds from time_t to i64 (some platforms `time_t` is smaller
-            // than `i64`)
-            time.seconds = seconds_time_t.into();
-            Some(time)
-        } else {
-            None
-        }
-    }
-
-    /// Returns true if this metadata object has a time
-    fn has_time_spec(&self) -> bool {
-        let mut has = false;
-        check_status(unsafe { uhd_sys::uhd_tx_metadata_has_time_spec(self.handle, &mut has) })
-            .unwrap();
-        has
+        self.time_spec
     }
 
     /// Returns true if the transmitted samples are at the beginning of a burst
     pub fn start_of_burst(&self) -> bool {
-        let mut value = false;
-        check_status(unsafe { uhd_sys::uhd_tx_metadata_start_of_burst(self.handle, &mut value) })
-            .unwrap();
-        value
+        self.start_of_burst
     }
 
     /// Returns true if the transmitted samples are at the end of a burst
     pub fn end_of_burst(&self) -> bool {
-        let mut value = false;
-        check_status(unsafe { uhd_sys::uhd_tx_metadata_end_of_burst(self.handle, &mut value) })
-            .unwrap();
-        value
+        self.end_of_burst
     }
 
     /// Returns the number of samples transmitted
@@ -110,44 +72,44 @@ impl TransmitMetadata {
         self.samples = samples
     }
 
-    pub(crate) fn handle_mut(&mut self) -> &mut uhd_sys::uhd_tx_metadata_handle {
-        &mut self.handle
+    /// Returns the handle to the C++ object, creating the object if it does not exist
+    pub(crate) fn handle_mut(&mut self) -> Result<&mut uhd_sys::uhd_tx_metadata_handle, Error> {
+        if self.handle.is_null() {
+            let (has_time_spec, full_secs, frac_secs) = match self.time_spec {
+                Some(time) => (true, time.seconds, time.fraction),
+                None => (false, 0, 0.0),
+            };
+            check_status(unsafe {
+                uhd_sys::uhd_tx_metadata_make(
+                    &mut self.handle,
+                    has_time_spec,
+                    full_secs as _,
+                    frac_secs,
+                    self.start_of_burst,
+                    self.end_of_burst,
+                )
+            })?;
+        }
+        Ok(&mut self.handle)
     }
 }
 
-// Thread safety: The uhd_tx_metadata struct just stores data. All exposed functions read fields.
+// Thread safety: The uhd_tx_metadata struct just stores data, and it is only accessed through
+// a mutable reference.
 unsafe impl Send for TransmitMetadata {}
 unsafe impl Sync for TransmitMetadata {}
 
 impl Default for TransmitMetadata {
     fn default() -> Self {
-        let mut handle: uhd_sys::uhd_tx_metadata_handle = ptr::null_mut();
-
-        // not sure what to do here, need to look at docs
-        let has_time_spec = Default::default();
-        let full_secs = Default::default();
-        let frac_secs = Default::default();
-        let start_of_burst = Default::default();
-        let end_of_burst = Default::default();
-
-        check_status(unsafe {
-            uhd_sys::uhd_tx_metadata_make(
-                &mut handle,
-                has_time_spec,
-                full_secs,
-                frac_secs,
-                start_of_burst,
-                end_of_burst,
-            )
-        })
-        .unwrap();
-        TransmitMetadata { handle, samples: 0 }
+        TransmitMetadata::with_flags(false, false, None)
     }
 }
 
 impl Drop for TransmitMetadata {
     fn drop(&mut self) {
-        let _ = unsafe { uhd_sys::uhd_tx_metadata_free(&mut self.handle) };
+        if !self.handle.is_null() {
+            let _ = unsafe { uhd_sys::uhd_tx_metadata_free(&mut self.handle) };
+        }
     }
 }
 
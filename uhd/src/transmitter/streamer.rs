@@ -2,11 +2,12 @@ use std::marker::PhantomData;
 use std::os::raw::c_void;
 use std::ptr;
 
+use super::async_metadata::AsyncMetadataHandle;
 use crate::{
     error::{check_status, Error},
     usrp::Usrp,
     utils::check_equal_buffer_lengths,
-    TransmitMetadata,
+    AsyncMetadata, TransmitMetadata,
 };
 
 /// A streamer used to transmit samples from a USRP
@@ -106,12 +107,13 @@ impl<I> TransmitStreamer<'_, I> {
             *entry = buffer.as_ptr() as *mut c_void;
         }
 
+        let metadata_handle = metadata.handle_mut()?;
         check_status(unsafe {
             uhd_sys::uhd_tx_streamer_send(
                 self.handle,
                 self.buffer_pointers.as_mut_ptr(),
                 buffer_length as _,
-                metadata.handle_mut(),
+                metadata_handle,
                 timeout,
                 &mut samples_transmitted as *mut usize as *mut _,
             )
@@ -149,6 +151,32 @@ impl<I> TransmitStreamer<'_, I> {
     pub fn transmit_simple(&mut self, buffer: &mut [I]) -> Result<TransmitMetadata, Error> {
         self.transmit(&mut [buffer], 0.1)
     }
+
+    /// Receives an asynchronous message about a previous transmit operation
+    ///
+    /// Messages report underflows, sequence errors, late packets, and acknowledgements of
+    /// the end of each burst.
+    ///
+    /// `timeout`: the maximum time to wait for a message, in seconds.
+    ///
+    /// Returns `Ok(None)` if no message arrived before the timeout expired.
+    pub fn recv_async_msg(&mut self, timeout: f64) -> Result<Option<AsyncMetadata>, Error> {
+        let mut metadata = AsyncMetadataHandle::new()?;
+        let mut valid = false;
+        check_status(unsafe {
+            uhd_sys::uhd_tx_streamer_recv_async_msg(
+                self.handle,
+                metadata.handle_mut(),
+                timeout,
+                &mut valid,
+            )
+        })?;
+        if valid {
+            metadata.read().map(Some)
+        } else {
+            Ok(None)
+        }
+    }
 }
 
 impl<I> Drop for TransmitStreamer<'_, I> {
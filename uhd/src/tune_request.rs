@@ -1,10 +1,15 @@
 /// A request to tune a frontend
-#[derive(Debug, Clone)]
+///
+/// With the `serde` feature, a request is serialized as a map with the keys `target_frequency`,
+/// `rf`, `dsp`, and `args` (which may be omitted when deserializing).
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TuneRequest {
     pub(crate) target_frequency: f64,
     pub(crate) rf: TuneRequestPolicy,
     pub(crate) dsp: TuneRequestPolicy,
     /// Extra arguments
+    #[cfg_attr(feature = "serde", serde(default))]
     pub(crate) args: String,
 }
 
@@ -45,7 +50,12 @@ impl TuneRequest {
 }
 
 /// Policies for how tuning should be accomplished
-#[derive(Debug, Clone)]
+///
+/// With the `serde` feature, policies are serialized as `"none"`, `"auto"`, or
+/// `{"manual": frequency}`.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 pub enum TuneRequestPolicy {
     /// Keep the current value
     None,
@@ -73,3 +83,33 @@ impl TuneRequestPolicy {
         }
     }
 }
+
+#[cfg(all(test, feature = "serde"))]
+mod test {
+    use super::{TuneRequest, TuneRequestPolicy};
+
+    #[test]
+    fn serde_forms() {
+        let mut request = TuneRequest::with_frequency_lo(915e6, 2e6);
+        request.set_dsp_policy(TuneRequestPolicy::None);
+        let json = serde_json::to_value(&request).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "target_frequency": 915e6,
+                "rf": { "manual": 917e6 },
+                "dsp": "none",
+                "args": "",
+            })
+        );
+
+        let request: TuneRequest = serde_json::from_str(
+            r#"{"target_frequency": 2.4e9, "rf": "auto", "dsp": {"manual": -1e3}}"#,
+        )
+        .unwrap();
+        assert_eq!(request.target_frequency, 2.4e9);
+        assert!(matches!(request.rf, TuneRequestPolicy::Auto));
+        assert_eq!(request.dsp.frequency(), -1e3);
+        assert_eq!(request.args, "");
+    }
+}
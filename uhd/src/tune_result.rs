@@ -3,6 +3,22 @@
 pub struct TuneResult(uhd_sys::uhd_tune_result_t);
 
 impl TuneResult {
+    pub(crate) fn new(
+        target_rf_freq: f64,
+        clipped_rf_freq: f64,
+        actual_rf_freq: f64,
+        target_dsp_freq: f64,
+        actual_dsp_freq: f64,
+    ) -> Self {
+        TuneResult(uhd_sys::uhd_tune_result_t {
+            clipped_rf_freq,
+            target_rf_freq,
+            actual_rf_freq,
+            target_dsp_freq,
+            actual_dsp_freq,
+        })
+    }
+
     /// Returns the target RF frequency
     pub fn target_rf_freq(&self) -> f64 {
         self.0.target_rf_freq
@@ -64,3 +80,62 @@ mod fmt {
         }
     }
 }
+
+#[cfg(feature = "serde")]
+mod serde_impl {
+    use super::TuneResult;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    /// The serialized form of a tune result
+    #[derive(Serialize, Deserialize)]
+    #[serde(rename = "TuneResult")]
+    struct Fields {
+        target_rf_freq: f64,
+        clipped_rf_freq: f64,
+        actual_rf_freq: f64,
+        target_dsp_freq: f64,
+        actual_dsp_freq: f64,
+    }
+
+    impl Serialize for TuneResult {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            Fields {
+                target_rf_freq: self.0.target_rf_freq,
+                clipped_rf_freq: self.0.clipped_rf_freq,
+                actual_rf_freq: self.0.actual_rf_freq,
+                target_dsp_freq: self.0.target_dsp_freq,
+                actual_dsp_freq: self.0.actual_dsp_freq,
+            }
+            .serialize(serializer)
+        }
+    }
+
+    impl<'de> Deserialize<'de> for TuneResult {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let fields = Fields::deserialize(deserializer)?;
+            Ok(TuneResult::new(
+                fields.target_rf_freq,
+                fields.clipped_rf_freq,
+                fields.actual_rf_freq,
+                fields.target_dsp_freq,
+                fields.actual_dsp_freq,
+            ))
+        }
+    }
+
+    #[cfg(test)]
+    mod test {
+        use super::TuneResult;
+
+        #[test]
+        fn round_trip() {
+            let result = TuneResult::new(100e6, 100e6, 99.99e6, 0.0, -10e3);
+            let json = serde_json::to_value(&result).unwrap();
+            assert_eq!(json["actual_rf_freq"], 99.99e6);
+            assert_eq!(json["actual_dsp_freq"], -10e3);
+            let parsed: TuneResult = serde_json::from_value(json).unwrap();
+            assert_eq!(parsed.clipped_rf_freq(), 100e6);
+            assert_eq!(parsed.actual_dsp_freq(), -10e3);
+        }
+    }
+}
@@ -1,12 +1,24 @@
 use crate::{
+    channel::{RxChannel, TxChannel},
+    device::check_device_channel,
+    device_args::{DeviceAddr, IntoDeviceArgs},
+    eeprom_edit::{DaughterBoardEepromEdit, MotherboardEepromEdit},
     error::{check_status, Error},
+    gpio::{GpioAttribute, GpioBank},
+    master_clock_rate::{
+        self, divides_evenly, ChannelDirection, MasterClockRateReport, SampleRateWarning,
+    },
     motherboard_eeprom::MotherboardEeprom,
-    range::MetaRange,
+    range::{MetaRange, MetaRangeHandle},
+    settings::ConfigReport,
+    snapshot::DeviceSnapshot,
     stream::{Item, StreamArgs, StreamArgsC},
     string_vector::StringVector,
+    subdev_spec::SubdevSpecHandle,
+    timed_commands::TimedCommands,
     utils::copy_string,
-    DaughterBoardEeprom, ReceiveInfo, ReceiveStreamer, TimeSpec, TransmitInfo, TransmitStreamer,
-    TuneRequest, TuneResult,
+    DaughterBoardEeprom, ReceiveInfo, ReceiveStreamer, SubdevSpec, TimeSpec, TransmitInfo,
+    TransmitStreamer, TuneRequest, TuneResult,
 };
 
 use std::convert::TryInto;
@@ -16,26 +28,45 @@ use std::ptr;
 pub struct Usrp(uhd_sys::uhd_usrp_handle);
 
 impl Usrp {
-    pub fn find(args: &str) -> Result<Vec<String>, Error> {
-        let args = CString::new(args)?;
+    /// A local oscillator name that refers to all local oscillators of a channel
+    pub const ALL_LOS: &'static str = "all";
+    /// A motherboard index that refers to all motherboards
+    pub const ALL_MBOARDS: usize = usize::MAX;
+
+    /// Returns the addresses of USRPs that match the provided arguments
+    ///
+    /// args: A string in the same format as for [`open`](Self::open), or a
+    /// [`DeviceArgs`](crate::DeviceArgs). Each returned address can be passed to `open`.
+    pub fn find<A>(args: A) -> Result<Vec<DeviceAddr>, Error>
+    where
+        A: IntoDeviceArgs,
+    {
+        let args = args.into_device_args()?.to_c_string()?;
         let mut addresses = StringVector::new()?;
         check_status(unsafe { uhd_sys::uhd_usrp_find(args.as_ptr(), addresses.handle_mut()) })?;
-        Ok(addresses.into())
+        Vec::<String>::from(addresses)
+            .iter()
+            .map(|address| DeviceAddr::parse(address))
+            .collect()
     }
 
     /// Opens a connection to a USRP
     ///
-    /// args: A string with parameters for the USRP connection. If this is an empty string,
-    /// one available USRP will be opened with the default settings. Arguments can be specified
-    /// with the syntax `key=value`, with key-value pairs separated by commas.
+    /// args: Parameters for the USRP connection, as a [`DeviceArgs`](crate::DeviceArgs), a
+    /// [`DeviceAddr`] from [`find`](Self::find), or a string. If the arguments are empty, one
+    /// available USRP will be opened with the default settings. In a string, arguments are
+    /// specified with the syntax `key=value`, with key-value pairs separated by commas.
     ///
     /// Frequently used arguments:
     /// * `addr`: The IP address of the USRP
     /// * `type`: The type of the USRP (allowed values include `usrp2` and others)
     ///
-    pub fn open(args: &str) -> Result<Self, Error> {
+    pub fn open<A>(args: A) -> Result<Self, Error>
+    where
+        A: IntoDeviceArgs,
+    {
         let mut handle: uhd_sys::uhd_usrp_handle = ptr::null_mut();
-        let args_c = CString::new(args)?;
+        let args_c = args.into_device_args()?.to_c_string()?;
         check_status(unsafe { uhd_sys::uhd_usrp_make(&mut handle, args_c.as_ptr()) })?;
         Ok(Usrp(handle))
     }
@@ -83,11 +114,11 @@ impl Usrp {
 
     /// Returns the supported range of receive front-end bandwidth
     pub fn get_rx_bandwidth_range(&self, channel: usize) -> Result<MetaRange, Error> {
-        let mut range = MetaRange::default();
+        let range = MetaRangeHandle::new()?;
         check_status(unsafe {
             uhd_sys::uhd_usrp_get_rx_bandwidth_range(self.0, channel as _, range.handle())
         })?;
-        Ok(range)
+        range.to_meta_range()
     }
 
     /// Returns the current transmit front-end bandwidth
@@ -101,11 +132,11 @@ impl Usrp {
 
     /// Returns the supported range of transmit front-end bandwidth
     pub fn get_tx_bandwidth_range(&self, channel: usize) -> Result<MetaRange, Error> {
-        let mut range = MetaRange::default();
+        let range = MetaRangeHandle::new()?;
         check_status(unsafe {
             uhd_sys::uhd_usrp_get_tx_bandwidth_range(self.0, channel as _, range.handle())
         })?;
-        Ok(range)
+        range.to_meta_range()
     }
 
     /// Returns the current receive frequency
@@ -117,11 +148,11 @@ impl Usrp {
 
     /// Returns the supported range of receive frequencies
     pub fn get_rx_frequency_range(&self, channel: usize) -> Result<MetaRange, Error> {
-        let mut range = MetaRange::default();
+        let range = MetaRangeHandle::new()?;
         check_status(unsafe {
             uhd_sys::uhd_usrp_get_rx_freq_range(self.0, channel as _, range.handle())
         })?;
-        Ok(range)
+        range.to_meta_range()
     }
 
     /// Returns the current transmit frequency
@@ -133,11 +164,11 @@ impl Usrp {
 
     /// Returns the supported range of transmit frequencies
     pub fn get_tx_frequency_range(&self, channel: usize) -> Result<MetaRange, Error> {
-        let mut range = MetaRange::default();
+        let range = MetaRangeHandle::new()?;
         check_status(unsafe {
             uhd_sys::uhd_usrp_get_tx_freq_range(self.0, channel as _, range.handle())
         })?;
-        Ok(range)
+        range.to_meta_range()
     }
 
     /// Returns the current gain of the gain element with the specified name
@@ -161,11 +192,11 @@ impl Usrp {
     /// Returns the range(s) of gains for a gain element
     pub fn get_rx_gain_range(&self, channel: usize, name: &str) -> Result<MetaRange, Error> {
         let name = CString::new(name)?;
-        let mut range = MetaRange::default();
+        let range = MetaRangeHandle::new()?;
         check_status(unsafe {
             uhd_sys::uhd_usrp_get_rx_gain_range(self.0, name.as_ptr(), channel as _, range.handle())
         })?;
-        Ok(range)
+        range.to_meta_range()
     }
 
     /// Returns the current gain of the gain element with the specified name
@@ -189,11 +220,11 @@ impl Usrp {
     /// Returns the range(s) of gains for a gain element
     pub fn get_tx_gain_range(&self, channel: usize, name: &str) -> Result<MetaRange, Error> {
         let name = CString::new(name)?;
-        let mut range = MetaRange::default();
+        let range = MetaRangeHandle::new()?;
         check_status(unsafe {
             uhd_sys::uhd_usrp_get_tx_gain_range(self.0, name.as_ptr(), channel as _, range.handle())
         })?;
-        Ok(range)
+        range.to_meta_range()
     }
 
     /// Sets the time at which subsequent timed commands will take effect
@@ -209,22 +240,33 @@ impl Usrp {
         check_status(unsafe { uhd_sys::uhd_usrp_clear_command_time(self.0, mboard as _) })
     }
 
+    /// Sets the command time and returns a guard that clears it when dropped
+    ///
+    /// Tuning and gain commands sent through the guard take effect at the provided time.
+    pub fn timed_commands(
+        &mut self,
+        time: TimeSpec,
+        mboard: usize,
+    ) -> Result<TimedCommands<'_>, Error> {
+        TimedCommands::new(self, time, mboard)
+    }
+
     /// Gets the ranges of front-end frequencies for a receive channel
     pub fn get_fe_rx_freq_range(&self, channel: usize) -> Result<MetaRange, Error> {
-        let mut range = MetaRange::default();
+        let range = MetaRangeHandle::new()?;
         check_status(unsafe {
             uhd_sys::uhd_usrp_get_fe_rx_freq_range(self.0, channel as _, range.handle())
         })?;
-        Ok(range)
+        range.to_meta_range()
     }
 
     /// Gets the ranges of front-end frequencies for a transmit channel
     pub fn get_fe_tx_freq_range(&self, channel: usize) -> Result<MetaRange, Error> {
-        let mut range = MetaRange::default();
+        let range = MetaRangeHandle::new()?;
         check_status(unsafe {
             uhd_sys::uhd_usrp_get_fe_tx_freq_range(self.0, channel as _, range.handle())
         })?;
-        Ok(range)
+        range.to_meta_range()
     }
 
     /// Returns the frequency of the master clock
@@ -236,6 +278,129 @@ impl Usrp {
         Ok(rate)
     }
 
+    /// Sets the frequency of the master clock
+    ///
+    /// mboard: The index of the motherboard, or [`Usrp::ALL_MBOARDS`] to set the rate of all
+    /// motherboards
+    ///
+    /// The UHD C API does not report the range of master clock rates that a device supports, so
+    /// this only checks that the rate is positive and finite. The device may coerce the requested
+    /// rate to a rate that it supports. The returned report contains the rate that was actually
+    /// applied (of motherboard 0 when setting all motherboards), and lists the receive and
+    /// transmit channels on the changed motherboards with sample rates that are no longer integer
+    /// divisions of the new master clock rate of their motherboard.
+    pub fn set_master_clock_rate(
+        &mut self,
+        rate: f64,
+        mboard: usize,
+    ) -> Result<MasterClockRateReport, Error> {
+        if !(rate.is_finite() && rate > 0.0) {
+            return Err(Error::Unique(format!(
+                "Invalid master clock rate {} Hz",
+                rate
+            )));
+        }
+        let num_mboards = self.get_num_motherboards()?;
+        let rx_mboards = self.channel_motherboards(ChannelDirection::Receive, num_mboards)?;
+        let tx_mboards = self.channel_motherboards(ChannelDirection::Transmit, num_mboards)?;
+        let changed =
+            |channel_mboard: &usize| mboard == Self::ALL_MBOARDS || *channel_mboard == mboard;
+        let rx_rates = rx_mboards
+            .iter()
+            .enumerate()
+            .filter(|(_, channel_mboard)| changed(channel_mboard))
+            .map(|(channel, &channel_mboard)| {
+                Ok((channel, channel_mboard, self.get_rx_sample_rate(channel)?))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        let tx_rates = tx_mboards
+            .iter()
+            .enumerate()
+            .filter(|(_, channel_mboard)| changed(channel_mboard))
+            .map(|(channel, &channel_mboard)| {
+                Ok((channel, channel_mboard, self.get_tx_sample_rate(channel)?))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        check_status(unsafe {
+            uhd_sys::uhd_usrp_set_master_clock_rate(self.0, rate, mboard as _)
+        })?;
+
+        let mboard_rates = (0..num_mboards)
+            .map(|mboard| self.get_master_clock_rate(mboard))
+            .collect::<Result<Vec<f64>, Error>>()?;
+        let actual = if mboard == Self::ALL_MBOARDS {
+            mboard_rates.first().copied()
+        } else {
+            mboard_rates.get(mboard).copied()
+        }
+        .ok_or_else(|| Error::Unique(format!("Motherboard {} does not exist", mboard)))?;
+        let mut warnings = Vec::new();
+        for (direction, previous_rates) in [
+            (ChannelDirection::Receive, rx_rates),
+            (ChannelDirection::Transmit, tx_rates),
+        ] {
+            for (channel, channel_mboard, previous_rate) in previous_rates {
+                let rate = match direction {
+                    ChannelDirection::Receive => self.get_rx_sample_rate(channel)?,
+                    ChannelDirection::Transmit => self.get_tx_sample_rate(channel)?,
+                };
+                let master_clock_rate = mboard_rates[channel_mboard];
+                if !divides_evenly(master_clock_rate, rate) {
+                    warnings.push(SampleRateWarning {
+                        direction,
+                        channel,
+                        previous_rate,
+                        rate,
+                        master_clock_rate,
+                    });
+                }
+            }
+        }
+        Ok(MasterClockRateReport {
+            requested: rate,
+            actual,
+            warnings,
+        })
+    }
+
+    /// Returns the index of the motherboard of each receive or transmit channel
+    ///
+    /// Channels are numbered in order of motherboard, and each motherboard has one channel for
+    /// each entry in its subdevice specification.
+    fn channel_motherboards(
+        &self,
+        direction: ChannelDirection,
+        num_mboards: usize,
+    ) -> Result<Vec<usize>, Error> {
+        let num_channels = match direction {
+            ChannelDirection::Receive => self.get_num_rx_channels()?,
+            ChannelDirection::Transmit => self.get_num_tx_channels()?,
+        };
+        if num_mboards <= 1 {
+            return Ok(vec![0; num_channels]);
+        }
+        let channels_per_mboard = (0..num_mboards)
+            .map(|mboard| {
+                let spec = match direction {
+                    ChannelDirection::Receive => self.get_rx_subdev_spec(mboard)?,
+                    ChannelDirection::Transmit => self.get_tx_subdev_spec(mboard)?,
+                };
+                Ok(spec.len())
+            })
+            .collect::<Result<Vec<usize>, Error>>()?;
+        let mboards = master_clock_rate::channel_motherboards(&channels_per_mboard);
+        if mboards.len() != num_channels {
+            return Err(Error::Unique(format!(
+                "The subdevice specifications list {} {} channels, but the device has {}",
+                mboards.len(),
+                direction,
+                num_channels
+            )));
+        }
+        Ok(mboards)
+    }
+
     /// Returns the name of the motherboard
     pub fn get_motherboard_name(&self, mboard: usize) -> Result<String, Error> {
         copy_string(|buffer, length| unsafe {
@@ -261,6 +426,20 @@ impl Usrp {
         Ok(value)
     }
 
+    /// Sets the transmit gain, normalized to [0, 1]
+    pub fn set_normalized_tx_gain(&mut self, gain: f64, channel: usize) -> Result<(), Error> {
+        check_status(unsafe {
+            uhd_sys::uhd_usrp_set_normalized_tx_gain(self.0, gain, channel as _)
+        })
+    }
+
+    /// Sets the receive gain, normalized to [0, 1]
+    pub fn set_normalized_rx_gain(&mut self, gain: f64, channel: usize) -> Result<(), Error> {
+        check_status(unsafe {
+            uhd_sys::uhd_usrp_set_normalized_rx_gain(self.0, gain, channel as _)
+        })
+    }
+
     /// Returns the number of motherboards that this Usrp object provides access to
     pub fn get_num_motherboards(&self) -> Result<usize, Error> {
         let mut value = 0usize;
@@ -356,7 +535,7 @@ impl Usrp {
 
     /// Returns the values stored in the motherboard EEPROM
     pub fn get_motherboard_eeprom(&self, mboard: usize) -> Result<MotherboardEeprom, Error> {
-        let mut eeprom = MotherboardEeprom::default();
+        let eeprom = MotherboardEeprom::default();
         check_status(unsafe {
             uhd_sys::uhd_usrp_get_mboard_eeprom(self.0, eeprom.handle(), mboard as _)
         })?;
@@ -377,7 +556,7 @@ impl Usrp {
         let unit = CString::new(unit)?;
         let slot = CString::new(slot)?;
 
-        let mut eeprom = DaughterBoardEeprom::default();
+        let eeprom = DaughterBoardEeprom::default();
 
         check_status(unsafe {
             uhd_sys::uhd_usrp_get_dboard_eeprom(
@@ -392,6 +571,63 @@ impl Usrp {
         Ok(eeprom)
     }
 
+    /// Writes values to the motherboard EEPROM
+    ///
+    /// Only the keys that are present in `eeprom` are written.
+    pub fn set_motherboard_eeprom(
+        &mut self,
+        eeprom: &MotherboardEeprom,
+        mboard: usize,
+    ) -> Result<(), Error> {
+        check_status(unsafe {
+            uhd_sys::uhd_usrp_set_mboard_eeprom(self.0, eeprom.handle(), mboard as _)
+        })
+    }
+
+    /// Writes values to a daughter board EEPROM
+    ///
+    /// See [`get_daughter_board_eeprom`](Self::get_daughter_board_eeprom) for the meanings of
+    /// unit and slot.
+    pub fn set_daughter_board_eeprom(
+        &mut self,
+        eeprom: &DaughterBoardEeprom,
+        unit: &str,
+        slot: &str,
+        mboard: usize,
+    ) -> Result<(), Error> {
+        let unit = CString::new(unit)?;
+        let slot = CString::new(slot)?;
+        check_status(unsafe {
+            uhd_sys::uhd_usrp_set_dboard_eeprom(
+                self.0,
+                eeprom.handle(),
+                unit.as_ptr(),
+                slot.as_ptr(),
+                mboard as _,
+            )
+        })
+    }
+
+    /// Reads the motherboard EEPROM and starts an edit that can show the differences from the
+    /// device before writing
+    pub fn edit_motherboard_eeprom(
+        &mut self,
+        mboard: usize,
+    ) -> Result<MotherboardEepromEdit<'_>, Error> {
+        MotherboardEepromEdit::new(self, mboard)
+    }
+
+    /// Reads a daughter board EEPROM and starts an edit that can show the differences from the
+    /// device before writing
+    pub fn edit_daughter_board_eeprom(
+        &mut self,
+        unit: &str,
+        slot: &str,
+        mboard: usize,
+    ) -> Result<DaughterBoardEepromEdit<'_>, Error> {
+        DaughterBoardEepromEdit::new(self, unit, slot, mboard)
+    }
+
     /// Gets information about the receive configuration of a channel
     pub fn get_rx_info(&self, channel: usize) -> Result<ReceiveInfo, Error> {
         let mut info_c = uhd_sys::uhd_usrp_rx_info_t {
@@ -440,6 +676,54 @@ impl Usrp {
         }
     }
 
+    /// Returns the subdevice specification that maps receive channels to frontends
+    pub fn get_rx_subdev_spec(&self, mboard: usize) -> Result<SubdevSpec, Error> {
+        let spec = SubdevSpecHandle::new(&SubdevSpec::new())?;
+        check_status(unsafe {
+            uhd_sys::uhd_usrp_get_rx_subdev_spec(self.0, mboard as _, spec.handle())
+        })?;
+        spec.to_spec()
+    }
+
+    /// Sets the subdevice specification that maps receive channels to frontends
+    pub fn set_rx_subdev_spec(&mut self, spec: &SubdevSpec, mboard: usize) -> Result<(), Error> {
+        let spec = SubdevSpecHandle::new(spec)?;
+        check_status(unsafe {
+            uhd_sys::uhd_usrp_set_rx_subdev_spec(self.0, spec.handle(), mboard as _)
+        })
+    }
+
+    /// Returns the name of the frontend that a receive channel is routed to
+    pub fn get_rx_subdev_name(&self, channel: usize) -> Result<String, Error> {
+        copy_string(|buffer, length| unsafe {
+            uhd_sys::uhd_usrp_get_rx_subdev_name(self.0, channel as _, buffer, length as _)
+        })
+    }
+
+    /// Returns the subdevice specification that maps transmit channels to frontends
+    pub fn get_tx_subdev_spec(&self, mboard: usize) -> Result<SubdevSpec, Error> {
+        let spec = SubdevSpecHandle::new(&SubdevSpec::new())?;
+        check_status(unsafe {
+            uhd_sys::uhd_usrp_get_tx_subdev_spec(self.0, mboard as _, spec.handle())
+        })?;
+        spec.to_spec()
+    }
+
+    /// Sets the subdevice specification that maps transmit channels to frontends
+    pub fn set_tx_subdev_spec(&mut self, spec: &SubdevSpec, mboard: usize) -> Result<(), Error> {
+        let spec = SubdevSpecHandle::new(spec)?;
+        check_status(unsafe {
+            uhd_sys::uhd_usrp_set_tx_subdev_spec(self.0, spec.handle(), mboard as _)
+        })
+    }
+
+    /// Returns the name of the frontend that a transmit channel is routed to
+    pub fn get_tx_subdev_name(&self, channel: usize) -> Result<String, Error> {
+        copy_string(|buffer, length| unsafe {
+            uhd_sys::uhd_usrp_get_tx_subdev_name(self.0, channel as _, buffer, length as _)
+        })
+    }
+
     /// Returns true if the provided local oscillator is exported
     pub fn get_rx_lo_export_enabled(&self, channel: usize, name: &str) -> Result<bool, Error> {
         let name = CString::new(name)?;
@@ -489,6 +773,92 @@ impl Usrp {
         Ok(vector.into())
     }
 
+    /// Returns the currently selected source of a receive local oscillator
+    pub fn get_rx_lo_source(&self, channel: usize, name: &str) -> Result<String, Error> {
+        let name = CString::new(name)?;
+        copy_string(|buffer, length| unsafe {
+            uhd_sys::uhd_usrp_get_rx_lo_source(
+                self.0,
+                name.as_ptr(),
+                channel as _,
+                buffer,
+                length as _,
+            )
+        })
+    }
+
+    /// Returns the sources that can be selected for a receive local oscillator
+    ///
+    /// Common sources are `internal`, `external`, and `companion`.
+    pub fn get_rx_lo_sources(&self, channel: usize, name: &str) -> Result<Vec<String>, Error> {
+        let name = CString::new(name)?;
+        let mut vector = StringVector::new()?;
+        check_status(unsafe {
+            uhd_sys::uhd_usrp_get_rx_lo_sources(
+                self.0,
+                name.as_ptr(),
+                channel as _,
+                vector.handle_mut(),
+            )
+        })?;
+        Ok(vector.into())
+    }
+
+    /// Selects the source of a receive local oscillator
+    ///
+    /// `name` may be [`Usrp::ALL_LOS`] to select the source of all local oscillators of
+    /// the channel.
+    pub fn set_rx_lo_source(
+        &mut self,
+        source: &str,
+        channel: usize,
+        name: &str,
+    ) -> Result<(), Error> {
+        let source = CString::new(source)?;
+        let name = CString::new(name)?;
+        check_status(unsafe {
+            uhd_sys::uhd_usrp_set_rx_lo_source(self.0, source.as_ptr(), name.as_ptr(), channel as _)
+        })
+    }
+
+    /// Enables or disables exporting a receive local oscillator so that other channels or
+    /// devices can import it
+    pub fn set_rx_lo_export_enabled(
+        &mut self,
+        enabled: bool,
+        channel: usize,
+        name: &str,
+    ) -> Result<(), Error> {
+        let name = CString::new(name)?;
+        check_status(unsafe {
+            uhd_sys::uhd_usrp_set_rx_lo_export_enabled(self.0, enabled, name.as_ptr(), channel as _)
+        })
+    }
+
+    /// Sets the frequency of a receive local oscillator
+    ///
+    /// On success, this function returns the frequency that the local oscillator was actually
+    /// tuned to.
+    pub fn set_rx_lo_frequency(
+        &mut self,
+        frequency: f64,
+        channel: usize,
+        name: &str,
+    ) -> Result<f64, Error> {
+        let name = CString::new(name)?;
+        let mut coerced = 0.0;
+        check_status(unsafe {
+            uhd_sys::uhd_usrp_set_rx_lo_freq(
+                self.0,
+                frequency,
+                name.as_ptr(),
+                channel as _,
+                &mut coerced,
+            )
+        })?;
+        Ok(coerced)
+    }
+
     /// Returns the names of sensors that relate to receiving
     pub fn get_rx_sensor_names(&self, channel: usize) -> Result<Vec<String>, Error> {
         let mut vector = StringVector::new()?;
@@ -527,6 +897,92 @@ impl Usrp {
         Ok(vector.into())
     }
 
+    /// Returns the currently selected source of a transmit local oscillator
+    pub fn get_tx_lo_source(&self, channel: usize, name: &str) -> Result<String, Error> {
+        let name = CString::new(name)?;
+        copy_string(|buffer, length| unsafe {
+            uhd_sys::uhd_usrp_get_tx_lo_source(
+                self.0,
+                name.as_ptr(),
+                channel as _,
+                buffer,
+                length as _,
+            )
+        })
+    }
+
+    /// Returns the sources that can be selected for a transmit local oscillator
+    ///
+    /// Common sources are `internal`, `external`, and `companion`.
+    pub fn get_tx_lo_sources(&self, channel: usize, name: &str) -> Result<Vec<String>, Error> {
+        let name = CString::new(name)?;
+        let mut vector = StringVector::new()?;
+        check_status(unsafe {
+            uhd_sys::uhd_usrp_get_tx_lo_sources(
+                self.0,
+                name.as_ptr(),
+                channel as _,
+                vector.handle_mut(),
+            )
+        })?;
+        Ok(vector.into())
+    }
+
+    /// Selects the source of a transmit local oscillator
+    ///
+    /// `name` may be [`Usrp::ALL_LOS`] to select the source of all local oscillators of
+    /// the channel.
+    pub fn set_tx_lo_source(
+        &mut self,
+        source: &str,
+        channel: usize,
+        name: &str,
+    ) -> Result<(), Error> {
+        let source = CString::new(source)?;
+        let name = CString::new(name)?;
+        check_status(unsafe {
+            uhd_sys::uhd_usrp_set_tx_lo_source(self.0, source.as_ptr(), name.as_ptr(), channel as _)
+        })
+    }
+
+    /// Enables or disables exporting a transmit local oscillator so that other channels or
+    /// devices can import it
+    pub fn set_tx_lo_export_enabled(
+        &mut self,
+        enabled: bool,
+        channel: usize,
+        name: &str,
+    ) -> Result<(), Error> {
+        let name = CString::new(name)?;
+        check_status(unsafe {
+            uhd_sys::uhd_usrp_set_tx_lo_export_enabled(self.0, enabled, name.as_ptr(), channel as _)
+        })
+    }
+
+    /// Sets the frequency of a transmit local oscillator
+    ///
+    /// On success, this function returns the frequency that the local oscillator was actually
+    /// tuned to.
+    pub fn set_tx_lo_frequency(
+        &mut self,
+        frequency: f64,
+        channel: usize,
+        name: &str,
+    ) -> Result<f64, Error> {
+        let name = CString::new(name)?;
+        let mut coerced = 0.0;
+        check_status(unsafe {
+            uhd_sys::uhd_usrp_set_tx_lo_freq(
+                self.0,
+                frequency,
+                name.as_ptr(),
+                channel as _,
+                &mut coerced,
+            )
+        })?;
+        Ok(coerced)
+    }
+
     /// Returns the names of sensors that relate to transmitting
     pub fn get_tx_sensor_names(&self, channel: usize) -> Result<Vec<String>, Error> {
         let mut vector = StringVector::new()?;
@@ -554,6 +1010,29 @@ impl Usrp {
     where
         I: Item,
     {
+        self.make_rx_stream(args)
+    }
+
+    /// Opens a receive stream and a transmit stream that can be used at the same time
+    pub fn get_rx_tx_streams<R, T>(
+        &mut self,
+        rx_args: &StreamArgs<R>,
+        tx_args: &StreamArgs<T>,
+    ) -> Result<(ReceiveStreamer<'_, R>, TransmitStreamer<'_, T>), Error>
+    where
+        R: Item,
+        T: Item,
+    {
+        let rx = self.make_rx_stream(rx_args)?;
+        let tx = self.make_tx_stream(tx_args)?;
+        Ok((rx, tx))
+    }
+
+    fn make_rx_stream<I>(&self, args: &StreamArgs<I>) -> Result<ReceiveStreamer<'_, I>, Error>
+    where
+        I: Item,
+    {
+        args.validate(self.get_num_rx_channels()?, ChannelDirection::Receive)?;
         // Convert arguments
         let args: StreamArgsC = args.try_into()?;
         // Convert some *T pointers to *mut T pointers. The C API doesn't mark them const, but
@@ -589,6 +1068,14 @@ impl Usrp {
     where
         I: Item,
     {
+        self.make_tx_stream(args)
+    }
+
+    fn make_tx_stream<I>(&self, args: &StreamArgs<I>) -> Result<TransmitStreamer<'_, I>, Error>
+    where
+        I: Item,
+    {
+        args.validate(self.get_num_tx_channels()?, ChannelDirection::Transmit)?;
         // Convert arguments
         let args: StreamArgsC = args.try_into()?;
         // Convert some *T pointers to *mut T pointers. The C API doesn't mark them const, but
@@ -625,11 +1112,11 @@ impl Usrp {
 
     /// Returns the ranges of supported sample rates
     pub fn get_rx_sample_rates(&self, channel: usize) -> Result<MetaRange, Error> {
-        let mut range = MetaRange::new();
+        let range = MetaRangeHandle::new()?;
         check_status(unsafe {
             uhd_sys::uhd_usrp_get_rx_rates(self.0, channel as _, range.handle())
         })?;
-        Ok(range)
+        range.to_meta_range()
     }
 
     /// Returns the current transmit sample rate in samples/second
@@ -641,11 +1128,11 @@ impl Usrp {
 
     /// Returns the ranges of supported sample rates
     pub fn get_tx_sample_rates(&self, channel: usize) -> Result<MetaRange, Error> {
-        let mut range = MetaRange::new();
+        let range = MetaRangeHandle::new()?;
         check_status(unsafe {
             uhd_sys::uhd_usrp_get_tx_rates(self.0, channel as _, range.handle())
         })?;
-        Ok(range)
+        range.to_meta_range()
     }
 
     /// Returns the USRP's current time. Commands can be scheduled relative to this time.
@@ -839,6 +1326,126 @@ impl Usrp {
         })?;
         Ok(banks.into())
     }
+
+    /// Sets the bits of a GPIO attribute that are set in `mask` to the corresponding bits
+    /// of `value`
+    ///
+    /// This returns an error without accessing the device if the attribute is read-only.
+    pub fn set_gpio_attr(
+        &mut self,
+        bank: &str,
+        attr: GpioAttribute,
+        value: u32,
+        mask: u32,
+        mboard: usize,
+    ) -> Result<(), Error> {
+        if !attr.is_writable() {
+            return Err(Error::Unique(format!(
+                "GPIO attribute {} is read-only",
+                attr
+            )));
+        }
+        let bank = CString::new(bank)?;
+        let attr = CString::new(attr.name())?;
+        check_status(unsafe {
+            uhd_sys::uhd_usrp_set_gpio_attr(
+                self.0,
+                bank.as_ptr(),
+                attr.as_ptr(),
+                value,
+                mask,
+                mboard as _,
+            )
+        })
+    }
+
+    /// Returns the value of a GPIO attribute
+    pub fn get_gpio_attr(
+        &self,
+        bank: &str,
+        attr: GpioAttribute,
+        mboard: usize,
+    ) -> Result<u32, Error> {
+        let bank = CString::new(bank)?;
+        let attr = CString::new(attr.name())?;
+        let mut value = 0;
+        check_status(unsafe {
+            uhd_sys::uhd_usrp_get_gpio_attr(
+                self.0,
+                bank.as_ptr(),
+                attr.as_ptr(),
+                mboard as _,
+                &mut value,
+            )
+        })?;
+        Ok(value)
+    }
+
+    /// Returns a handle to a GPIO bank
+    ///
+    /// This function returns an error if the motherboard does not have a bank with the
+    /// provided name (see [`Usrp::get_gpio_banks`]).
+    pub fn gpio_bank(&mut self, bank: &str, mboard: usize) -> Result<GpioBank<'_>, Error> {
+        let banks = self.get_gpio_banks(mboard)?;
+        if !banks.iter().any(|name| name == bank) {
+            return Err(Error::Unique(format!(
+                "Motherboard {} has no GPIO bank \"{}\" (available banks: {:?})",
+                mboard, bank, banks
+            )));
+        }
+        Ok(GpioBank::new(self, bank.to_owned(), mboard))
+    }
+
+    /// Returns a handle to a receive channel
+    ///
+    /// This function returns `Error::NoSuchChannel` if the channel does not exist.
+    pub fn rx_channel(&mut self, channel: usize) -> Result<RxChannel<'_>, Error> {
+        check_device_channel(self, channel, ChannelDirection::Receive)?;
+        Ok(RxChannel::new(self, channel))
+    }
+
+    /// Returns a handle to a transmit channel
+    ///
+    /// This function returns `Error::NoSuchChannel` if the channel does not exist.
+    pub fn tx_channel(&mut self, channel: usize) -> Result<TxChannel<'_>, Error> {
+        check_device_channel(self, channel, ChannelDirection::Transmit)?;
+        Ok(TxChannel::new(self, channel))
+    }
+
+    /// Reads the settings of all motherboards and channels
+    ///
+    /// Settings that can't be read are left out of the snapshot. An error is returned only if
+    /// the numbers of motherboards and channels can't be read.
+    pub fn snapshot(&self) -> Result<DeviceSnapshot, Error> {
+        DeviceSnapshot::from_usrp(self)
+    }
+
+    /// Applies the settings in a snapshot from [`Usrp::snapshot`]
+    ///
+    /// Each setting is read back after it is applied. The returned report lists the settings
+    /// that the device rejected or coerced to different values.
+    ///
+    /// Motherboard settings, including subdevice specifications, are restored first. An error is
+    /// returned if the device then has fewer motherboards or channels than the snapshot.
+    ///
+    /// ```no_run
+    /// use uhd::{TuneRequest, Usrp};
+    ///
+    /// let mut usrp = Usrp::open("")?;
+    /// let snapshot = usrp.snapshot()?;
+    /// usrp.set_rx_frequency(&TuneRequest::with_frequency(2.4e9), 0)?;
+    /// // ...
+    /// let report = usrp.restore(&snapshot)?;
+    /// for setting in report.settings() {
+    ///     if setting.is_coerced() || setting.error().is_some() {
+    ///         eprintln!("Not restored: {}", setting);
+    ///     }
+    /// }
+    /// # Ok::<(), uhd::Error>(())
+    /// ```
+    pub fn restore(&mut self, snapshot: &DeviceSnapshot) -> Result<ConfigReport, Error> {
+        snapshot.restore(self)
+    }
 }
 
 impl Drop for Usrp {
@@ -849,14 +1456,51 @@ impl Drop for Usrp {
 }
 
 /// A typed sensor reading.
+///
+/// With the `serde` feature, a value is serialized with its type, like `{"real": 1.5}`. Reals
+/// that are not finite are serialized as the strings `"NaN"`, `"inf"`, and `"-inf"`.
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 pub enum SensorValue {
     Boolean(bool),
     Integer(i32),
-    Real(f64),
+    Real(#[cfg_attr(feature = "serde", serde(with = "real_serde"))] f64),
     String(String),
 }
 
+/// Serializes reals that may be NaN or infinite, which JSON numbers can't represent
+#[cfg(feature = "serde")]
+mod real_serde {
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(untagged)]
+    enum Real {
+        Number(f64),
+        Text(String),
+    }
+
+    pub(super) fn serialize<S: Serializer>(value: &f64, serializer: S) -> Result<S::Ok, S::Error> {
+        if value.is_finite() {
+            Real::Number(*value)
+        } else {
+            Real::Text(value.to_string())
+        }
+        .serialize(serializer)
+    }
+
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+        match Real::deserialize(deserializer)? {
+            Real::Number(value) => Ok(value),
+            Real::Text(text) => text
+                .parse()
+                .map_err(|_| D::Error::custom(format!("invalid real sensor value {:?}", text))),
+        }
+    }
+}
+
 impl std::fmt::Display for SensorValue {
     fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -868,16 +1512,16 @@ impl std::fmt::Display for SensorValue {
     }
 }
 
-struct SensorValueHandle(uhd_sys::uhd_sensor_value_handle);
+pub(crate) struct SensorValueHandle(uhd_sys::uhd_sensor_value_handle);
 
 impl SensorValueHandle {
-    fn new() -> Result<Self, Error> {
+    pub(crate) fn new() -> Result<Self, Error> {
         let mut handle = ptr::null_mut();
         check_status(unsafe { uhd_sys::uhd_sensor_value_make(&mut handle) })?;
         Ok(Self(handle))
     }
 
-    fn value(&self) -> Result<SensorValue, Error> {
+    pub(crate) fn value(&self) -> Result<SensorValue, Error> {
         use uhd_sys::uhd_sensor_value_data_type_t as data_type;
 
         let mut sensor_type = 0;
@@ -911,6 +1555,10 @@ impl SensorValueHandle {
             ))),
         }
     }
+
+    pub(crate) fn handle_mut(&mut self) -> &mut uhd_sys::uhd_sensor_value_handle {
+        &mut self.0
+    }
 }
 
 impl Drop for SensorValueHandle {
@@ -919,6 +1567,11 @@ impl Drop for SensorValueHandle {
     }
 }
 
+// Thread safety: see https://files.ettus.com/manual/page_general.html#general_threading
+// All functions associated with the Usrp struct are thread-safe
+unsafe impl Send for Usrp {}
+unsafe impl Sync for Usrp {}
+
 #[cfg(test)]
 mod sensor_value_tests {
     use super::{SensorValue, SensorValueHandle};
@@ -928,9 +1581,33 @@ mod sensor_value_tests {
         let sensor = SensorValueHandle::new().unwrap();
         assert_eq!(sensor.value().unwrap(), SensorValue::Boolean(false));
     }
-}
 
-// Thread safety: see https://files.ettus.com/manual/page_general.html#general_threading
-// All functions associated with the Usrp struct are thread-safe
-unsafe impl Send for Usrp {}
-unsafe impl Sync for Usrp {}
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_tagged() {
+        let values = vec![
+            SensorValue::Boolean(true),
+            SensorValue::Integer(-3),
+            SensorValue::Real(1.5),
+            SensorValue::Real(f64::NEG_INFINITY),
+            SensorValue::String("locked".to_owned()),
+        ];
+        let json = serde_json::to_string(&values).unwrap();
+        assert_eq!(
+            json,
+            r#"[{"boolean":true},{"integer":-3},{"real":1.5},{"real":"-inf"},{"string":"locked"}]"#
+        );
+        assert_eq!(
+            serde_json::from_str::<Vec<SensorValue>>(&json).unwrap(),
+            values
+        );
+
+        let json = serde_json::to_string(&SensorValue::Real(f64::NAN)).unwrap();
+        assert_eq!(json, r#"{"real":"NaN"}"#);
+        match serde_json::from_str(&json).unwrap() {
+            SensorValue::Real(value) => assert!(value.is_nan()),
+            other => panic!("Unexpected value {:?}", other),
+        }
+        assert!(serde_json::from_str::<SensorValue>(r#"{"real":"locked"}"#).is_err());
+    }
+}
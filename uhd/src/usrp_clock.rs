@@ -0,0 +1,115 @@
+use crate::{
+    device_args::{DeviceAddr, IntoDeviceArgs},
+    error::{check_status, Error},
+    string_vector::StringVector,
+    usrp::SensorValueHandle,
+    utils::copy_string,
+    SensorValue,
+};
+
+use std::ffi::CString;
+use std::ptr;
+
+/// A connection to an external clock distribution device, such as an OctoClock
+///
+/// ```no_run
+/// use uhd::{SensorValue, UsrpClock};
+/// let clock = UsrpClock::open("")?;
+/// for board in 0..clock.get_num_boards()? {
+///     let locked = clock.get_sensor("gps_locked", board)?;
+///     println!("Board {} GPS locked: {}", board, locked == SensorValue::Boolean(true));
+/// }
+/// # Ok::<(), uhd::Error>(())
+/// ```
+pub struct UsrpClock(uhd_sys::uhd_usrp_clock_handle);
+
+impl UsrpClock {
+    /// Returns the addresses of clock devices that match the provided arguments
+    pub fn find<A>(args: A) -> Result<Vec<DeviceAddr>, Error>
+    where
+        A: IntoDeviceArgs,
+    {
+        let args = args.into_device_args()?.to_c_string()?;
+        let mut addresses = StringVector::new()?;
+        check_status(unsafe {
+            uhd_sys::uhd_usrp_clock_find(args.as_ptr(), *addresses.handle_mut())
+        })?;
+        Vec::<String>::from(addresses)
+            .iter()
+            .map(|address| DeviceAddr::parse(address))
+            .collect()
+    }
+
+    /// Opens a connection to a clock device
+    ///
+    /// args: Parameters for the connection, as a [`DeviceArgs`](crate::DeviceArgs), a
+    /// [`DeviceAddr`] from [`find`](Self::find), or a string with the syntax `key=value` and
+    /// key-value pairs separated by commas. If the arguments are empty, one available clock
+    /// device will be opened.
+    pub fn open<A>(args: A) -> Result<Self, Error>
+    where
+        A: IntoDeviceArgs,
+    {
+        let mut handle: uhd_sys::uhd_usrp_clock_handle = ptr::null_mut();
+        let args_c = args.into_device_args()?.to_c_string()?;
+        check_status(unsafe { uhd_sys::uhd_usrp_clock_make(&mut handle, args_c.as_ptr()) })?;
+        Ok(UsrpClock(handle))
+    }
+
+    /// Returns a human-readable description of the clock device
+    pub fn get_pp_string(&self) -> Result<String, Error> {
+        copy_string(|buffer, length| unsafe {
+            uhd_sys::uhd_usrp_clock_get_pp_string(self.0, buffer, length as _)
+        })
+    }
+
+    /// Returns the number of boards that this UsrpClock object provides access to
+    pub fn get_num_boards(&self) -> Result<usize, Error> {
+        let mut value = 0usize;
+        check_status(unsafe {
+            uhd_sys::uhd_usrp_clock_get_num_boards(self.0, &mut value as *mut usize as *mut _)
+        })?;
+        Ok(value)
+    }
+
+    /// Returns the time reported by a board's GPSDO, in whole seconds since the Unix epoch
+    pub fn get_time(&self, board: usize) -> Result<u32, Error> {
+        let mut time = 0;
+        check_status(unsafe { uhd_sys::uhd_usrp_clock_get_time(self.0, board as _, &mut time) })?;
+        Ok(time)
+    }
+
+    /// Returns the available sensors on a board
+    pub fn get_sensor_names(&self, board: usize) -> Result<Vec<String>, Error> {
+        let mut vector = StringVector::new()?;
+        check_status(unsafe {
+            uhd_sys::uhd_usrp_clock_get_sensor_names(self.0, board as _, vector.handle_mut())
+        })?;
+        Ok(vector.into())
+    }
+
+    /// Returns the typed value of a board sensor
+    pub fn get_sensor(&self, name: &str, board: usize) -> Result<SensorValue, Error> {
+        let name = CString::new(name)?;
+        let mut sensor = SensorValueHandle::new()?;
+        check_status(unsafe {
+            uhd_sys::uhd_usrp_clock_get_sensor(
+                self.0,
+                name.as_ptr(),
+                board as _,
+                sensor.handle_mut(),
+            )
+        })?;
+        sensor.value()
+    }
+}
+
+impl Drop for UsrpClock {
+    fn drop(&mut self) {
+        let _ = unsafe { uhd_sys::uhd_usrp_clock_free(&mut self.0) };
+    }
+}
+
+// Thread safety: As with Usrp, all functions of the underlying multi_usrp_clock are thread-safe
+unsafe impl Send for UsrpClock {}
+unsafe impl Sync for UsrpClock {}
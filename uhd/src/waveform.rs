@@ -0,0 +1,231 @@
+//! Synthesized test waveforms
+
+use std::f64::consts::PI;
+
+use num_complex::Complex64;
+
+use crate::stream::Sample;
+
+/// A waveform that [`WaveformGenerator`] can produce
+///
+/// Frequencies are baseband offsets in hertz, and can be negative.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Waveform {
+    /// A complex sinusoid
+    Sine { frequency: f64 },
+    /// Square waves on I and Q, with Q a quarter period behind I
+    Square { frequency: f64 },
+    /// A constant value on I
+    Constant,
+    /// A complex sinusoid that sweeps linearly from `start` to `stop` hertz over `period`
+    /// seconds, and then starts again
+    Chirp { start: f64, stop: f64, period: f64 },
+    /// Complex Gaussian noise, with the amplitude as its RMS value
+    Noise,
+}
+
+/// Generates samples of a waveform
+///
+/// The generator is an infinite iterator of samples.
+#[derive(Debug, Clone)]
+pub struct WaveformGenerator {
+    waveform: Waveform,
+    sample_rate: f64,
+    amplitude: f64,
+    /// The phase of periodic waveforms, in cycles (0 to 1)
+    phase: f64,
+    /// The time since the start of the current chirp, seconds
+    chirp_time: f64,
+    rng: Rng,
+}
+
+impl WaveformGenerator {
+    /// Creates a generator with a sample rate in samples/second and an amplitude
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the sample rate is not a finite positive number, or if the
+    /// waveform is a chirp with a period that is not a finite positive number.
+    pub fn new(waveform: Waveform, sample_rate: f64, amplitude: f64) -> Self {
+        assert!(
+            sample_rate.is_finite() && sample_rate > 0.0,
+            "Sample rate must be positive"
+        );
+        if let Waveform::Chirp { period, .. } = waveform {
+            assert!(
+                period.is_finite() && period > 0.0,
+                "Chirp period must be positive"
+            );
+        }
+        WaveformGenerator {
+            waveform,
+            sample_rate,
+            amplitude,
+            phase: 0.0,
+            chirp_time: 0.0,
+            rng: Rng::new(0),
+        }
+    }
+
+    /// Fills a buffer with the next samples
+    pub fn fill<I>(&mut self, buffer: &mut [I])
+    where
+        I: Sample,
+    {
+        for (item, sample) in buffer.iter_mut().zip(self.by_ref()) {
+            *item = I::from_fc64(sample);
+        }
+    }
+
+    /// Advances the phase by one sample at a frequency
+    fn advance(&mut self, frequency: f64) {
+        self.phase = (self.phase + frequency / self.sample_rate).rem_euclid(1.0);
+    }
+}
+
+impl Iterator for WaveformGenerator {
+    type Item = Complex64;
+
+    fn next(&mut self) -> Option<Complex64> {
+        let sample = match self.waveform {
+            Waveform::Sine { frequency } => {
+                let sample = Complex64::from_polar(self.amplitude, 2.0 * PI * self.phase);
+                self.advance(frequency);
+                sample
+            }
+            Waveform::Square { frequency } => {
+                let level = |phase: f64| {
+                    if phase.rem_euclid(1.0) < 0.5 {
+                        self.amplitude
+                    } else {
+                        -self.amplitude
+                    }
+                };
+                let sample = Complex64::new(level(self.phase), level(self.phase - 0.25));
+                self.advance(frequency);
+                sample
+            }
+            Waveform::Constant => Complex64::new(self.amplitude, 0.0),
+            Waveform::Chirp {
+                start,
+                stop,
+                period,
+            } => {
+                let sample = Complex64::from_polar(self.amplitude, 2.0 * PI * self.phase);
+                let frequency = start + (stop - start) * self.chirp_time / period;
+                self.advance(frequency);
+                self.chirp_time += 1.0 / self.sample_rate;
+                if self.chirp_time >= period {
+                    self.chirp_time -= period;
+                }
+                sample
+            }
+            Waveform::Noise => self.rng.complex_gaussian(self.amplitude),
+        };
+        Some(sample)
+    }
+}
+
+/// A small pseudorandom number generator (xorshift64*) for noise
+///
+/// This is not suitable for anything other than test signals.
+#[derive(Debug, Clone)]
+pub(crate) struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        // The state must not be zero
+        Rng(seed ^ 0x9E37_79B9_7F4A_7C15)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    /// Returns a uniformly distributed value in the range (0, 1]
+    fn next_f64(&mut self) -> f64 {
+        ((self.next_u64() >> 11) + 1) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Returns a complex Gaussian value with the provided RMS amplitude
+    pub fn complex_gaussian(&mut self, amplitude: f64) -> Complex64 {
+        // Box-Muller transform
+        let radius = (-self.next_f64().ln()).sqrt();
+        let angle = 2.0 * PI * self.next_f64();
+        Complex64::from_polar(amplitude * radius, angle)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use num_complex::{Complex, Complex64};
+
+    use super::{Rng, Waveform, WaveformGenerator};
+
+    fn close(a: Complex64, b: Complex64) -> bool {
+        (a - b).norm() < 1e-9
+    }
+
+    #[test]
+    fn sine_and_square() {
+        let sine = WaveformGenerator::new(Waveform::Sine { frequency: -250.0 }, 1000.0, 0.5)
+            .take(3)
+            .collect::<Vec<_>>();
+        assert!(close(sine[0], Complex64::new(0.5, 0.0)));
+        assert!(close(sine[1], Complex64::new(0.0, -0.5)));
+        assert!(close(sine[2], Complex64::new(-0.5, 0.0)));
+
+        let square = WaveformGenerator::new(Waveform::Square { frequency: 250.0 }, 1000.0, 1.0)
+            .take(4)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            square,
+            vec![
+                Complex64::new(1.0, -1.0),
+                Complex64::new(1.0, 1.0),
+                Complex64::new(-1.0, 1.0),
+                Complex64::new(-1.0, -1.0)
+            ]
+        );
+
+        let mut buffer = [Complex::<i16>::default(); 2];
+        WaveformGenerator::new(Waveform::Constant, 1.0, 0.5).fill(&mut buffer);
+        assert_eq!(buffer, [Complex::new(16384, 0); 2]);
+    }
+
+    #[test]
+    fn chirp_sweeps_and_repeats() {
+        let rate = 1000.0;
+        let mut chirp = WaveformGenerator::new(
+            Waveform::Chirp {
+                start: 0.0,
+                stop: 100.0,
+                period: 0.5,
+            },
+            rate,
+            1.0,
+        );
+        let samples = chirp.by_ref().take(1000).collect::<Vec<_>>();
+        // The phase difference between samples is the instantaneous frequency
+        let frequency =
+            |i: usize| (samples[i + 1] / samples[i]).arg() * rate / std::f64::consts::TAU;
+        assert!(frequency(0).abs() < 1e-6);
+        assert!((frequency(250) - 50.0).abs() < 1e-6);
+        assert!((frequency(499) - 99.8).abs() < 1e-6);
+        assert!(frequency(500).abs() < 1e-6);
+    }
+
+    #[test]
+    fn noise_power() {
+        let mut rng = Rng::new(7);
+        let count = 100_000;
+        let power = (0..count)
+            .map(|_| rng.complex_gaussian(0.1).norm_sqr())
+            .sum::<f64>()
+            / count as f64;
+        assert!((power - 0.01).abs() < 0.0005, "power {}", power);
+    }
+}